@@ -0,0 +1,220 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::bail;
+use axum::extract::State;
+use axum::http::{header, HeaderValue, Method, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tower_http::cors::CorsLayer;
+
+use crate::router::{Ctx, Router};
+use crate::utils::fs::read_json;
+
+/// Tokens shorter than this are too easy to guess
+const MIN_TOKEN_LEN: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiConfig {
+  #[serde(default)]
+  pub enabled: bool,
+  #[serde(default = "default_port")]
+  pub port: u16,
+  /// Sent as `Authorization: Bearer <token>`, or `?token=<token>` where
+  /// headers can't be set (e.g. browser WebSockets)
+  pub token: String,
+  /// Origins allowed to call the API from a browser, none by default
+  #[serde(default)]
+  pub cors_origins: Vec<String>,
+}
+
+fn default_port() -> u16 { 9417 }
+
+impl ApiConfig {
+  /// A missing file leaves the API disabled
+  pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+    let config: Option<Self> = read_json(path)?;
+    if let Some(config) = &config {
+      config.validate()?;
+    }
+
+    Ok(config)
+  }
+
+  fn validate(&self) -> anyhow::Result<()> {
+    if self.token.chars().count() < MIN_TOKEN_LEN {
+      bail!("The API token must be at least {MIN_TOKEN_LEN} characters");
+    }
+
+    for origin in &self.cors_origins {
+      HeaderValue::from_str(origin)?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Serves the rspc router on localhost: queries and mutations over HTTP at
+/// `/rspc/<procedure>`, subscriptions over the WebSocket at `/rspc/ws`
+pub fn spawn(router: Arc<Router>, ctx: Ctx, config: Option<ApiConfig>) -> Option<JoinHandle<()>> {
+  let config = config.filter(|config| config.enabled)?;
+  let address = SocketAddr::from((Ipv4Addr::LOCALHOST, config.port));
+
+  println!("[Api] listening on http://{}", address);
+
+  let app = protect(axum::Router::new().nest("/rspc", router.endpoint(move || ctx.clone()).axum()), &config);
+
+  Some(tokio::spawn(async move {
+    if let Err(err) = axum::Server::bind(&address).serve(app.into_make_service()).await {
+      println!("[Api] Error: {:?}", err);
+    }
+  }))
+}
+
+/// Puts the token check, and CORS when configured, in front of `app`
+fn protect(app: axum::Router, config: &ApiConfig) -> axum::Router {
+  let app = app.layer(middleware::from_fn_with_state(Arc::<str>::from(config.token.as_str()), authenticate));
+
+  if config.cors_origins.is_empty() {
+    return app;
+  }
+
+  // Preflight requests carry no credentials, so CORS goes outside the check
+  app.layer(
+    CorsLayer::new()
+      .allow_origin(
+        config
+          .cors_origins
+          .iter()
+          .filter_map(|origin| HeaderValue::from_str(origin).ok())
+          .collect::<Vec<_>>(),
+      )
+      .allow_methods([Method::GET, Method::POST])
+      .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]),
+  )
+}
+
+async fn authenticate<B>(State(token): State<Arc<str>>, request: Request<B>, next: Next<B>) -> Response {
+  let bearer = request
+    .headers()
+    .get(header::AUTHORIZATION)
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| value.strip_prefix("Bearer "));
+  let query = request.uri().query().and_then(|query| {
+    query
+      .split('&')
+      .find_map(|pair| pair.strip_prefix("token="))
+  });
+
+  let authorized = [bearer, query]
+    .into_iter()
+    .flatten()
+    .any(|candidate| constant_time_eq(candidate.as_bytes(), token.as_bytes()));
+
+  if !authorized {
+    return (StatusCode::UNAUTHORIZED, "Missing or invalid token").into_response();
+  }
+
+  next.run(request).await
+}
+
+/// Doesn't return early on the first mismatch, so response times don't leak
+/// how much of the token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+  use axum::body::Body;
+  use axum::routing::get;
+  use tower::ServiceExt;
+
+  use super::*;
+
+  const TOKEN: &str = "0123456789abcdef";
+
+  fn config(cors_origins: &[&str]) -> ApiConfig {
+    ApiConfig {
+      enabled: true,
+      port: default_port(),
+      token: TOKEN.into(),
+      cors_origins: cors_origins.iter().map(|origin| origin.to_string()).collect(),
+    }
+  }
+
+  fn app(config: &ApiConfig) -> axum::Router { protect(axum::Router::new().route("/ping", get(|| async { "pong" })), config) }
+
+  async fn status(app: axum::Router, request: Request<Body>) -> StatusCode { app.oneshot(request).await.unwrap().status() }
+
+  #[tokio::test]
+  async fn requires_the_token() {
+    let app = app(&config(&[]));
+    let request = |uri: &str, authorization: Option<&str>| {
+      let mut request = Request::get(uri);
+      if let Some(authorization) = authorization {
+        request = request.header(header::AUTHORIZATION, authorization);
+      }
+      request.body(Body::empty()).unwrap()
+    };
+
+    let cases = [
+      (request("/ping", None), StatusCode::UNAUTHORIZED),
+      (request("/ping", Some("Bearer nope")), StatusCode::UNAUTHORIZED),
+      (request("/ping", Some(TOKEN)), StatusCode::UNAUTHORIZED),
+      (request("/ping?token=0123456789abcde", None), StatusCode::UNAUTHORIZED),
+      (request(&format!("/ping?token={TOKEN}"), None), StatusCode::OK),
+      (request(&format!("/ping?input=1&token={TOKEN}"), None), StatusCode::OK),
+      (request("/ping", Some(&format!("Bearer {TOKEN}"))), StatusCode::OK),
+    ];
+
+    for (request, expected) in cases {
+      let uri = request.uri().clone();
+      assert_eq!(status(app.clone(), request).await, expected, "{uri}");
+    }
+  }
+
+  #[tokio::test]
+  async fn only_answers_cors_for_configured_origins() {
+    let preflight = |origin: &str| {
+      Request::builder()
+        .method(Method::OPTIONS)
+        .uri("/ping")
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+        .body(Body::empty())
+        .unwrap()
+    };
+
+    let response = app(&config(&[])).oneshot(preflight("http://localhost:3000")).await.unwrap();
+    assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+    let app = app(&config(&["http://localhost:3000"]));
+    let response = app.clone().oneshot(preflight("http://localhost:3000")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:3000");
+
+    let response = app.oneshot(preflight("http://evil.example")).await.unwrap();
+    assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+  }
+
+  #[test]
+  fn rejects_short_tokens() {
+    let mut config = config(&[]);
+    assert!(config.validate().is_ok());
+
+    config.token = "short".into();
+    assert!(config.validate().is_err());
+  }
+
+  #[test]
+  fn compares_tokens() {
+    assert!(constant_time_eq(b"abc", b"abc"));
+    assert!(!constant_time_eq(b"abc", b"abd"));
+    assert!(!constant_time_eq(b"abc", b"abcd"));
+  }
+}
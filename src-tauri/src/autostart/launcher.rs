@@ -0,0 +1,277 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::bail;
+
+/// Passed by the launchers, so we know the session just started
+pub const AUTOSTART_ARG: &str = "--autostart";
+
+/// Name of the entry in each launcher
+const NAME: &str = "media-widget";
+
+/// Starts the widget with the desktop session, however the OS does it
+pub trait Launcher: Send + Sync {
+  fn install(&self) -> anyhow::Result<()>;
+  fn remove(&self) -> anyhow::Result<()>;
+  fn is_installed(&self) -> bool;
+}
+
+/// The launcher for this OS, starting `exe`. Only LaunchAgents are named
+/// after the app's `identifier`.
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+pub fn system_launcher(identifier: &str, exe: PathBuf) -> anyhow::Result<Arc<dyn Launcher>> {
+  #[cfg(target_os = "windows")]
+  return Ok(Arc::new(RunKey::new(exe)));
+
+  #[cfg(target_os = "macos")]
+  return Ok(Arc::new(LaunchAgent::new(home()?.join("Library/LaunchAgents"), identifier, exe)));
+
+  #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+  return Ok(Arc::new(XdgAutostart::from_env(exe)?));
+}
+
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+fn home() -> anyhow::Result<PathBuf> {
+  match std::env::var_os("HOME") {
+    Some(home) if !home.is_empty() => Ok(PathBuf::from(home)),
+    _ => bail!("Unable to resolve the home directory"),
+  }
+}
+
+/// A value under `HKCU\...\Run`, set through `reg.exe`
+#[cfg(target_os = "windows")]
+pub struct RunKey {
+  exe: PathBuf,
+}
+
+#[cfg(target_os = "windows")]
+impl RunKey {
+  const KEY: &'static str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+  pub fn new(exe: PathBuf) -> Self { Self { exe } }
+
+  fn reg(args: &[&str]) -> std::io::Result<std::process::Output> {
+    use std::os::windows::process::CommandExt;
+
+    // Keeps a console from flashing up
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    std::process::Command::new("reg").args(args).creation_flags(CREATE_NO_WINDOW).output()
+  }
+}
+
+#[cfg(target_os = "windows")]
+impl Launcher for RunKey {
+  fn install(&self) -> anyhow::Result<()> {
+    let command = format!("\"{}\" {AUTOSTART_ARG}", self.exe.display());
+    let output = Self::reg(&["add", Self::KEY, "/v", NAME, "/t", "REG_SZ", "/d", &command, "/f"])?;
+    if !output.status.success() {
+      bail!("reg add failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(())
+  }
+
+  fn remove(&self) -> anyhow::Result<()> {
+    if !self.is_installed() {
+      return Ok(());
+    }
+
+    let output = Self::reg(&["delete", Self::KEY, "/v", NAME, "/f"])?;
+    if !output.status.success() {
+      bail!("reg delete failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(())
+  }
+
+  fn is_installed(&self) -> bool {
+    Self::reg(&["query", Self::KEY, "/v", NAME]).is_ok_and(|output| output.status.success())
+  }
+}
+
+/// A LaunchAgent plist in `~/Library/LaunchAgents`, loaded at login
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub struct LaunchAgent {
+  path: PathBuf,
+  label: String,
+  exe: PathBuf,
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+impl LaunchAgent {
+  pub fn new(dir: PathBuf, identifier: &str, exe: PathBuf) -> Self {
+    Self {
+      path: dir.join(format!("{identifier}.plist")),
+      label: identifier.into(),
+      exe,
+    }
+  }
+
+  fn plist(&self) -> String {
+    format!(
+      r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>Label</key>
+  <string>{}</string>
+  <key>ProgramArguments</key>
+  <array>
+    <string>{}</string>
+    <string>{AUTOSTART_ARG}</string>
+  </array>
+  <key>RunAtLoad</key>
+  <true/>
+</dict>
+</plist>
+"#,
+      escape_xml(&self.label),
+      escape_xml(&self.exe.to_string_lossy()),
+    )
+  }
+}
+
+impl Launcher for LaunchAgent {
+  fn install(&self) -> anyhow::Result<()> { write_entry(&self.path, &self.plist()) }
+
+  fn remove(&self) -> anyhow::Result<()> { remove_entry(&self.path) }
+
+  fn is_installed(&self) -> bool { self.path.exists() }
+}
+
+/// A desktop entry in `$XDG_CONFIG_HOME/autostart`, per the XDG Autostart spec
+#[cfg_attr(any(target_os = "windows", target_os = "macos"), allow(dead_code))]
+pub struct XdgAutostart {
+  path: PathBuf,
+  exe: PathBuf,
+}
+
+#[cfg_attr(any(target_os = "windows", target_os = "macos"), allow(dead_code))]
+impl XdgAutostart {
+  pub fn new(config_home: &Path, exe: PathBuf) -> Self {
+    Self {
+      path: config_home.join("autostart").join(format!("{NAME}.desktop")),
+      exe,
+    }
+  }
+
+  pub fn from_env(exe: PathBuf) -> anyhow::Result<Self> {
+    let config_home = config_home(std::env::var_os("XDG_CONFIG_HOME"), home())?;
+    Ok(Self::new(&config_home, exe))
+  }
+
+  pub fn path(&self) -> &Path { &self.path }
+
+  fn desktop_entry(&self) -> String {
+    format!(
+      "[Desktop Entry]\nType=Application\nName={NAME}\nExec={} {AUTOSTART_ARG}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+      quote_exec(&self.exe.to_string_lossy())
+    )
+  }
+}
+
+impl Launcher for XdgAutostart {
+  fn install(&self) -> anyhow::Result<()> { write_entry(&self.path, &self.desktop_entry()) }
+
+  fn remove(&self) -> anyhow::Result<()> { remove_entry(&self.path) }
+
+  fn is_installed(&self) -> bool { self.path.exists() }
+}
+
+/// `$XDG_CONFIG_HOME`, or `~/.config` when it isn't set. Relative paths are
+/// invalid per the spec and ignored as well.
+#[cfg_attr(any(target_os = "windows", target_os = "macos"), allow(dead_code))]
+fn config_home(xdg_config_home: Option<OsString>, home: anyhow::Result<PathBuf>) -> anyhow::Result<PathBuf> {
+  match xdg_config_home {
+    Some(dir) if Path::new(&dir).is_absolute() => Ok(PathBuf::from(dir)),
+    _ => Ok(home?.join(".config")),
+  }
+}
+
+fn write_entry(path: &Path, contents: &str) -> anyhow::Result<()> {
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir)?;
+  }
+
+  std::fs::write(path, contents)?;
+  Ok(())
+}
+
+fn remove_entry(path: &Path) -> anyhow::Result<()> {
+  match std::fs::remove_file(path) {
+    Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+    _ => Ok(()),
+  }
+}
+
+/// Quotes an `Exec` argument, these are the characters the spec wants
+/// escaped inside quotes
+fn quote_exec(arg: &str) -> String {
+  let mut quoted = String::from('"');
+  for char in arg.chars() {
+    if matches!(char, '"' | '`' | '$' | '\\') {
+      quoted.push('\\');
+    }
+    quoted.push(char);
+  }
+  quoted.push('"');
+
+  // Desktop entries unescape `\\` once more before splitting the command
+  quoted.replace('\\', "\\\\")
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn escape_xml(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("media-widget-autostart-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+  }
+
+  #[test]
+  fn installs_a_desktop_entry() {
+    let config_home = temp_dir("xdg");
+
+    let launcher = XdgAutostart::new(&config_home, PathBuf::from("/opt/media widget/media-widget"));
+    assert_eq!(launcher.path(), config_home.join("autostart/media-widget.desktop"));
+    assert!(!launcher.is_installed());
+
+    launcher.install().unwrap();
+    assert!(launcher.is_installed());
+    assert!(std::fs::read_to_string(launcher.path())
+      .unwrap()
+      .contains("\nExec=\"/opt/media widget/media-widget\" --autostart\n"));
+
+    launcher.remove().unwrap();
+    assert!(!launcher.is_installed());
+    // Already gone
+    launcher.remove().unwrap();
+
+    std::fs::remove_dir_all(&config_home).unwrap();
+  }
+
+  #[test]
+  fn finds_the_config_home() {
+    let home = || Ok(PathBuf::from("/home/a"));
+
+    assert_eq!(config_home(Some("/xdg".into()), home()).unwrap(), PathBuf::from("/xdg"));
+    assert_eq!(config_home(Some("relative".into()), home()).unwrap(), PathBuf::from("/home/a/.config"));
+    assert_eq!(config_home(None, home()).unwrap(), PathBuf::from("/home/a/.config"));
+    assert!(config_home(None, Err(anyhow::anyhow!("no home"))).is_err());
+  }
+
+  #[test]
+  fn quotes_exec_arguments() {
+    assert_eq!(quote_exec("/usr/bin/media-widget"), r#""/usr/bin/media-widget""#);
+    assert_eq!(quote_exec(r#"/home/a "b"/$c"#), r#""/home/a \\"b\\"/\\$c""#);
+  }
+}
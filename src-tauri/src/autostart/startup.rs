@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+
+use super::launcher::Launcher;
+use crate::media::lib::MediaEvent;
+use crate::settings::schema::Settings;
+
+/// Installs or removes the launcher as `settings.autostart` says. It's
+/// installed again on every start so it follows the app when it moves.
+pub fn follow_settings(mut settings: watch::Receiver<Settings>, launcher: Arc<dyn Launcher>) -> JoinHandle<()> {
+  tokio::spawn(async move {
+    let mut enabled = settings.borrow_and_update().autostart;
+    apply(launcher.as_ref(), enabled);
+
+    while settings.changed().await.is_ok() {
+      let autostart = settings.borrow_and_update().autostart;
+      if autostart != enabled {
+        enabled = autostart;
+        apply(launcher.as_ref(), enabled);
+      }
+    }
+  })
+}
+
+fn apply(launcher: &dyn Launcher, enabled: bool) {
+  let result = match enabled {
+    true => launcher.install(),
+    false if launcher.is_installed() => launcher.remove(),
+    false => Ok(()),
+  };
+
+  match result {
+    Ok(()) => println!("[Autostart] {}", if enabled { "installed" } else { "removed" }),
+    Err(err) => println!("[Autostart] Error: {:?}", err),
+  }
+}
+
+/// Waits until the player shows up. Started with the session, the widget
+/// stays out of the way until there's something to show.
+pub async fn player_found(events: &mut broadcast::Receiver<MediaEvent>) -> bool {
+  loop {
+    match events.recv().await {
+      Ok(MediaEvent::Connect(_)) => return true,
+      Ok(_) | Err(RecvError::Lagged(_)) => continue,
+      Err(RecvError::Closed) => return false,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Mutex;
+
+  use super::*;
+  use crate::media::lib::MediaVolumeData;
+  use crate::utils::wait::until;
+
+  #[derive(Default)]
+  struct RecordingLauncher {
+    installed: Mutex<bool>,
+    calls: Mutex<Vec<&'static str>>,
+  }
+
+  impl Launcher for RecordingLauncher {
+    fn install(&self) -> anyhow::Result<()> {
+      *self.installed.lock().unwrap() = true;
+      self.calls.lock().unwrap().push("install");
+      Ok(())
+    }
+
+    fn remove(&self) -> anyhow::Result<()> {
+      *self.installed.lock().unwrap() = false;
+      self.calls.lock().unwrap().push("remove");
+      Ok(())
+    }
+
+    fn is_installed(&self) -> bool { *self.installed.lock().unwrap() }
+  }
+
+  #[tokio::test]
+  async fn follows_the_toggle() {
+    let launcher = Arc::new(RecordingLauncher::default());
+    let (settings, receiver) = watch::channel(Settings::default());
+    follow_settings(receiver, launcher.clone());
+    let calls = || launcher.calls.lock().unwrap().clone();
+
+    // Nothing to remove at first, so installing is the first call
+    settings.send_modify(|settings| settings.autostart = true);
+    until(|| !calls().is_empty()).await;
+    assert_eq!(calls(), vec!["install"]);
+
+    settings.send_modify(|settings| settings.window.width = 400);
+    settings.send_modify(|settings| settings.autostart = false);
+    until(|| calls().len() > 1).await;
+
+    assert_eq!(calls(), vec!["install", "remove"]);
+  }
+
+  #[tokio::test]
+  async fn waits_for_the_player() {
+    let (sender, mut events) = broadcast::channel(16);
+    sender.send(MediaEvent::VolumeChanged(MediaVolumeData::fixture(0.5))).unwrap();
+    sender.send(MediaEvent::Connect("Spotify.exe".into())).unwrap();
+    assert!(player_found(&mut events).await);
+
+    drop(sender);
+    assert!(!player_found(&mut events).await);
+  }
+}
@@ -0,0 +1,252 @@
+use std::io::Write;
+
+use anyhow::bail;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use super::protocol::{format_position, parse_position, parse_volume, Command, Reply, Request, Response, Status};
+use super::server::endpoint;
+
+const USAGE: &str = "Usage: media-widget ctl <command> [--json]
+
+Commands:
+  play              Resume playback
+  pause             Pause playback
+  next              Skip to the next track
+  prev              Go back to the previous track
+  seek <position>   Jump to a position, e.g. 1:23 or 83
+  volume <level>    Set the volume, e.g. 40%
+  status            Print the current track
+  watch             Print the current track after every change";
+
+/// Runs `media-widget ctl ...`, returning the exit code
+pub async fn run(args: &[String]) -> i32 {
+  attach_console();
+
+  let (command, json) = match parse_args(args) {
+    Ok(parsed) => parsed,
+    Err(message) => {
+      eprintln!("{message}\n\n{USAGE}");
+      return 2;
+    },
+  };
+
+  let stream = match connect().await {
+    Ok(stream) => stream,
+    Err(err) => {
+      eprintln!("Unable to reach media-widget at {:?}, is it running? ({err})", endpoint());
+      return 1;
+    },
+  };
+
+  match exchange(stream, command, json, &mut std::io::stdout()).await {
+    Ok(()) => 0,
+    Err(err) => {
+      eprintln!("{err}");
+      1
+    },
+  }
+}
+
+/// Release builds use the windows subsystem, which has no console to print
+/// to unless we borrow the one we were started from
+#[cfg(windows)]
+fn attach_console() {
+  use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+  unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+fn parse_args(args: &[String]) -> Result<(Command, bool), String> {
+  let json = args.iter().any(|arg| arg == "--json");
+  let args = args.iter().map(String::as_str).filter(|arg| *arg != "--json").collect::<Vec<_>>();
+
+  let command = match args.as_slice() {
+    ["play"] => Command::Play,
+    ["pause"] => Command::Pause,
+    ["next"] => Command::Next,
+    ["prev" | "previous"] => Command::Previous,
+    ["seek", position] => Command::Seek {
+      position_ms: parse_position(position).ok_or_else(|| format!("Invalid position '{position}'"))?,
+    },
+    ["volume", level] => Command::Volume {
+      level: parse_volume(level).ok_or_else(|| format!("Invalid volume '{level}'"))?,
+    },
+    ["status"] => Command::Status,
+    ["watch"] => Command::Watch,
+    [] => return Err("Missing command".into()),
+    _ => return Err(format!("Unknown command '{}'", args.join(" "))),
+  };
+
+  Ok((command, json))
+}
+
+#[cfg(unix)]
+async fn connect() -> std::io::Result<tokio::net::UnixStream> { tokio::net::UnixStream::connect(endpoint()).await }
+
+#[cfg(windows)]
+async fn connect() -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+  tokio::net::windows::named_pipe::ClientOptions::new().open(endpoint())
+}
+
+/// Sends `command` and prints the responses, until the last one for
+/// commands other than `watch`
+async fn exchange<S: AsyncRead + AsyncWrite>(
+  stream: S,
+  command: Command,
+  json: bool,
+  out: &mut (dyn Write + Send),
+) -> anyhow::Result<()> {
+  let (reader, mut writer) = tokio::io::split(stream);
+  let is_watch = command == Command::Watch;
+
+  let mut line = serde_json::to_vec(&Request::new(1, command))?;
+  line.push(b'\n');
+  writer.write_all(&line).await?;
+  writer.flush().await?;
+
+  let mut lines = BufReader::new(reader).lines();
+  while let Some(line) = lines.next_line().await? {
+    let response: Response = serde_json::from_str(&line)?;
+
+    match response.reply {
+      Reply::Error { message } => bail!(message),
+      Reply::Ok if json => writeln!(out, "{line}")?,
+      Reply::Ok => {},
+      Reply::Status { .. } if json => writeln!(out, "{line}")?,
+      Reply::Status { status } => writeln!(out, "{}", describe(&status))?,
+    }
+    out.flush()?;
+
+    if !is_watch {
+      return Ok(());
+    }
+  }
+
+  if is_watch {
+    return Ok(());
+  }
+
+  bail!("media-widget closed the connection")
+}
+
+fn describe(status: &Status) -> String {
+  let Some(title) = &status.title else {
+    return "Nothing playing".into();
+  };
+
+  let mut text = format!("{} {}", if status.is_playing { "▶" } else { "⏸" }, title);
+  if !status.artists.is_empty() {
+    text.push_str(&format!(" — {}", status.artists.join(", ")));
+  }
+
+  match (status.position_ms, status.duration_ms) {
+    (Some(position), Some(duration)) => {
+      text.push_str(&format!(" ({} / {})", format_position(position), format_position(duration)))
+    },
+    (Some(position), None) => text.push_str(&format!(" ({})", format_position(position))),
+    _ => {},
+  }
+
+  if let Some(volume) = status.volume {
+    text.push_str(&format!(" [{:.0}%]", volume * 100.0));
+  }
+
+  text
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+  use std::time::Duration;
+
+  use tokio::sync::broadcast;
+
+  use super::*;
+  use crate::control::server::{serve, ControlHandler};
+  use crate::media::lib::{EventBus, MediaEvent, MediaSessionData};
+  use crate::router::media::stub::RecordingControl;
+  use crate::router::media::Method;
+
+  fn args(line: &str) -> Vec<String> { line.split_whitespace().map(String::from).collect() }
+
+  fn handler(event_bus: Arc<EventBus>) -> (Arc<RecordingControl>, Arc<ControlHandler>) {
+    let control = Arc::new(RecordingControl::default());
+    (control.clone(), ControlHandler::new(control, event_bus).arced())
+  }
+
+  #[test]
+  fn parses_arguments() {
+    let cases = [
+      ("play", Ok((Command::Play, false))),
+      ("prev", Ok((Command::Previous, false))),
+      ("seek 1:23", Ok((Command::Seek { position_ms: 83_000 }, false))),
+      ("volume 40%", Ok((Command::Volume { level: 0.4 }, false))),
+      ("status --json", Ok((Command::Status, true))),
+      ("--json watch", Ok((Command::Watch, true))),
+      ("", Err("Missing command".to_string())),
+      ("seek soon", Err("Invalid position 'soon'".into())),
+      ("volume 200%", Err("Invalid volume '200%'".into())),
+      ("play now", Err("Unknown command 'play now'".into())),
+    ];
+
+    for (line, expected) in cases {
+      assert_eq!(parse_args(&args(line)), expected, "{line}");
+    }
+  }
+
+  #[test]
+  fn describes_the_status() {
+    assert_eq!(describe(&Status::default()), "Nothing playing");
+    assert_eq!(
+      describe(&Status {
+        title: Some("Song".into()),
+        artists: vec!["A".into(), "B".into()],
+        is_playing: true,
+        position_ms: Some(83_000),
+        duration_ms: Some(200_000),
+        volume: Some(0.4),
+        ..Default::default()
+      }),
+      "▶ Song — A, B (1:23 / 3:20) [40%]"
+    );
+  }
+
+  #[tokio::test]
+  async fn sends_commands_to_the_server() {
+    let (control, handler) = handler(Arc::new(broadcast::channel(16)));
+    let (client, server) = tokio::io::duplex(4096);
+    tokio::spawn(serve(server, handler));
+
+    let mut out = vec![];
+    exchange(client, Command::Next, false, &mut out).await.unwrap();
+
+    assert!(out.is_empty());
+    assert_eq!(*control.invocations.lock().unwrap(), vec![Method::Next]);
+  }
+
+  #[tokio::test]
+  async fn watches_for_changes() {
+    let event_bus = Arc::new(broadcast::channel(16));
+    let (_, handler) = handler(event_bus.clone());
+    let (client, server) = tokio::io::duplex(4096);
+    tokio::spawn(serve(server, handler));
+
+    let watcher = tokio::spawn(async move {
+      let mut out = vec![];
+      let _ = tokio::time::timeout(Duration::from_millis(300), exchange(client, Command::Watch, false, &mut out)).await;
+      out
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    event_bus
+      .0
+      .send(MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A"])))
+      .unwrap();
+
+    let out = String::from_utf8(watcher.await.unwrap()).unwrap();
+    assert_eq!(out.lines().collect::<Vec<_>>(), vec!["Nothing playing", "⏸ Song — A (0:00)"]);
+  }
+}
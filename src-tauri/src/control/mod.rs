@@ -0,0 +1,4 @@
+pub mod cli;
+pub mod protocol;
+pub mod server;
+pub mod state;
\ No newline at end of file
@@ -0,0 +1,231 @@
+//! Line-delimited JSON spoken over the control socket. Every line is one
+//! message carrying the protocol version `v`, so other tools can implement
+//! it:
+//!
+//! ```text
+//! → {"v":1,"id":1,"command":{"kind":"seek","positionMs":83000}}
+//! ← {"v":1,"id":1,"type":"ok"}
+//! → {"v":1,"id":2,"command":{"kind":"watch"}}
+//! ← {"v":1,"id":2,"type":"status","status":{...}}
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Command {
+  Play,
+  Pause,
+  Next,
+  Previous,
+  #[serde(rename_all = "camelCase")]
+  Seek {
+    position_ms: u64,
+  },
+  /// Between 0 and 1
+  Volume {
+    level: f32,
+  },
+  Status,
+  /// Sends the status now and after every change, until disconnected
+  Watch,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+  pub v: u32,
+  pub id: u64,
+  pub command: Command,
+}
+
+impl Request {
+  pub fn new(id: u64, command: Command) -> Self {
+    Self {
+      v: PROTOCOL_VERSION,
+      id,
+      command,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Status {
+  /// `None` without an active media session
+  pub app: Option<String>,
+  pub title: Option<String>,
+  pub artists: Vec<String>,
+  pub album: Option<String>,
+  pub is_playing: bool,
+  pub position_ms: Option<u64>,
+  pub duration_ms: Option<u64>,
+  pub volume: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Reply {
+  Ok,
+  Status { status: Box<Status> },
+  Error { message: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+  pub v: u32,
+  /// The request this answers, 0 when the request couldn't be read
+  pub id: u64,
+  #[serde(flatten)]
+  pub reply: Reply,
+}
+
+impl Response {
+  pub fn new(id: u64, reply: Reply) -> Self {
+    Self {
+      v: PROTOCOL_VERSION,
+      id,
+      reply,
+    }
+  }
+}
+
+/// Parses one request line, answering with the error to send back when it
+/// can't be handled
+pub fn parse_request(line: &str) -> Result<Request, Response> {
+  let value = serde_json::from_str::<serde_json::Value>(line)
+    .map_err(|err| Response::new(0, Reply::Error { message: format!("Invalid JSON: {err}") }))?;
+
+  let id = value.get("id").and_then(serde_json::Value::as_u64).unwrap_or_default();
+  let version = value.get("v").and_then(serde_json::Value::as_u64);
+  if version != Some(PROTOCOL_VERSION as u64) {
+    return Err(Response::new(id, Reply::Error {
+      message: format!("Unsupported protocol version {version:?}, expected {PROTOCOL_VERSION}"),
+    }));
+  }
+
+  serde_json::from_value(value)
+    .map_err(|err| Response::new(id, Reply::Error { message: format!("Invalid request: {err}") }))
+}
+
+/// Parses `1:23`, `1:02:03` or plain seconds into milliseconds
+pub fn parse_position(input: &str) -> Option<u64> {
+  let parts = input.split(':').collect::<Vec<_>>();
+  if parts.len() > 3 || parts.iter().any(|part| part.is_empty()) {
+    return None;
+  }
+
+  let (seconds, rest) = parts.split_last()?;
+  let seconds = seconds.parse::<f64>().ok().filter(|seconds| seconds.is_finite() && *seconds >= 0.0)?;
+  if !rest.is_empty() && seconds >= 60.0 {
+    return None;
+  }
+
+  // Only the leading part may go past 59
+  let minutes = rest.iter().enumerate().try_fold(0u64, |total, (index, part)| {
+    let value = part.parse::<u64>().ok().filter(|value| index == 0 || *value < 60)?;
+    total.checked_mul(60)?.checked_add(value)
+  })?;
+
+  minutes.checked_mul(60_000)?.checked_add((seconds * 1000.0).round() as u64)
+}
+
+/// Parses `40%` or `40` into a 0..1 level
+pub fn parse_volume(input: &str) -> Option<f32> {
+  let percent = input.strip_suffix('%').unwrap_or(input).trim().parse::<f32>().ok()?;
+
+  (0.0..=100.0).contains(&percent).then_some(percent / 100.0)
+}
+
+pub fn format_position(ms: u64) -> String {
+  let seconds = ms / 1000;
+  match seconds / 3600 {
+    0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+    hours => format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_positions() {
+    let cases = [
+      ("0", Some(0)),
+      ("83", Some(83_000)),
+      ("1.5", Some(1_500)),
+      ("1:23", Some(83_000)),
+      ("0:05", Some(5_000)),
+      ("90:00", Some(5_400_000)),
+      ("1:02:03", Some(3_723_000)),
+      ("1:60", None),
+      ("1:60:00", None),
+      ("1:2:3:4", None),
+      (":30", None),
+      ("1:", None),
+      ("-5", None),
+      ("abc", None),
+      ("inf", None),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(parse_position(input), expected, "{input}");
+    }
+  }
+
+  #[test]
+  fn parses_volumes() {
+    assert_eq!(parse_volume("40%"), Some(0.4));
+    assert_eq!(parse_volume("100"), Some(1.0));
+    assert_eq!(parse_volume("0%"), Some(0.0));
+    assert_eq!(parse_volume("101%"), None);
+    assert_eq!(parse_volume("-1%"), None);
+    assert_eq!(parse_volume("loud"), None);
+  }
+
+  #[test]
+  fn formats_positions() {
+    assert_eq!(format_position(83_000), "1:23");
+    assert_eq!(format_position(5_999), "0:05");
+    assert_eq!(format_position(3_723_000), "1:02:03");
+  }
+
+  #[test]
+  fn reads_requests() {
+    let request = parse_request(r#"{"v":1,"id":7,"command":{"kind":"seek","positionMs":83000}}"#).unwrap();
+    assert_eq!(request, Request::new(7, Command::Seek { position_ms: 83_000 }));
+
+    let request = parse_request(r#"{"v":1,"id":1,"command":{"kind":"volume","level":0.4}}"#).unwrap();
+    assert_eq!(request.command, Command::Volume { level: 0.4 });
+  }
+
+  #[test]
+  fn answers_bad_requests_with_errors() {
+    let cases = [
+      ("nope", 0, "Invalid JSON"),
+      (r#"{"id":3,"command":{"kind":"play"}}"#, 3, "Unsupported protocol version None"),
+      (r#"{"v":2,"id":4,"command":{"kind":"play"}}"#, 4, "Unsupported protocol version Some(2)"),
+      (r#"{"v":1,"id":5,"command":{"kind":"dance"}}"#, 5, "Invalid request"),
+    ];
+
+    for (line, id, message) in cases {
+      let response = parse_request(line).unwrap_err();
+      assert_eq!(response.id, id, "{line}");
+      assert!(
+        matches!(&response.reply, Reply::Error { message: actual } if actual.starts_with(message)),
+        "{line}: {response:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn writes_flat_responses() {
+    let response = serde_json::to_value(Response::new(1, Reply::Ok)).unwrap();
+    assert_eq!(response, serde_json::json!({ "v": 1, "id": 1, "type": "ok" }));
+
+    let response = serde_json::to_value(Response::new(2, Reply::Error { message: "x".into() })).unwrap();
+    assert_eq!(response, serde_json::json!({ "v": 1, "id": 2, "type": "error", "message": "x" }));
+  }
+}
@@ -0,0 +1,302 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use super::protocol::{parse_request, Command, Reply, Response};
+use super::state::ControlState;
+use crate::media::lib::{EventBus, MediaEvent, TICKS_PER_MS};
+use crate::router::media::{MediaControl, Method};
+use crate::utils::time::now_ms;
+
+/// Where the control server listens, and the CLI connects
+#[cfg(unix)]
+pub fn endpoint() -> PathBuf { crate::utils::fs::runtime_dir().join("media-widget.sock") }
+
+#[cfg(windows)]
+pub fn endpoint() -> PathBuf { crate::utils::fs::user_pipe("media-widget") }
+
+pub struct ControlHandler {
+  control: Arc<dyn MediaControl>,
+  event_bus: Arc<EventBus>,
+  state: Mutex<ControlState>,
+}
+
+impl ControlHandler {
+  pub fn new(control: Arc<dyn MediaControl>, event_bus: Arc<EventBus>) -> Self {
+    Self {
+      control,
+      event_bus,
+      state: Mutex::new(ControlState::new()),
+    }
+  }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+
+  fn execute(&self, command: Command) -> Reply {
+    let method = match command {
+      Command::Play => Method::Play,
+      Command::Pause => Method::Pause,
+      Command::Next => Method::Next,
+      Command::Previous => Method::Previous,
+      Command::Seek { position_ms } => {
+        let Some(ticks) = usize::try_from(position_ms).ok().and_then(|ms| ms.checked_mul(TICKS_PER_MS)) else {
+          return Reply::Error {
+            message: format!("Position {position_ms}ms is out of range"),
+          };
+        };
+
+        Method::SetPlaybackPosition(ticks)
+      },
+      Command::Volume { level } if (0.0..=1.0).contains(&level) => Method::SetVolume(level),
+      Command::Volume { level } => {
+        return Reply::Error {
+          message: format!("Volume {level} is not between 0 and 1"),
+        }
+      },
+      Command::Status => {
+        return Reply::Status {
+          status: Box::new(self.state.lock().unwrap().status(now_ms())),
+        }
+      },
+      // Handled by the connection, which it takes over
+      Command::Watch => {
+        return Reply::Error {
+          message: "Watch needs a connection of its own".into(),
+        }
+      },
+    };
+
+    match self.control.invoke(method) {
+      Ok(()) => Reply::Ok,
+      Err(err) => Reply::Error { message: err.to_string() },
+    }
+  }
+}
+
+/// Keeps the status up to date from `events` and accepts connections on
+/// `endpoint()`
+pub fn spawn(
+  handler: Arc<ControlHandler>,
+  mut events: broadcast::Receiver<MediaEvent>,
+) -> anyhow::Result<(JoinHandle<()>, JoinHandle<()>)> {
+  let listener = listen()?;
+
+  println!("[ControlServer] listening on {:?}", endpoint());
+
+  let tracker = handler.clone();
+  Ok((
+    tokio::spawn(async move {
+      loop {
+        match events.recv().await {
+          Ok(event) => tracker.state.lock().unwrap().handle(&event, now_ms()),
+          Err(RecvError::Lagged(_)) => continue,
+          Err(RecvError::Closed) => break,
+        }
+      }
+    }),
+    tokio::spawn(accept(listener, handler)),
+  ))
+}
+
+#[cfg(unix)]
+fn listen() -> anyhow::Result<tokio::net::UnixListener> {
+  use std::os::unix::fs::PermissionsExt;
+
+  let path = endpoint();
+  if path.exists() {
+    if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+      anyhow::bail!("Another instance is already listening on {:?}", path);
+    }
+
+    // Left over from a crash
+    std::fs::remove_file(&path)?;
+  }
+
+  let listener = tokio::net::UnixListener::bind(&path)?;
+  std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+  Ok(listener)
+}
+
+#[cfg(unix)]
+async fn accept(listener: tokio::net::UnixListener, handler: Arc<ControlHandler>) {
+  loop {
+    match listener.accept().await {
+      Ok((stream, _)) => {
+        tokio::spawn(serve(stream, handler.clone()));
+      },
+      Err(err) => println!("[ControlServer] accept | Error: {}", err),
+    }
+  }
+}
+
+#[cfg(windows)]
+fn listen() -> anyhow::Result<tokio::net::windows::named_pipe::NamedPipeServer> {
+  use tokio::net::windows::named_pipe::ServerOptions;
+
+  Ok(ServerOptions::new().first_pipe_instance(true).create(endpoint())?)
+}
+
+#[cfg(windows)]
+async fn accept(mut server: tokio::net::windows::named_pipe::NamedPipeServer, handler: Arc<ControlHandler>) {
+  use tokio::net::windows::named_pipe::ServerOptions;
+
+  loop {
+    if let Err(err) = server.connect().await {
+      println!("[ControlServer] accept | Error: {}", err);
+      continue;
+    }
+
+    // A new instance has to exist before the next client can connect
+    let next = match ServerOptions::new().create(endpoint()) {
+      Ok(next) => next,
+      Err(err) => {
+        println!("[ControlServer] Error: {}", err);
+        break;
+      },
+    };
+
+    tokio::spawn(serve(std::mem::replace(&mut server, next), handler.clone()));
+  }
+}
+
+/// Answers one request per line until the client disconnects
+pub async fn serve<S: AsyncRead + AsyncWrite>(stream: S, handler: Arc<ControlHandler>) {
+  if let Err(err) = serve_connection(stream, handler).await {
+    println!("[ControlServer] Error: {}", err);
+  }
+}
+
+async fn serve_connection<S: AsyncRead + AsyncWrite>(stream: S, handler: Arc<ControlHandler>) -> anyhow::Result<()> {
+  let (reader, mut writer) = tokio::io::split(stream);
+  let mut lines = BufReader::new(reader).lines();
+
+  while let Some(line) = lines.next_line().await? {
+    if line.trim().is_empty() {
+      continue;
+    }
+
+    let request = match parse_request(&line) {
+      Ok(request) => request,
+      Err(response) => {
+        send(&mut writer, &response).await?;
+        continue;
+      },
+    };
+
+    if request.command != Command::Watch {
+      send(&mut writer, &Response::new(request.id, handler.execute(request.command))).await?;
+      continue;
+    }
+
+    // Subscribe before copying the state so no change falls in between
+    let mut events = handler.event_bus.0.subscribe();
+    let mut state = handler.state.lock().unwrap().clone();
+    let status = |state: &ControlState| Response::new(request.id, Reply::Status { status: Box::new(state.status(now_ms())) });
+
+    send(&mut writer, &status(&state)).await?;
+
+    loop {
+      tokio::select! {
+        event = events.recv() => match event {
+          Ok(event) => {
+            state.handle(&event, now_ms());
+            send(&mut writer, &status(&state)).await?;
+          },
+          Err(RecvError::Lagged(_)) => continue,
+          Err(RecvError::Closed) => return Ok(()),
+        },
+        // Nothing else is read while watching, this only notices the
+        // client going away
+        line = lines.next_line() => if line?.is_none() {
+          return Ok(());
+        },
+      }
+    }
+  }
+
+  Ok(())
+}
+
+async fn send<W: AsyncWrite + Unpin>(writer: &mut W, response: &Response) -> anyhow::Result<()> {
+  let mut line = serde_json::to_vec(response)?;
+  line.push(b'\n');
+
+  writer.write_all(&line).await?;
+  writer.flush().await?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::MediaVolumeData;
+  use crate::router::media::stub::RecordingControl;
+
+  async fn roundtrip(handler: Arc<ControlHandler>, lines: &str) -> Vec<serde_json::Value> {
+    let (mut client, server) = tokio::io::duplex(4096);
+    let task = tokio::spawn(serve(server, handler));
+
+    client.write_all(lines.as_bytes()).await.unwrap();
+    client.shutdown().await.unwrap();
+
+    let mut responses = vec![];
+    let mut reader = BufReader::new(client).lines();
+    while let Some(line) = reader.next_line().await.unwrap() {
+      responses.push(serde_json::from_str(&line).unwrap());
+    }
+
+    task.await.unwrap();
+    responses
+  }
+
+  #[tokio::test]
+  async fn maps_commands_to_methods() {
+    let control = Arc::new(RecordingControl::default());
+    let handler = ControlHandler::new(control.clone(), Arc::new(broadcast::channel(16))).arced();
+
+    let responses = roundtrip(
+      handler,
+      concat!(
+        r#"{"v":1,"id":1,"command":{"kind":"play"}}"#,
+        "\n\n",
+        r#"{"v":1,"id":2,"command":{"kind":"seek","positionMs":83000}}"#,
+        "\n",
+        r#"{"v":1,"id":3,"command":{"kind":"volume","level":0.4}}"#,
+        "\n",
+        r#"{"v":1,"id":4,"command":{"kind":"volume","level":4}}"#,
+        "\n",
+        r#"{"v":9,"id":5,"command":{"kind":"play"}}"#,
+        "\n",
+        r#"{"v":1,"id":6,"command":{"kind":"seek","positionMs":18446744073709551615}}"#,
+        "\n",
+      ),
+    )
+    .await;
+
+    assert_eq!(
+      responses.iter().map(|response| (response["id"].as_u64().unwrap(), response["type"].as_str().unwrap())).collect::<Vec<_>>(),
+      vec![(1, "ok"), (2, "ok"), (3, "ok"), (4, "error"), (5, "error"), (6, "error")]
+    );
+    assert_eq!(*control.invocations.lock().unwrap(), vec![
+      Method::Play,
+      Method::SetPlaybackPosition(830_000_000),
+      Method::SetVolume(0.4),
+    ]);
+  }
+
+  #[tokio::test]
+  async fn reports_the_status() {
+    let handler = ControlHandler::new(Arc::new(RecordingControl::default()), Arc::new(broadcast::channel(16))).arced();
+    handler.state.lock().unwrap().handle(&MediaEvent::VolumeChanged(MediaVolumeData::fixture(0.5)), 0);
+
+    let responses = roundtrip(handler, "{\"v\":1,\"id\":1,\"command\":{\"kind\":\"status\"}}\n").await;
+
+    assert_eq!(responses[0]["type"], "status");
+    assert_eq!(responses[0]["status"]["volume"], 0.5);
+    assert_eq!(responses[0]["status"]["title"], serde_json::Value::Null);
+  }
+}
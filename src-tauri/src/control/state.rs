@@ -0,0 +1,90 @@
+use super::protocol::Status;
+use crate::media::lib::{MediaEvent, MediaSessionData};
+use crate::media::position::PlaybackPosition;
+
+/// What `status` reports, kept up to date from the media events
+#[derive(Debug, Clone, Default)]
+pub struct ControlState {
+  app: Option<String>,
+  session: Option<MediaSessionData>,
+  position: PlaybackPosition,
+  volume: Option<f32>,
+}
+
+impl ControlState {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn handle(&mut self, event: &MediaEvent, now: i64) {
+    match event {
+      MediaEvent::Connect(app) => self.app = Some(app.clone()),
+      MediaEvent::Disconnect(_) => *self = Self::default(),
+      MediaEvent::MediaPropertiesChanged(data) => {
+        let is_same_track = self
+          .session
+          .as_ref()
+          .is_some_and(|session| session.title == data.title && session.artists == data.artists);
+        if !is_same_track {
+          self.position.reset(now);
+        }
+
+        self.session = Some(data.clone());
+      },
+      MediaEvent::PlaybackInfoChanged(data) => self.position.set_playing(data.is_playing, now),
+      MediaEvent::TimelinePropertiesChanged(data) => self.position.set_timeline(data, now),
+      MediaEvent::VolumeChanged(data) => self.volume = Some(data.level),
+    }
+  }
+
+  pub fn status(&self, now: i64) -> Status {
+    let Some(session) = &self.session else {
+      return Status {
+        app: self.app.clone(),
+        volume: self.volume,
+        ..Default::default()
+      };
+    };
+
+    Status {
+      app: self.app.clone(),
+      title: Some(session.title.clone()),
+      artists: session.artists.clone(),
+      album: Some(session.album.clone()),
+      is_playing: self.position.is_playing(),
+      position_ms: Some(self.position.position_ms(now).max(0) as u64),
+      duration_ms: self.position.duration_ms().map(|duration| duration as u64),
+      volume: self.volume,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::{MediaPlaybackData, MediaTimelineData, MediaVolumeData};
+
+  #[test]
+  fn reports_the_current_track() {
+    let mut state = ControlState::new();
+    assert_eq!(state.status(0), Status::default());
+
+    state.handle(&MediaEvent::Connect("Spotify.exe".into()), 0);
+    state.handle(&MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A"])), 0);
+    state.handle(&MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing: true }), 0);
+    state.handle(&MediaEvent::TimelinePropertiesChanged(MediaTimelineData::fixture(10_000, 200_000)), 1_000);
+    state.handle(&MediaEvent::VolumeChanged(MediaVolumeData::fixture(0.4)), 1_000);
+
+    assert_eq!(state.status(3_000), Status {
+      app: Some("Spotify.exe".into()),
+      title: Some("Song".into()),
+      artists: vec!["A".into()],
+      album: Some("Album".into()),
+      is_playing: true,
+      position_ms: Some(12_000),
+      duration_ms: Some(200_000),
+      volume: Some(0.4),
+    });
+
+    state.handle(&MediaEvent::Disconnect("Spotify.exe".into()), 4_000);
+    assert_eq!(state.status(5_000), Status::default());
+  }
+}
@@ -0,0 +1,315 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Discord never sends anything close to this, a bigger frame means the
+/// stream is out of sync
+const MAX_FRAME_LEN: u32 = 64 * 1024;
+
+/// How long Discord gets to answer, a socket left behind by a hung client
+/// would otherwise stall the publisher forever
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Every frame is `opcode: u32 LE`, `length: u32 LE`, then `length` bytes of
+/// JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+  Handshake = 0,
+  Frame = 1,
+  Close = 2,
+  Ping = 3,
+  Pong = 4,
+}
+
+impl TryFrom<u32> for Opcode {
+  type Error = anyhow::Error;
+
+  fn try_from(value: u32) -> Result<Self, Self::Error> {
+    Ok(match value {
+      0 => Self::Handshake,
+      1 => Self::Frame,
+      2 => Self::Close,
+      3 => Self::Ping,
+      4 => Self::Pong,
+      _ => bail!("Unknown opcode {value}"),
+    })
+  }
+}
+
+pub fn encode(opcode: Opcode, payload: &Value) -> Vec<u8> {
+  let payload = payload.to_string();
+  let mut frame = Vec::with_capacity(8 + payload.len());
+
+  frame.extend_from_slice(&(opcode as u32).to_le_bytes());
+  frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+  frame.extend_from_slice(payload.as_bytes());
+  frame
+}
+
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, opcode: Opcode, payload: &Value) -> anyhow::Result<()> {
+  writer.write_all(&encode(opcode, payload)).await?;
+  writer.flush().await?;
+  Ok(())
+}
+
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> anyhow::Result<(Opcode, Value)> {
+  let mut header = [0u8; 8];
+  reader.read_exact(&mut header).await?;
+
+  let opcode = Opcode::try_from(u32::from_le_bytes(header[..4].try_into()?))?;
+  let len = u32::from_le_bytes(header[4..].try_into()?);
+  if len > MAX_FRAME_LEN {
+    bail!("Frame of {len} bytes is too large");
+  }
+
+  let mut payload = vec![0u8; len as usize];
+  reader.read_exact(&mut payload).await?;
+
+  Ok((opcode, serde_json::from_slice(&payload)?))
+}
+
+pub trait IpcStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> IpcStream for T {}
+
+/// A handshaken connection to the local Discord client
+/// https://discord.com/developers/docs/topics/rpc
+pub struct DiscordIpc<S> {
+  stream: S,
+  nonce: u64,
+  timeout: Duration,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> DiscordIpc<S> {
+  /// Identifies as `client_id` and waits for Discord to be ready
+  pub async fn handshake(mut stream: S, client_id: &str) -> anyhow::Result<Self> {
+    write_frame(&mut stream, Opcode::Handshake, &json!({ "v": 1, "client_id": client_id })).await?;
+
+    let mut ipc = Self {
+      stream,
+      nonce: 0,
+      timeout: READ_TIMEOUT,
+    };
+    ipc.read_until(|payload| payload["evt"] == "READY").await?;
+    Ok(ipc)
+  }
+
+  /// `None` clears the presence
+  pub async fn set_activity<T: serde::Serialize>(&mut self, activity: Option<&T>) -> anyhow::Result<()> {
+    self.nonce += 1;
+    let nonce = format!("{}-{}", std::process::id(), self.nonce);
+
+    let command = json!({
+      "cmd": "SET_ACTIVITY",
+      "args": { "pid": std::process::id(), "activity": activity },
+      "nonce": nonce,
+    });
+    write_frame(&mut self.stream, Opcode::Frame, &command).await?;
+
+    let response = self.read_until(|payload| payload["nonce"] == nonce.as_str()).await?;
+    if response["evt"] == "ERROR" {
+      bail!("SET_ACTIVITY failed: {}", response["data"]["message"]);
+    }
+
+    Ok(())
+  }
+
+  /// Answers pings until a frame matching `predicate` arrives
+  async fn read_until(&mut self, predicate: impl Fn(&Value) -> bool) -> anyhow::Result<Value> {
+    let read = async {
+      loop {
+        match read_frame(&mut self.stream).await? {
+          (Opcode::Frame, payload) if predicate(&payload) => return Ok(payload),
+          (Opcode::Frame | Opcode::Pong, _) => {},
+          (Opcode::Ping, payload) => write_frame(&mut self.stream, Opcode::Pong, &payload).await?,
+          (Opcode::Close, payload) => bail!("Discord closed the connection: {}", payload["message"]),
+          (Opcode::Handshake, _) => bail!("Unexpected handshake frame"),
+        }
+      }
+    };
+
+    tokio::time::timeout(self.timeout, read)
+      .await
+      .map_err(|_| anyhow!("Discord didn't answer within {:?}", self.timeout))?
+  }
+}
+
+/// Discord listens on the first free of `discord-ipc-0` to `discord-ipc-9`,
+/// they are tried in order until one answers
+pub async fn connect(client_id: &str) -> anyhow::Result<DiscordIpc<Box<dyn IpcStream>>> {
+  for path in candidates() {
+    let Ok(stream) = open(&path).await else {
+      continue;
+    };
+
+    match DiscordIpc::handshake(stream, client_id).await {
+      Ok(ipc) => return Ok(ipc),
+      Err(err) => println!("[DiscordIpc] handshake {} | Error: {}", path.display(), err),
+    }
+  }
+
+  Err(anyhow!("Discord is not running"))
+}
+
+#[cfg(windows)]
+fn candidates() -> Vec<PathBuf> { (0..10).map(|index| format!(r"\\.\pipe\discord-ipc-{index}").into()).collect() }
+
+#[cfg(windows)]
+async fn open(path: &Path) -> std::io::Result<Box<dyn IpcStream>> {
+  let pipe = tokio::net::windows::named_pipe::ClientOptions::new().open(path)?;
+  Ok(Box::new(pipe))
+}
+
+#[cfg(unix)]
+fn candidates() -> Vec<PathBuf> { socket_paths(&runtime_dirs()) }
+
+#[cfg(unix)]
+async fn open(path: &Path) -> std::io::Result<Box<dyn IpcStream>> {
+  let stream = tokio::net::UnixStream::connect(path).await?;
+  Ok(Box::new(stream))
+}
+
+#[cfg(unix)]
+fn runtime_dirs() -> Vec<PathBuf> {
+  let mut dirs = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+    .iter()
+    .filter_map(std::env::var_os)
+    .map(PathBuf::from)
+    .collect::<Vec<_>>();
+  dirs.push("/tmp".into());
+  dirs.dedup();
+  dirs
+}
+
+/// Flatpak and Snap installs put their socket in a subdirectory
+#[cfg(unix)]
+fn socket_paths(dirs: &[PathBuf]) -> Vec<PathBuf> {
+  dirs
+    .iter()
+    .flat_map(|dir| {
+      [dir.clone(), dir.join("app/com.discordapp.Discord"), dir.join("snap.discord")]
+    })
+    .flat_map(|dir| (0..10).map(move |index| dir.join(format!("discord-ipc-{index}"))))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn round_trips_frames() {
+    let (mut client, mut server) = tokio::io::duplex(1024);
+    write_frame(&mut client, Opcode::Ping, &json!({ "a": 1 })).await.unwrap();
+
+    assert_eq!(read_frame(&mut server).await.unwrap(), (Opcode::Ping, json!({ "a": 1 })));
+  }
+
+  #[test]
+  fn encodes_header_little_endian() {
+    let frame = encode(Opcode::Frame, &json!({}));
+
+    assert_eq!(frame, [1, 0, 0, 0, 2, 0, 0, 0, b'{', b'}']);
+  }
+
+  #[tokio::test]
+  async fn rejects_oversized_and_unknown_frames() {
+    let mut oversized = vec![1, 0, 0, 0];
+    oversized.extend_from_slice(&(MAX_FRAME_LEN + 1).to_le_bytes());
+    assert!(read_frame(&mut oversized.as_slice()).await.is_err());
+
+    let unknown = [9u8, 0, 0, 0, 2, 0, 0, 0, b'{', b'}'];
+    assert!(read_frame(&mut unknown.as_slice()).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn surfaces_close_frames() {
+    let (mut client, mut server) = tokio::io::duplex(1024);
+    write_frame(&mut server, Opcode::Close, &json!({ "code": 4000, "message": "Invalid Client ID" }))
+      .await
+      .unwrap();
+
+    let err = DiscordIpc::handshake(&mut client, "bad").await.err().unwrap();
+    assert!(err.to_string().contains("Invalid Client ID"));
+  }
+
+  #[tokio::test]
+  async fn gives_up_on_a_silent_client() {
+    let (client, _server) = tokio::io::duplex(1024);
+    let mut ipc = DiscordIpc {
+      stream: client,
+      nonce: 0,
+      timeout: Duration::from_millis(50),
+    };
+
+    let err = ipc.set_activity::<Value>(None).await.err().unwrap();
+    assert!(err.to_string().contains("didn't answer"), "{err}");
+  }
+
+  #[cfg(unix)]
+  mod socket {
+    use super::*;
+    use tokio::net::{UnixListener, UnixStream};
+
+    /// Plays the Discord side of a connection: handshake, one ping, then
+    /// acknowledges every command
+    async fn fake_discord(listener: UnixListener) -> Vec<Value> {
+      let (mut stream, _) = listener.accept().await.unwrap();
+      let mut received = vec![];
+
+      let (opcode, handshake) = read_frame(&mut stream).await.unwrap();
+      assert_eq!(opcode, Opcode::Handshake);
+      received.push(handshake);
+
+      write_frame(&mut stream, Opcode::Frame, &json!({ "cmd": "DISPATCH", "evt": "READY" })).await.unwrap();
+      write_frame(&mut stream, Opcode::Ping, &json!({ "ping": true })).await.unwrap();
+
+      while let Ok((opcode, payload)) = read_frame(&mut stream).await {
+        if opcode == Opcode::Frame {
+          let response = json!({ "cmd": "SET_ACTIVITY", "evt": null, "nonce": payload["nonce"] });
+          write_frame(&mut stream, Opcode::Frame, &response).await.unwrap();
+        }
+        received.push(payload);
+      }
+
+      received
+    }
+
+    #[tokio::test]
+    async fn talks_to_a_local_socket() {
+      let dir = std::env::temp_dir().join(format!("media-widget-discord-{}", std::process::id()));
+      std::fs::create_dir_all(&dir).unwrap();
+
+      // discord-ipc-0 is taken by something else
+      let path = socket_paths(std::slice::from_ref(&dir))[1].clone();
+      let _ = std::fs::remove_file(&path);
+      let server = tokio::spawn(fake_discord(UnixListener::bind(&path).unwrap()));
+
+      let stream = UnixStream::connect(&path).await.unwrap();
+      let mut ipc = DiscordIpc::handshake(stream, "1234").await.unwrap();
+      ipc.set_activity(Some(&json!({ "details": "Song" }))).await.unwrap();
+      ipc.set_activity::<Value>(None).await.unwrap();
+      drop(ipc);
+
+      let received = server.await.unwrap();
+      assert_eq!(received[0], json!({ "v": 1, "client_id": "1234" }));
+      // The ping is answered while waiting for the first acknowledgement
+      assert_eq!(received[1]["args"]["activity"], json!({ "details": "Song" }));
+      assert_eq!(received[2], json!({ "ping": true }));
+      assert_eq!(received[3]["args"]["activity"], Value::Null);
+
+      std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn looks_in_flatpak_and_snap_dirs() {
+      let paths = socket_paths(&["/run/user/1000".into()]);
+
+      assert_eq!(paths.len(), 30);
+      assert!(paths.contains(&"/run/user/1000/app/com.discordapp.Discord/discord-ipc-0".into()));
+      assert!(paths.contains(&"/run/user/1000/snap.discord/discord-ipc-9".into()));
+    }
+  }
+}
@@ -0,0 +1,3 @@
+pub mod ipc;
+pub mod presence;
+pub mod publisher;
\ No newline at end of file
@@ -0,0 +1,296 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use crate::media::lib::{MediaEvent, MediaSessionData};
+use crate::media::position::PlaybackPosition;
+
+/// Discord's "Listening to" activity type
+const LISTENING: u8 = 2;
+
+/// Discord rejects strings outside of 2..=128 characters
+const MAX_TEXT_LEN: usize = 128;
+
+/// Timeline updates that move the timestamps less than this are just drift,
+/// anything more is a seek
+const SEEK_THRESHOLD_MS: i64 = 2_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ActivityAssets {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub large_image: Option<String>,
+  pub large_text: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub small_image: Option<String>,
+  pub small_text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActivityTimestamps {
+  pub start: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub end: Option<i64>,
+}
+
+/// https://discord.com/developers/docs/topics/gateway-events#activity-object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Activity {
+  #[serde(rename = "type")]
+  pub kind: u8,
+  pub details: String,
+  pub state: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub timestamps: Option<ActivityTimestamps>,
+  pub assets: ActivityAssets,
+}
+
+/// Asset keys uploaded to the Discord application, all optional
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceAssets {
+  pub large_image: Option<String>,
+  pub playing_image: Option<String>,
+  pub paused_image: Option<String>,
+}
+
+/// Turns media events into the activity to show, and tells whether it changed
+/// enough to be worth sending
+#[derive(Debug, Default)]
+pub struct PresenceState {
+  assets: PresenceAssets,
+  session: Option<MediaSessionData>,
+  position: PlaybackPosition,
+}
+
+impl PresenceState {
+  pub fn new(assets: PresenceAssets) -> Self {
+    Self {
+      assets,
+      ..Default::default()
+    }
+  }
+
+  /// Returns true when the presence should be sent again
+  pub fn handle(&mut self, event: &MediaEvent, now: i64) -> bool {
+    match event {
+      MediaEvent::MediaPropertiesChanged(data) => {
+        if self.session.as_ref() == Some(data) {
+          return false;
+        }
+
+        let is_same_track = self
+          .session
+          .as_ref()
+          .is_some_and(|session| session.title == data.title && session.artists == data.artists);
+        if !is_same_track {
+          self.position.reset(now);
+        }
+
+        self.session = Some(data.clone());
+        true
+      },
+      MediaEvent::PlaybackInfoChanged(data) => {
+        let changed = self.position.is_playing() != data.is_playing;
+        self.position.set_playing(data.is_playing, now);
+        changed && self.session.is_some()
+      },
+      MediaEvent::TimelinePropertiesChanged(data) => {
+        let (before, duration) = (self.timestamps(now), self.position.duration_ms());
+        self.position.set_timeline(data, now);
+
+        match (before, self.timestamps(now)) {
+          (Some(before), Some(after)) => {
+            duration != self.position.duration_ms() || (before.start - after.start).abs() >= SEEK_THRESHOLD_MS
+          },
+          (before, after) => before != after,
+        }
+      },
+      MediaEvent::Disconnect(_) => {
+        self.position = PlaybackPosition::new();
+        self.session.take().is_some()
+      },
+      MediaEvent::Connect(_) | MediaEvent::VolumeChanged(_) => false,
+    }
+  }
+
+  /// `None` means the presence should be cleared
+  pub fn activity(&self, now: i64) -> Option<Activity> {
+    let session = self.session.as_ref()?;
+    let is_playing = self.position.is_playing();
+
+    Some(Activity {
+      kind: LISTENING,
+      details: fit(&session.title),
+      state: fit(&format!("by {}", session.artists.join(", "))),
+      timestamps: self.timestamps(now),
+      assets: ActivityAssets {
+        large_image: self.assets.large_image.clone(),
+        large_text: fit(&session.album),
+        small_image: if is_playing { &self.assets.playing_image } else { &self.assets.paused_image }.clone(),
+        small_text: if is_playing { "Playing" } else { "Paused" }.into(),
+      },
+    })
+  }
+
+  /// Elapsed time from `start`, remaining time until `end`. Hidden while
+  /// paused since Discord would keep counting.
+  fn timestamps(&self, now: i64) -> Option<ActivityTimestamps> {
+    if self.session.is_none() || !self.position.is_playing() {
+      return None;
+    }
+
+    let start = now - self.position.position_ms(now);
+    Some(ActivityTimestamps {
+      start,
+      end: self.position.duration_ms().map(|duration| start + duration),
+    })
+  }
+}
+
+fn fit(text: &str) -> String {
+  let mut text = text.chars().take(MAX_TEXT_LEN).collect::<String>();
+  while text.chars().count() < 2 {
+    text.push('\u{2800}');
+  }
+  text
+}
+
+/// Discord drops updates beyond 5 per 20 seconds, so they are held back and
+/// only the latest is sent
+#[derive(Debug)]
+pub struct RateLimiter {
+  limit: usize,
+  window_ms: i64,
+  sent: VecDeque<i64>,
+}
+
+impl Default for RateLimiter {
+  fn default() -> Self { Self::new(5, 20_000) }
+}
+
+impl RateLimiter {
+  pub fn new(limit: usize, window_ms: i64) -> Self {
+    Self {
+      limit,
+      window_ms,
+      sent: VecDeque::with_capacity(limit),
+    }
+  }
+
+  /// Milliseconds until the next update may be sent
+  pub fn delay(&self, now: i64) -> i64 {
+    if self.sent.len() < self.limit {
+      return 0;
+    }
+
+    self.sent.front().map_or(0, |oldest| (oldest + self.window_ms - now).max(0))
+  }
+
+  pub fn record(&mut self, now: i64) {
+    while self.sent.len() >= self.limit {
+      self.sent.pop_front();
+    }
+    self.sent.push_back(now);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::{MediaPlaybackData, MediaTimelineData};
+
+  fn track(title: &str) -> MediaEvent {
+    MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture(title, &["A", "B"]))
+  }
+
+  fn playing(is_playing: bool) -> MediaEvent {
+    MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing })
+  }
+
+  fn timeline(position_ms: usize) -> MediaEvent {
+    MediaEvent::TimelinePropertiesChanged(MediaTimelineData::fixture(position_ms, 200_000))
+  }
+
+  #[test]
+  fn shows_the_current_track() {
+    let mut state = PresenceState::new(PresenceAssets {
+      playing_image: Some("play".into()),
+      ..Default::default()
+    });
+
+    assert!(!state.handle(&MediaEvent::Connect("Spotify.exe".into()), 0));
+    assert!(state.handle(&track("Song"), 0));
+    assert!(state.handle(&playing(true), 0));
+    assert!(state.handle(&timeline(30_000), 10_000));
+
+    let activity = state.activity(10_000).unwrap();
+    assert_eq!(activity.details, "Song");
+    assert_eq!(activity.state, "by A, B");
+    assert_eq!(activity.assets.large_text, "Album");
+    assert_eq!(activity.assets.small_image.as_deref(), Some("play"));
+    assert_eq!(activity.timestamps, Some(ActivityTimestamps {
+      start: -20_000,
+      end: Some(180_000),
+    }));
+  }
+
+  #[test]
+  fn hides_timestamps_while_paused() {
+    let mut state = PresenceState::default();
+    state.handle(&track("Song"), 0);
+    state.handle(&playing(true), 0);
+
+    assert!(state.handle(&playing(false), 5_000));
+    assert!(!state.handle(&playing(false), 6_000));
+
+    let activity = state.activity(6_000).unwrap();
+    assert_eq!(activity.timestamps, None);
+    assert_eq!(activity.assets.small_text, "Paused");
+    assert_eq!(activity.assets.small_image, None);
+  }
+
+  #[test]
+  fn only_resends_timeline_on_seek() {
+    let mut state = PresenceState::default();
+    state.handle(&track("Song"), 0);
+    state.handle(&playing(true), 0);
+    assert!(state.handle(&timeline(0), 0));
+
+    // Regular progress updates
+    assert!(!state.handle(&timeline(5_000), 5_000));
+    assert!(!state.handle(&timeline(10_500), 10_000));
+
+    assert!(state.handle(&timeline(60_000), 11_000));
+  }
+
+  #[test]
+  fn clears_on_disconnect() {
+    let mut state = PresenceState::default();
+    state.handle(&track("Song"), 0);
+
+    assert!(state.handle(&MediaEvent::Disconnect("Spotify.exe".into()), 0));
+    assert_eq!(state.activity(0), None);
+    assert!(!state.handle(&MediaEvent::Disconnect("Spotify.exe".into()), 0));
+  }
+
+  #[test]
+  fn fits_text_to_discord_limits() {
+    assert_eq!(fit("A").chars().count(), 2);
+    assert_eq!(fit(&"x".repeat(200)).chars().count(), MAX_TEXT_LEN);
+    assert_eq!(fit("Song"), "Song");
+  }
+
+  #[test]
+  fn limits_updates_per_window() {
+    let mut limiter = RateLimiter::new(2, 10_000);
+
+    assert_eq!(limiter.delay(0), 0);
+    limiter.record(0);
+    limiter.record(1_000);
+    assert_eq!(limiter.delay(2_000), 8_000);
+    assert_eq!(limiter.delay(10_000), 0);
+
+    limiter.record(10_000);
+    assert_eq!(limiter.delay(10_000), 1_000);
+  }
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use super::ipc::{self, DiscordIpc, IpcStream};
+use super::presence::{PresenceAssets, PresenceState, RateLimiter};
+use crate::media::lib::EventBus;
+use crate::settings::schema::DiscordSettings;
+use crate::utils::time::now_ms;
+
+/// How long to wait before looking for Discord again
+const RECONNECT_MS: i64 = 15_000;
+
+/// How long to sleep when there is nothing to wait for
+const IDLE: Duration = Duration::from_secs(60 * 60);
+
+fn assets(settings: &DiscordSettings) -> PresenceAssets {
+  PresenceAssets {
+    large_image: settings.large_image.clone(),
+    playing_image: settings.playing_image.clone(),
+    paused_image: settings.paused_image.clone(),
+  }
+}
+
+/// Mirrors the media events to the Discord rich presence, connecting lazily
+/// and reconnecting whenever Discord restarts
+pub fn spawn(event_bus: Arc<EventBus>, settings: Option<DiscordSettings>) -> Option<JoinHandle<()>> {
+  let Some(settings) = settings else {
+    println!("[DiscordPresence] not configured");
+    return None;
+  };
+
+  println!("[DiscordPresence] spawn");
+
+  let mut events = event_bus.0.subscribe();
+
+  Some(tokio::spawn(async move {
+    let mut state = PresenceState::new(assets(&settings));
+    let mut limiter = RateLimiter::default();
+    let mut connection: Option<DiscordIpc<Box<dyn IpcStream>>> = None;
+    let mut reconnect_at = 0;
+    let mut dirty = false;
+
+    loop {
+      let now = now_ms();
+      let activity = state.activity(now);
+
+      // Nothing to clear if we never got to show anything
+      if dirty && activity.is_none() && connection.is_none() {
+        dirty = false;
+      }
+
+      if dirty && connection.is_none() && now >= reconnect_at {
+        match ipc::connect(&settings.client_id).await {
+          Ok(ipc) => connection = Some(ipc),
+          Err(err) => {
+            println!("[DiscordPresence] connect | Error: {}", err);
+            reconnect_at = now + RECONNECT_MS;
+          },
+        }
+      }
+
+      let wait = match &mut connection {
+        Some(ipc) if dirty && limiter.delay(now) == 0 => {
+          limiter.record(now);
+
+          match ipc.set_activity(activity.as_ref()).await {
+            Ok(()) => dirty = false,
+            Err(err) => {
+              println!("[DiscordPresence] set activity | Error: {}", err);
+              connection = None;
+              reconnect_at = now + RECONNECT_MS;
+            },
+          }
+
+          continue;
+        },
+        Some(_) if dirty => limiter.delay(now),
+        None if dirty => (reconnect_at - now).max(0),
+        _ => IDLE.as_millis() as i64,
+      };
+
+      tokio::select! {
+        event = events.recv() => {
+          match event {
+            Ok(event) => dirty |= state.handle(&event, now_ms()),
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+          };
+        },
+        _ = tokio::time::sleep(Duration::from_millis(wait as u64)) => {},
+      }
+    }
+  }))
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task::JoinHandle;
+
+use super::rules::{AudioActivity, DuckAction, DuckingState};
+use crate::control::state::ControlState;
+use crate::media::lib::MediaEvent;
+use crate::router::media::{MediaControl, Method};
+use crate::settings::schema::Settings;
+use crate::utils::time::now_ms;
+
+/// Applies the ducking rules to the audio sessions of other apps, as
+/// reported by the player's session
+pub fn spawn(
+  control: Arc<dyn MediaControl>,
+  mut events: broadcast::Receiver<MediaEvent>,
+  mut activity: mpsc::UnboundedReceiver<AudioActivity>,
+  mut settings: watch::Receiver<Settings>,
+) -> JoinHandle<()> {
+  println!("[Ducking] spawn");
+
+  tokio::spawn(async move {
+    let mut player = ControlState::new();
+    let mut state = DuckingState::new();
+
+    loop {
+      let next_change = state.next_change_in(&settings.borrow().ducking, now_ms());
+
+      tokio::select! {
+        event = events.recv() => match event {
+          Ok(event) => player.handle(&event, now_ms()),
+          Err(RecvError::Lagged(_)) => continue,
+          Err(RecvError::Closed) => break,
+        },
+        activity = activity.recv() => match activity {
+          Some(activity) => state.handle(&activity),
+          None => break,
+        },
+        changed = settings.changed() => if changed.is_err() { break },
+        _ = tokio::time::sleep(next_change.unwrap_or_default()), if next_change.is_some() => {},
+      }
+
+      let now = now_ms();
+      let rules = settings.borrow().ducking.clone();
+      let Some(action) = state.evaluate(&rules, &player.status(now), now) else {
+        continue;
+      };
+
+      println!("[Ducking] {:?}", action);
+      let method = match action {
+        DuckAction::Lower(volume) | DuckAction::Restore(volume) => Method::SetVolume(volume),
+        DuckAction::Pause => Method::Pause,
+        DuckAction::Resume => Method::Play,
+      };
+      if let Err(err) = control.invoke(method) {
+        println!("[Ducking] Error: {}", err);
+      }
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::{MediaPlaybackData, MediaSessionData, MediaVolumeData};
+  use crate::router::media::stub::RecordingControl;
+  use crate::settings::schema::DuckMode;
+  use crate::utils::wait::until;
+
+  fn invoked(control: &RecordingControl, count: usize) -> bool { control.invocations.lock().unwrap().len() == count }
+
+  #[tokio::test]
+  async fn ducks_for_synthetic_sessions() {
+    let control = Arc::new(RecordingControl::default());
+    let (events, event_receiver) = broadcast::channel(16);
+    let (activity, activity_receiver) = mpsc::unbounded_channel();
+    let mut settings = Settings::default();
+    settings.ducking.enabled = true;
+    settings.ducking.restore_delay_secs = 0;
+    let (settings, settings_receiver) = watch::channel(settings);
+
+    spawn(control.clone(), event_receiver, activity_receiver, settings_receiver);
+
+    events.send(MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A"]))).unwrap();
+    events.send(MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing: true })).unwrap();
+    events.send(MediaEvent::VolumeChanged(MediaVolumeData::fixture(0.6))).unwrap();
+
+    activity
+      .send(AudioActivity::Started {
+        session: 1,
+        process: "Teams.exe".into(),
+      })
+      .unwrap();
+    until(|| invoked(&control, 1)).await;
+    activity.send(AudioActivity::Stopped { session: 1 }).unwrap();
+    until(|| invoked(&control, 2)).await;
+
+    settings.send_modify(|settings| settings.ducking.mode = DuckMode::Pause);
+    activity
+      .send(AudioActivity::Started {
+        session: 2,
+        process: "chrome.exe".into(),
+      })
+      .unwrap();
+    until(|| invoked(&control, 3)).await;
+
+    assert_eq!(*control.invocations.lock().unwrap(), vec![
+      Method::SetVolume(0.2),
+      Method::SetVolume(0.6),
+      Method::Pause,
+    ]);
+  }
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::control::protocol::Status;
+use crate::settings::schema::{DuckMode, DuckingSettings};
+
+/// Audio sessions of other apps. One process can have several (browser
+/// tabs, calls), so each session has its own id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AudioActivity {
+  /// `process` is the process name, e.g. `Teams.exe`
+  Started { session: u64, process: String },
+  Stopped { session: u64 },
+}
+
+/// What to do with the player
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuckAction {
+  Lower(f32),
+  Pause,
+  Restore(f32),
+  Resume,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ducked {
+  Lowered { from: f32 },
+  Paused,
+}
+
+/// Whether `process` ducks the player, names are compared ignoring case
+pub fn triggers(rules: &DuckingSettings, process: &str) -> bool {
+  let listed = |apps: &[String]| apps.iter().any(|app| app.eq_ignore_ascii_case(process));
+
+  !listed(&rules.deny) && (rules.allow.is_empty() || listed(&rules.allow))
+}
+
+/// Ducks the player while other apps play audio, and puts it back the way
+/// it was once they've been quiet for a while. Time is unix ms.
+#[derive(Debug, Clone, Default)]
+pub struct DuckingState {
+  /// Process names of the sessions playing, the rules are applied when
+  /// evaluating so they can change in between
+  active: BTreeMap<u64, String>,
+  ducked: Option<Ducked>,
+  quiet_since: Option<i64>,
+}
+
+impl DuckingState {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn handle(&mut self, activity: &AudioActivity) {
+    match activity {
+      AudioActivity::Started { session, process } => self.active.insert(*session, process.clone()),
+      AudioActivity::Stopped { session } => self.active.remove(session),
+    };
+  }
+
+  pub fn is_ducked(&self) -> bool { self.ducked.is_some() }
+
+  pub fn evaluate(&mut self, rules: &DuckingSettings, player: &Status, now: i64) -> Option<DuckAction> {
+    let others_playing = rules.enabled && self.active.values().any(|process| triggers(rules, process));
+
+    if others_playing {
+      self.quiet_since = None;
+      if self.ducked.is_some() {
+        return None;
+      }
+
+      let (ducked, action) = match rules.mode {
+        DuckMode::Lower => {
+          let target = rules.lower_to_percent as f32 / 100.0;
+          let from = player.volume.filter(|volume| *volume > target)?;
+          (Ducked::Lowered { from }, DuckAction::Lower(target))
+        },
+        DuckMode::Pause if player.is_playing => (Ducked::Paused, DuckAction::Pause),
+        DuckMode::Pause => return None,
+      };
+
+      self.ducked = Some(ducked);
+      return Some(action);
+    }
+
+    self.ducked?;
+
+    // Turned off, right away
+    let delay = if rules.enabled { rules.restore_delay_secs as i64 * 1000 } else { 0 };
+    if now - *self.quiet_since.get_or_insert(now) < delay {
+      return None;
+    }
+
+    self.quiet_since = None;
+    match self.ducked.take()? {
+      Ducked::Lowered { from } => Some(DuckAction::Restore(from)),
+      Ducked::Paused => Some(DuckAction::Resume),
+    }
+  }
+
+  /// How long until the player is restored, while waiting for it
+  pub fn next_change_in(&self, rules: &DuckingSettings, now: i64) -> Option<Duration> {
+    self.ducked?;
+    let quiet_for = now - self.quiet_since?;
+
+    Some(Duration::from_millis((rules.restore_delay_secs as i64 * 1000 - quiet_for).max(0) as u64))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rules(mode: DuckMode) -> DuckingSettings {
+    DuckingSettings {
+      enabled: true,
+      mode,
+      ..DuckingSettings::default()
+    }
+  }
+
+  fn player(volume: f32, is_playing: bool) -> Status {
+    Status {
+      is_playing,
+      volume: Some(volume),
+      ..Status::default()
+    }
+  }
+
+  fn started(session: u64, process: &str) -> AudioActivity {
+    AudioActivity::Started {
+      session,
+      process: process.into(),
+    }
+  }
+
+  fn stopped(session: u64) -> AudioActivity { AudioActivity::Stopped { session } }
+
+  #[test]
+  fn lowers_and_restores() {
+    let rules = rules(DuckMode::Lower);
+    let mut state = DuckingState::new();
+
+    state.handle(&started(1, "Teams.exe"));
+    assert_eq!(state.evaluate(&rules, &player(0.8, true), 0), Some(DuckAction::Lower(0.2)));
+    // Our own change
+    assert_eq!(state.evaluate(&rules, &player(0.2, true), 100), None);
+
+    state.handle(&started(2, "chrome.exe"));
+    state.handle(&stopped(1));
+    assert_eq!(state.evaluate(&rules, &player(0.2, true), 200), None);
+
+    state.handle(&stopped(2));
+    assert_eq!(state.evaluate(&rules, &player(0.2, true), 1_000), None);
+    assert_eq!(state.next_change_in(&rules, 2_000), Some(Duration::from_secs(1)));
+
+    // Back before the delay is up
+    state.handle(&started(2, "chrome.exe"));
+    assert_eq!(state.evaluate(&rules, &player(0.2, true), 2_500), None);
+    state.handle(&stopped(2));
+    assert_eq!(state.evaluate(&rules, &player(0.2, true), 3_000), None);
+
+    assert_eq!(state.evaluate(&rules, &player(0.2, true), 5_000), Some(DuckAction::Restore(0.8)));
+    assert!(!state.is_ducked());
+  }
+
+  #[test]
+  fn waits_for_every_session_of_a_process() {
+    let rules = rules(DuckMode::Lower);
+    let mut state = DuckingState::new();
+
+    state.handle(&started(1, "chrome.exe"));
+    state.handle(&started(2, "chrome.exe"));
+    assert_eq!(state.evaluate(&rules, &player(0.8, true), 0), Some(DuckAction::Lower(0.2)));
+
+    // Sessions report stopping more than once, e.g. inactive then expired
+    state.handle(&stopped(1));
+    state.handle(&stopped(1));
+    assert_eq!(state.evaluate(&rules, &player(0.2, true), 5_000), None);
+
+    state.handle(&stopped(2));
+    assert_eq!(state.evaluate(&rules, &player(0.2, true), 5_000), None);
+    assert_eq!(state.evaluate(&rules, &player(0.2, true), 7_000), Some(DuckAction::Restore(0.8)));
+  }
+
+  #[test]
+  fn leaves_quiet_players_alone() {
+    let mut state = DuckingState::new();
+    state.handle(&started(1, "Teams.exe"));
+
+    assert_eq!(state.evaluate(&rules(DuckMode::Lower), &player(0.1, true), 0), None);
+    assert_eq!(state.evaluate(&rules(DuckMode::Pause), &player(0.8, false), 0), None);
+
+    assert_eq!(state.evaluate(&rules(DuckMode::Pause), &player(0.8, true), 0), Some(DuckAction::Pause));
+    state.handle(&stopped(1));
+    assert_eq!(state.evaluate(&rules(DuckMode::Pause), &player(0.8, false), 0), None);
+    assert_eq!(state.evaluate(&rules(DuckMode::Pause), &player(0.8, false), 2_000), Some(DuckAction::Resume));
+  }
+
+  #[test]
+  fn follows_the_allow_and_deny_lists() {
+    let mut rules = rules(DuckMode::Lower);
+    rules.deny = vec!["Discord.exe".into()];
+    assert!(triggers(&rules, "Teams.exe"));
+    assert!(!triggers(&rules, "discord.exe"));
+
+    rules.allow = vec!["Teams.exe".into(), "Discord.exe".into()];
+    assert!(!triggers(&rules, "chrome.exe"));
+    assert!(!triggers(&rules, "Discord.exe"));
+
+    let mut state = DuckingState::new();
+    state.handle(&started(2, "chrome.exe"));
+    assert_eq!(state.evaluate(&rules, &player(0.8, true), 0), None);
+    state.handle(&started(3, "TEAMS.EXE"));
+    assert_eq!(state.evaluate(&rules, &player(0.8, true), 0), Some(DuckAction::Lower(0.2)));
+
+    // Turning it off restores right away
+    rules.enabled = false;
+    assert_eq!(state.evaluate(&rules, &player(0.2, true), 0), Some(DuckAction::Restore(0.8)));
+  }
+}
@@ -0,0 +1,3 @@
+pub mod recorder;
+pub mod store;
+pub mod tracker;
\ No newline at end of file
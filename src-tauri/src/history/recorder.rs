@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use super::store::HistoryStore;
+use super::tracker::PlayTracker;
+use crate::media::lib::EventBus;
+use crate::utils::time::now_ms;
+
+/// Feeds every media event into a `PlayTracker` and stores the finished plays
+pub fn spawn(event_bus: Arc<EventBus>, store: Arc<HistoryStore>) -> JoinHandle<()> {
+  println!("[HistoryRecorder] spawn");
+
+  let mut events = event_bus.0.subscribe();
+
+  tokio::spawn(async move {
+    let mut tracker = PlayTracker::new();
+
+    loop {
+      let event = match events.recv().await {
+        Ok(event) => event,
+        Err(RecvError::Lagged(skipped)) => {
+          println!("[HistoryRecorder] skipped {skipped} events");
+          continue;
+        },
+        Err(RecvError::Closed) => break,
+      };
+
+      let Some(play) = tracker.handle(&event, now_ms()) else {
+        continue;
+      };
+
+      if let Err(err) = store.insert(&play) {
+        println!("[HistoryRecorder] Error: {:?}", err);
+      }
+    }
+  })
+}
@@ -0,0 +1,346 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::tracker::{Play, PlayStatus};
+
+/// Each entry upgrades the schema by one version, in order
+const MIGRATIONS: &[&str] = &[r#"
+  CREATE TABLE plays (
+    id INTEGER PRIMARY KEY,
+    app_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    album TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    listened_ms INTEGER NOT NULL,
+    duration_ms INTEGER,
+    status TEXT NOT NULL
+  );
+  CREATE INDEX plays_started_at ON plays (started_at);
+
+  CREATE TABLE play_artists (
+    play_id INTEGER NOT NULL REFERENCES plays (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    artist TEXT NOT NULL,
+    PRIMARY KEY (play_id, position)
+  );
+  CREATE INDEX play_artists_artist ON play_artists (artist);
+"#];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRange {
+  /// Inclusive lower bound, unix time in milliseconds
+  pub from: Option<i64>,
+  /// Exclusive upper bound, unix time in milliseconds
+  pub to: Option<i64>,
+  pub limit: Option<u32>,
+}
+
+impl HistoryRange {
+  fn bounds(&self) -> (i64, i64, u32) {
+    (
+      self.from.unwrap_or(i64::MIN),
+      self.to.unwrap_or(i64::MAX),
+      self.limit.unwrap_or(50),
+    )
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackStats {
+  pub title: String,
+  pub artists: Vec<String>,
+  pub album: String,
+  pub plays: u32,
+  pub listened_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtistStats {
+  pub artist: String,
+  pub plays: u32,
+  pub listened_ms: i64,
+}
+
+pub struct HistoryStore {
+  connection: Mutex<Connection>,
+}
+
+impl HistoryStore {
+  pub fn open(path: &Path) -> anyhow::Result<Self> {
+    println!("[HistoryStore] open {:?}", path);
+
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+
+    Self::from_connection(Connection::open(path)?)
+  }
+
+  #[cfg(test)]
+  pub fn open_in_memory() -> anyhow::Result<Self> { Self::from_connection(Connection::open_in_memory()?) }
+
+  fn from_connection(mut connection: Connection) -> anyhow::Result<Self> {
+    connection.pragma_update(None, "foreign_keys", true)?;
+    migrate(&mut connection)?;
+
+    Ok(Self {
+      connection: Mutex::new(connection),
+    })
+  }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+
+  pub fn insert(&self, play: &Play) -> anyhow::Result<()> {
+    let mut connection = self.connection.lock().unwrap();
+    let transaction = connection.transaction()?;
+
+    transaction.execute(
+      "INSERT INTO plays (app_id, title, album, started_at, listened_ms, duration_ms, status)
+       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+      params![
+        play.app_id,
+        play.title,
+        play.album,
+        play.started_at,
+        play.listened_ms,
+        play.duration_ms,
+        play.status.as_str(),
+      ],
+    )?;
+
+    let play_id = transaction.last_insert_rowid();
+    for (position, artist) in play.artists.iter().enumerate() {
+      transaction.execute(
+        "INSERT INTO play_artists (play_id, position, artist) VALUES (?1, ?2, ?3)",
+        params![play_id, position, artist],
+      )?;
+    }
+
+    transaction.commit()?;
+    Ok(())
+  }
+
+  /// Most recent plays first
+  pub fn recent(&self, range: &HistoryRange) -> anyhow::Result<Vec<Play>> {
+    let (from, to, limit) = range.bounds();
+    let connection = self.connection.lock().unwrap();
+
+    let mut statement = connection.prepare(
+      "SELECT app_id, title, album, started_at, listened_ms, duration_ms, status,
+         (SELECT group_concat(artist, char(31)) FROM
+           (SELECT artist FROM play_artists WHERE play_id = plays.id ORDER BY position))
+       FROM plays
+       WHERE started_at >= ?1 AND started_at < ?2
+       ORDER BY started_at DESC, id DESC
+       LIMIT ?3",
+    )?;
+
+    let plays = statement
+      .query_map(params![from, to, limit], |row| {
+        Ok(Play {
+          app_id: row.get(0)?,
+          title: row.get(1)?,
+          album: row.get(2)?,
+          started_at: row.get(3)?,
+          listened_ms: row.get(4)?,
+          duration_ms: row.get(5)?,
+          status: PlayStatus::parse(&row.get::<_, String>(6)?),
+          artists: split_artists(row.get(7)?),
+        })
+      })?
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(plays)
+  }
+
+  /// Tracks with the most plays, ties broken by listening time
+  pub fn top_tracks(&self, range: &HistoryRange) -> anyhow::Result<Vec<TrackStats>> {
+    let (from, to, limit) = range.bounds();
+    let connection = self.connection.lock().unwrap();
+
+    let mut statement = connection.prepare(
+      "SELECT title, album, artists, count(*) AS play_count, sum(listened_ms) AS listened
+       FROM (
+         SELECT plays.*,
+           (SELECT group_concat(artist, char(31)) FROM
+             (SELECT artist FROM play_artists WHERE play_id = plays.id ORDER BY position)) AS artists
+         FROM plays
+         WHERE started_at >= ?1 AND started_at < ?2
+       )
+       GROUP BY title, album, artists
+       ORDER BY play_count DESC, listened DESC, title
+       LIMIT ?3",
+    )?;
+
+    let tracks = statement
+      .query_map(params![from, to, limit], |row| {
+        Ok(TrackStats {
+          title: row.get(0)?,
+          album: row.get(1)?,
+          artists: split_artists(row.get(2)?),
+          plays: row.get(3)?,
+          listened_ms: row.get(4)?,
+        })
+      })?
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(tracks)
+  }
+
+  /// Artists with the most plays, counting every credited artist
+  pub fn top_artists(&self, range: &HistoryRange) -> anyhow::Result<Vec<ArtistStats>> {
+    let (from, to, limit) = range.bounds();
+    let connection = self.connection.lock().unwrap();
+
+    let mut statement = connection.prepare(
+      "SELECT play_artists.artist, count(*) AS play_count, sum(plays.listened_ms) AS listened
+       FROM play_artists
+       JOIN plays ON plays.id = play_artists.play_id
+       WHERE plays.started_at >= ?1 AND plays.started_at < ?2
+       GROUP BY play_artists.artist
+       ORDER BY play_count DESC, listened DESC, play_artists.artist
+       LIMIT ?3",
+    )?;
+
+    let artists = statement
+      .query_map(params![from, to, limit], |row| {
+        Ok(ArtistStats {
+          artist: row.get(0)?,
+          plays: row.get(1)?,
+          listened_ms: row.get(2)?,
+        })
+      })?
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(artists)
+  }
+}
+
+fn migrate(connection: &mut Connection) -> anyhow::Result<()> {
+  let version = connection
+    .query_row("PRAGMA user_version", [], |row| row.get::<_, usize>(0))
+    .optional()?
+    .unwrap_or(0);
+
+  for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+    println!("[HistoryStore] migrating to version {}", index + 1);
+
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(migration)?;
+    transaction.pragma_update(None, "user_version", index + 1)?;
+    transaction.commit()?;
+  }
+
+  Ok(())
+}
+
+/// Artists are concatenated with the ASCII unit separator in queries
+fn split_artists(artists: Option<String>) -> Vec<String> {
+  artists
+    .map(|artists| artists.split('\u{1f}').map(str::to_string).collect())
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn play(title: &str, artists: &[&str], started_at: i64, listened_ms: i64) -> Play {
+    Play {
+      app_id: "Spotify.exe".into(),
+      title: title.into(),
+      artists: artists.iter().map(|artist| artist.to_string()).collect(),
+      album: "Album".into(),
+      started_at,
+      listened_ms,
+      duration_ms: Some(200_000),
+      status: PlayStatus::Completed,
+    }
+  }
+
+  fn store() -> HistoryStore {
+    let store = HistoryStore::open_in_memory().unwrap();
+
+    for play in [
+      play("A", &["X"], 1_000, 100),
+      play("B", &["Y", "X"], 2_000, 200),
+      play("A", &["X"], 3_000, 100),
+      play("C", &["Z"], 4_000, 500),
+      play("A", &["X"], 5_000, 100),
+    ] {
+      store.insert(&play).unwrap();
+    }
+
+    store
+  }
+
+  #[test]
+  fn returns_recent_plays_in_range() {
+    let plays = store()
+      .recent(&HistoryRange {
+        from: Some(2_000),
+        to: Some(5_000),
+        limit: None,
+      })
+      .unwrap();
+
+    assert_eq!(plays, vec![
+      play("C", &["Z"], 4_000, 500),
+      play("A", &["X"], 3_000, 100),
+      play("B", &["Y", "X"], 2_000, 200),
+    ]);
+  }
+
+  #[test]
+  fn ranks_top_tracks() {
+    let tracks = store()
+      .top_tracks(&HistoryRange {
+        limit: Some(2),
+        ..Default::default()
+      })
+      .unwrap();
+
+    assert_eq!(
+      tracks
+        .iter()
+        .map(|track| (track.title.as_str(), track.plays, track.listened_ms))
+        .collect::<Vec<_>>(),
+      vec![("A", 3, 300), ("C", 1, 500)]
+    );
+    assert_eq!(tracks[0].artists, vec!["X"]);
+  }
+
+  #[test]
+  fn ranks_top_artists() {
+    let artists = store()
+      .top_artists(&HistoryRange {
+        from: Some(2_000),
+        ..Default::default()
+      })
+      .unwrap();
+
+    assert_eq!(
+      artists
+        .iter()
+        .map(|artist| (artist.artist.as_str(), artist.plays, artist.listened_ms))
+        .collect::<Vec<_>>(),
+      vec![("X", 3, 400), ("Z", 1, 500), ("Y", 1, 200)]
+    );
+  }
+
+  #[test]
+  fn migrations_are_idempotent() {
+    let mut connection = Connection::open_in_memory().unwrap();
+    migrate(&mut connection).unwrap();
+    migrate(&mut connection).unwrap();
+
+    let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+    assert_eq!(version, MIGRATIONS.len());
+  }
+}
@@ -0,0 +1,308 @@
+use serde::{Deserialize, Serialize};
+
+use crate::media::lib::{MediaEvent, MediaSessionData};
+
+/// Share of the track that has to be listened to for a play to count as
+/// completed rather than skipped
+const COMPLETED_RATIO: f64 = 0.8;
+
+/// Position (ms) below which a jump backwards on the same track is treated as
+/// the track being played again
+const REPLAY_POSITION_MS: usize = 3_000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum PlayStatus {
+  Completed,
+  Skipped,
+}
+
+impl PlayStatus {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Completed => "completed",
+      Self::Skipped => "skipped",
+    }
+  }
+
+  pub fn parse(value: &str) -> Self {
+    match value {
+      "completed" => Self::Completed,
+      _ => Self::Skipped,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Play {
+  pub app_id: String,
+  pub title: String,
+  pub artists: Vec<String>,
+  pub album: String,
+  /// Unix time in milliseconds
+  pub started_at: i64,
+  /// Time actually spent playing, in milliseconds
+  pub listened_ms: i64,
+  pub duration_ms: Option<i64>,
+  pub status: PlayStatus,
+}
+
+#[derive(Debug)]
+struct CurrentPlay {
+  title: String,
+  artists: Vec<String>,
+  album: String,
+  started_at: i64,
+  listened_ms: i64,
+  /// When playback last resumed, if currently playing
+  playing_since: Option<i64>,
+  duration_ms: Option<i64>,
+}
+
+impl CurrentPlay {
+  fn is_same_track(&self, data: &MediaSessionData) -> bool {
+    self.title == data.title && self.artists == data.artists && self.album == data.album
+  }
+
+  fn listened_at(&self, now: i64) -> i64 {
+    self.listened_ms + self.playing_since.map(|since| (now - since).max(0)).unwrap_or(0)
+  }
+}
+
+/// Turns the stream of media events into finished plays. Time is passed in
+/// explicitly so the tracker itself has no clock.
+#[derive(Debug, Default)]
+pub struct PlayTracker {
+  app_id: Option<String>,
+  is_playing: bool,
+  current: Option<CurrentPlay>,
+}
+
+impl PlayTracker {
+  pub fn new() -> Self { Self::default() }
+
+  /// Feeds one event into the tracker and returns the play it finished, if any
+  pub fn handle(&mut self, event: &MediaEvent, now: i64) -> Option<Play> {
+    match event {
+      MediaEvent::Connect(app_id) => {
+        let finished = self.finish(now);
+        self.app_id = Some(app_id.clone());
+        finished
+      },
+      MediaEvent::Disconnect(_) => {
+        let finished = self.finish(now);
+        self.app_id = None;
+        self.is_playing = false;
+        finished
+      },
+      MediaEvent::MediaPropertiesChanged(data) => {
+        if self.current.as_ref().is_some_and(|current| current.is_same_track(data)) {
+          return None;
+        }
+
+        let finished = self.finish(now);
+        self.start(data, now);
+        finished
+      },
+      MediaEvent::PlaybackInfoChanged(data) => {
+        self.is_playing = data.is_playing;
+
+        if let Some(current) = self.current.as_mut() {
+          match (data.is_playing, current.playing_since) {
+            (true, None) => current.playing_since = Some(now),
+            (false, Some(since)) => {
+              current.listened_ms += (now - since).max(0);
+              current.playing_since = None;
+            },
+            _ => {},
+          }
+        }
+
+        None
+      },
+      MediaEvent::TimelinePropertiesChanged(data) => {
+        let duration_ms = data.duration_ms();
+        let position_ms = data.position_ms();
+
+        let current = self.current.as_mut()?;
+        if duration_ms > 0 {
+          current.duration_ms = Some(duration_ms as i64);
+        }
+
+        // Jumping back to the start after listening to most of the track is a
+        // repeat, so it becomes a new play
+        let is_replay = position_ms < REPLAY_POSITION_MS
+          && current
+            .duration_ms
+            .is_some_and(|duration| current.listened_at(now) as f64 >= duration as f64 * COMPLETED_RATIO);
+
+        if !is_replay {
+          return None;
+        }
+
+        let (title, artists, album, duration_ms) = (
+          current.title.clone(),
+          current.artists.clone(),
+          current.album.clone(),
+          current.duration_ms,
+        );
+
+        let finished = self.finish(now);
+        self.current = Some(CurrentPlay {
+          title,
+          artists,
+          album,
+          started_at: now,
+          listened_ms: 0,
+          playing_since: self.is_playing.then_some(now),
+          duration_ms,
+        });
+        finished
+      },
+      MediaEvent::VolumeChanged(_) => None,
+    }
+  }
+
+  fn start(&mut self, data: &MediaSessionData, now: i64) {
+    self.current = Some(CurrentPlay {
+      title: data.title.clone(),
+      artists: data.artists.clone(),
+      album: data.album.clone(),
+      started_at: now,
+      listened_ms: 0,
+      playing_since: self.is_playing.then_some(now),
+      duration_ms: None,
+    });
+  }
+
+  fn finish(&mut self, now: i64) -> Option<Play> {
+    let current = self.current.take()?;
+    let listened_ms = current.listened_at(now);
+
+    if listened_ms <= 0 {
+      return None;
+    }
+
+    let status = match current.duration_ms {
+      Some(duration) if listened_ms as f64 >= duration as f64 * COMPLETED_RATIO => PlayStatus::Completed,
+      _ => PlayStatus::Skipped,
+    };
+
+    Some(Play {
+      app_id: self.app_id.clone().unwrap_or_default(),
+      title: current.title,
+      artists: current.artists,
+      album: current.album,
+      started_at: current.started_at,
+      listened_ms,
+      duration_ms: current.duration_ms,
+      status,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::{MediaPlaybackData, MediaTimelineData};
+
+  fn track(title: &str) -> MediaEvent {
+    MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture(title, &["Artist"]))
+  }
+
+  fn playing(is_playing: bool) -> MediaEvent {
+    MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing })
+  }
+
+  fn timeline(position_ms: usize, duration_ms: usize) -> MediaEvent {
+    MediaEvent::TimelinePropertiesChanged(MediaTimelineData::fixture(position_ms, duration_ms))
+  }
+
+  fn run(events: &[(i64, MediaEvent)]) -> Vec<Play> {
+    let mut tracker = PlayTracker::new();
+    tracker.handle(&MediaEvent::Connect("Spotify.exe".into()), 0);
+
+    events
+      .iter()
+      .filter_map(|(now, event)| tracker.handle(event, *now))
+      .collect()
+  }
+
+  #[test]
+  fn only_counts_playing_time() {
+    let plays = run(&[
+      (0, playing(true)),
+      (0, track("A")),
+      (0, timeline(0, 100_000)),
+      (30_000, playing(false)),
+      (90_000, playing(true)),
+      (100_000, track("B")),
+    ]);
+
+    assert_eq!(plays.len(), 1);
+    assert_eq!(plays[0].title, "A");
+    assert_eq!(plays[0].app_id, "Spotify.exe");
+    assert_eq!(plays[0].listened_ms, 40_000);
+    assert_eq!(plays[0].duration_ms, Some(100_000));
+    assert_eq!(plays[0].status, PlayStatus::Skipped);
+  }
+
+  #[test]
+  fn marks_completed_plays() {
+    let plays = run(&[
+      (0, track("A")),
+      (0, timeline(0, 100_000)),
+      (0, playing(true)),
+      (95_000, track("B")),
+      (96_000, playing(false)),
+      (97_000, MediaEvent::Disconnect("Spotify.exe".into())),
+    ]);
+
+    assert_eq!(plays.iter().map(|play| (play.title.as_str(), play.status)).collect::<Vec<_>>(), vec![
+      ("A", PlayStatus::Completed),
+      ("B", PlayStatus::Skipped),
+    ]);
+    assert_eq!(plays[1].listened_ms, 1_000);
+  }
+
+  #[test]
+  fn ignores_repeated_properties_for_the_same_track() {
+    let plays = run(&[
+      (0, track("A")),
+      (0, playing(true)),
+      (10_000, track("A")),
+      (20_000, track("A")),
+      (30_000, track("B")),
+    ]);
+
+    assert_eq!(plays.len(), 1);
+    assert_eq!(plays[0].listened_ms, 30_000);
+  }
+
+  #[test]
+  fn ignores_tracks_that_never_played() {
+    let plays = run(&[(0, track("A")), (10_000, track("B")), (20_000, track("C"))]);
+
+    assert!(plays.is_empty());
+  }
+
+  #[test]
+  fn splits_repeats_into_separate_plays() {
+    let plays = run(&[
+      (0, track("A")),
+      (0, playing(true)),
+      (0, timeline(0, 100_000)),
+      (100_000, timeline(0, 100_000)),
+      (150_000, MediaEvent::Disconnect("Spotify.exe".into())),
+    ]);
+
+    assert_eq!(
+      plays
+        .iter()
+        .map(|play| (play.started_at, play.listened_ms, play.status))
+        .collect::<Vec<_>>(),
+      vec![(0, 100_000, PlayStatus::Completed), (100_000, 50_000, PlayStatus::Skipped)]
+    );
+  }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+
+/// Modifiers in the order they're written out
+const MODIFIERS: [&str; 4] = ["Control", "Alt", "Shift", "Super"];
+
+/// Keys that are fine on their own, nothing types them
+const STANDALONE: &[&str] = &[
+  "MediaPlayPause",
+  "MediaStop",
+  "MediaTrackNext",
+  "MediaTrackPrevious",
+  "AudioVolumeUp",
+  "AudioVolumeDown",
+  "AudioVolumeMute",
+];
+
+/// A global shortcut in Tauri's accelerator syntax, normalized so that
+/// equivalent spellings ("ctrl+alt+p", "Alt+Control+P") compare equal
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Accelerator {
+  modifiers: Vec<&'static str>,
+  key: String,
+}
+
+impl Accelerator {
+  pub fn parse(text: &str) -> Result<Self, String> {
+    if text.trim().is_empty() {
+      return Err("Empty shortcut".into());
+    }
+
+    let parts = text.split('+').map(str::trim).collect::<Vec<_>>();
+    let (key, modifiers) = parts.split_last().unwrap();
+
+    let mut parsed = vec![];
+    for modifier in modifiers {
+      let modifier = parse_modifier(modifier).ok_or_else(|| format!("Unknown modifier '{modifier}'"))?;
+      if parsed.contains(&modifier) {
+        return Err(format!("'{modifier}' is used twice"));
+      }
+      parsed.push(modifier);
+    }
+    parsed.sort_by_key(|modifier| MODIFIERS.iter().position(|known| known == modifier));
+
+    let key = parse_key(key).ok_or_else(|| format!("Unknown key '{key}'"))?;
+    if parsed.is_empty() && !STANDALONE.contains(&key.as_str()) && !is_spare_function_key(&key) {
+      return Err(format!("'{key}' needs a modifier, it would stop working everywhere else"));
+    }
+
+    Ok(Self { modifiers: parsed, key })
+  }
+}
+
+impl fmt::Display for Accelerator {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for modifier in &self.modifiers {
+      write!(f, "{modifier}+")?;
+    }
+    write!(f, "{}", self.key)
+  }
+}
+
+fn parse_modifier(modifier: &str) -> Option<&'static str> {
+  match modifier.to_lowercase().as_str() {
+    "ctrl" | "control" => Some("Control"),
+    "alt" | "option" => Some("Alt"),
+    "shift" => Some("Shift"),
+    "super" | "cmd" | "command" | "meta" | "win" => Some("Super"),
+    // Resolved here so "CmdOrCtrl+P" and "Ctrl+P" are seen as the same
+    "cmdorctrl" | "commandorcontrol" if cfg!(target_os = "macos") => Some("Super"),
+    "cmdorctrl" | "commandorcontrol" => Some("Control"),
+    _ => None,
+  }
+}
+
+fn parse_key(key: &str) -> Option<String> {
+  let lower = key.to_lowercase();
+
+  if key.chars().count() == 1 && key.chars().all(|c| c.is_ascii_alphanumeric()) {
+    return Some(key.to_uppercase());
+  }
+
+  if let Some(number) = lower.strip_prefix('f').and_then(|number| number.parse::<u8>().ok()) {
+    return (1..=24).contains(&number).then(|| format!("F{number}"));
+  }
+
+  let named = [
+    "Space", "Tab", "Enter", "Backspace", "Delete", "Insert", "Home", "End", "PageUp", "PageDown", "Escape", "Up",
+    "Down", "Left", "Right", "Plus", "Minus", "Comma", "Period",
+  ];
+
+  named
+    .iter()
+    .chain(STANDALONE)
+    .find(|name| name.to_lowercase() == lower)
+    .map(|name| name.to_string())
+    .or(match lower.as_str() {
+      "return" => Some("Enter".into()),
+      "esc" => Some("Escape".into()),
+      "arrowup" => Some("Up".into()),
+      "arrowdown" => Some("Down".into()),
+      "arrowleft" => Some("Left".into()),
+      "arrowright" => Some("Right".into()),
+      _ => None,
+    })
+}
+
+/// F13 to F24 exist on few keyboards and are free for this
+fn is_spare_function_key(key: &str) -> bool {
+  key
+    .strip_prefix('F')
+    .and_then(|number| number.parse::<u8>().ok())
+    .is_some_and(|number| number >= 13)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn normalizes_accelerators() {
+    let cases = [
+      ("ctrl+alt+p", "Control+Alt+P"),
+      ("Alt + Control + p", "Control+Alt+P"),
+      ("Shift+Super+arrowright", "Shift+Super+Right"),
+      ("Cmd+Esc", "Super+Escape"),
+      ("MediaPlayPause", "MediaPlayPause"),
+      ("f14", "F14"),
+    ];
+
+    for (text, expected) in cases {
+      assert_eq!(Accelerator::parse(text).map(|accelerator| accelerator.to_string()), Ok(expected.into()), "{text}");
+    }
+
+    assert_eq!(Accelerator::parse("CmdOrCtrl+P"), Accelerator::parse(if cfg!(target_os = "macos") {
+      "Super+P"
+    } else {
+      "Ctrl+P"
+    }));
+  }
+
+  #[test]
+  fn rejects_invalid_accelerators() {
+    let cases = [
+      ("", "Empty shortcut"),
+      ("Ctrl+", "Unknown key ''"),
+      ("Hyper+P", "Unknown modifier 'Hyper'"),
+      ("Ctrl+Control+P", "'Control' is used twice"),
+      ("Ctrl+F25", "Unknown key 'F25'"),
+      ("P", "'P' needs a modifier, it would stop working everywhere else"),
+      ("Space", "'Space' needs a modifier, it would stop working everywhere else"),
+    ];
+
+    for (text, expected) in cases {
+      assert_eq!(Accelerator::parse(text), Err(expected.into()), "{text}");
+    }
+  }
+}
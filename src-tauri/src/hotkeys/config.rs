@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::accelerator::Accelerator;
+use crate::settings::schema::SettingsError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum HotkeyAction {
+  PlayPause,
+  Next,
+  Previous,
+  /// Relative to the current position, negative goes back
+  Seek { seconds: i32 },
+  /// Steps of `volume.stepPercent` along the volume curve, negative is
+  /// quieter
+  Volume { steps: i32 },
+  ToggleMute,
+  ToggleWindow,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Hotkey {
+  /// Tauri accelerator, e.g. `Ctrl+Alt+Space`
+  pub accelerator: String,
+  pub action: HotkeyAction,
+}
+
+/// What `Settings::hotkeys` starts out with
+pub fn default_hotkeys() -> Vec<Hotkey> {
+  let hotkey = |accelerator: &str, action| Hotkey {
+    accelerator: accelerator.into(),
+    action,
+  };
+
+  vec![
+    hotkey("Ctrl+Alt+Space", HotkeyAction::PlayPause),
+    hotkey("Ctrl+Alt+Right", HotkeyAction::Next),
+    hotkey("Ctrl+Alt+Left", HotkeyAction::Previous),
+    hotkey("Ctrl+Alt+Shift+Right", HotkeyAction::Seek { seconds: 10 }),
+    hotkey("Ctrl+Alt+Shift+Left", HotkeyAction::Seek { seconds: -10 }),
+    hotkey("Ctrl+Alt+Up", HotkeyAction::Volume { steps: 1 }),
+    hotkey("Ctrl+Alt+Down", HotkeyAction::Volume { steps: -1 }),
+    hotkey("AudioVolumeMute", HotkeyAction::ToggleMute),
+    hotkey("Ctrl+Alt+W", HotkeyAction::ToggleWindow),
+  ]
+}
+
+/// Normalizes every accelerator, or reports each one that's invalid or
+/// bound more than once
+pub fn validate(hotkeys: &[Hotkey]) -> Result<Vec<(Accelerator, HotkeyAction)>, Vec<SettingsError>> {
+  let mut errors = vec![];
+  let mut parsed = vec![];
+  let mut first_use = HashMap::new();
+
+  for (index, hotkey) in hotkeys.iter().enumerate() {
+    let error = |message| SettingsError {
+      field: format!("hotkeys.{index}"),
+      message,
+    };
+
+    let accelerator = match Accelerator::parse(&hotkey.accelerator) {
+      Ok(accelerator) => accelerator,
+      Err(message) => {
+        errors.push(error(message));
+        continue;
+      },
+    };
+
+    if let Some(first) = first_use.get(&accelerator) {
+      errors.push(error(format!("{accelerator} is already used by hotkeys.{first}")));
+      continue;
+    }
+
+    first_use.insert(accelerator.clone(), index);
+    parsed.push((accelerator, hotkey.action.clone()));
+  }
+
+  if errors.is_empty() {
+    Ok(parsed)
+  } else {
+    Err(errors)
+  }
+}
+
+/// The accelerators as `validate` normalized them
+pub fn normalized(hotkeys: Vec<(Accelerator, HotkeyAction)>) -> Vec<Hotkey> {
+  hotkeys
+    .into_iter()
+    .map(|(accelerator, action)| Hotkey {
+      accelerator: accelerator.to_string(),
+      action,
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn defaults_are_valid() { assert_eq!(validate(&default_hotkeys()).map(|hotkeys| hotkeys.len()), Ok(9)); }
+
+  #[test]
+  fn reports_invalid_and_conflicting_hotkeys() {
+    let hotkeys: Vec<Hotkey> = serde_json::from_str(
+      r#"[
+        {"accelerator": "Ctrl+Alt+P", "action": {"kind": "playPause"}},
+        {"accelerator": "Ctrl+Nope", "action": {"kind": "next"}},
+        {"accelerator": "alt+ctrl+p", "action": {"kind": "volume", "steps": 1}}
+      ]"#,
+    )
+    .unwrap();
+
+    assert_eq!(validate(&hotkeys), Err(vec![
+      SettingsError {
+        field: "hotkeys.1".into(),
+        message: "Unknown key 'Nope'".into(),
+      },
+      SettingsError {
+        field: "hotkeys.2".into(),
+        message: "Control+Alt+P is already used by hotkeys.0".into(),
+      },
+    ]));
+  }
+}
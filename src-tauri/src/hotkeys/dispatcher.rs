@@ -0,0 +1,106 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, bail};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use super::config::HotkeyAction;
+use crate::control::state::ControlState;
+use crate::media::lib::{MediaEvent, TICKS_PER_MS};
+use crate::router::media::{MediaControl, Method};
+use crate::utils::time::now_ms;
+
+/// Turns hotkey actions into `Method`s. Toggling and relative seeks depend
+/// on the current state, which is followed from the media events.
+pub struct HotkeyDispatcher {
+  control: Arc<dyn MediaControl>,
+  state: Mutex<ControlState>,
+}
+
+impl HotkeyDispatcher {
+  pub fn new(control: Arc<dyn MediaControl>) -> Self {
+    Self {
+      control,
+      state: Mutex::new(ControlState::new()),
+    }
+  }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+
+  pub fn track(self: &Arc<Self>, mut events: broadcast::Receiver<MediaEvent>) -> JoinHandle<()> {
+    let dispatcher = self.clone();
+
+    tokio::spawn(async move {
+      loop {
+        match events.recv().await {
+          Ok(event) => dispatcher.state.lock().unwrap().handle(&event, now_ms()),
+          Err(RecvError::Lagged(_)) => continue,
+          Err(RecvError::Closed) => break,
+        }
+      }
+    })
+  }
+
+  pub fn method(&self, action: &HotkeyAction, now: i64) -> anyhow::Result<Method> {
+    let status = self.state.lock().unwrap().status(now);
+
+    Ok(match action {
+      HotkeyAction::PlayPause if status.is_playing => Method::Pause,
+      HotkeyAction::PlayPause => Method::Play,
+      HotkeyAction::Next => Method::Next,
+      HotkeyAction::Previous => Method::Previous,
+      HotkeyAction::Seek { seconds } => {
+        let position = status.position_ms.ok_or_else(|| anyhow!("Nothing is playing"))?;
+        let duration = status.duration_ms.map_or(i64::MAX, |duration| duration as i64);
+        let target = (position as i64 + *seconds as i64 * 1000).clamp(0, duration);
+
+        Method::SetPlaybackPosition(target as usize * TICKS_PER_MS)
+      },
+      HotkeyAction::Volume { steps } => Method::StepVolume(*steps),
+      HotkeyAction::ToggleMute => Method::ToggleMute,
+      HotkeyAction::ToggleWindow => bail!("Toggling the window isn't a media method"),
+    })
+  }
+
+  pub fn dispatch(&self, action: &HotkeyAction) -> anyhow::Result<()> { self.control.invoke(self.method(action, now_ms())?) }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::{MediaPlaybackData, MediaSessionData, MediaTimelineData};
+  use crate::router::media::stub::RecordingControl;
+
+  #[test]
+  fn maps_actions_against_the_current_state() {
+    let control = Arc::new(RecordingControl::default());
+    let dispatcher = HotkeyDispatcher::new(control.clone());
+
+    assert!(dispatcher.dispatch(&HotkeyAction::Seek { seconds: 10 }).is_err());
+
+    {
+      let mut state = dispatcher.state.lock().unwrap();
+      state.handle(&MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A"])), 0);
+      state.handle(&MediaEvent::TimelinePropertiesChanged(MediaTimelineData::fixture(5_000, 60_000)), 0);
+    }
+
+    let method = |action| dispatcher.method(&action, 0).unwrap();
+    assert_eq!(method(HotkeyAction::PlayPause), Method::Play);
+    assert_eq!(method(HotkeyAction::Seek { seconds: 10 }), Method::SetPlaybackPosition(150_000_000));
+    assert_eq!(method(HotkeyAction::Seek { seconds: -10 }), Method::SetPlaybackPosition(0));
+    assert_eq!(method(HotkeyAction::Seek { seconds: 600 }), Method::SetPlaybackPosition(600_000_000));
+    assert_eq!(method(HotkeyAction::Volume { steps: -2 }), Method::StepVolume(-2));
+    assert_eq!(method(HotkeyAction::ToggleMute), Method::ToggleMute);
+
+    dispatcher
+      .state
+      .lock()
+      .unwrap()
+      .handle(&MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing: true }), 0);
+    dispatcher.dispatch(&HotkeyAction::PlayPause).unwrap();
+    dispatcher.dispatch(&HotkeyAction::Next).unwrap();
+
+    assert_eq!(*control.invocations.lock().unwrap(), vec![Method::Pause, Method::Next]);
+  }
+}
@@ -0,0 +1,293 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+
+use super::accelerator::Accelerator;
+use super::config::{normalized, validate, Hotkey, HotkeyAction};
+use crate::settings::schema::SettingsError;
+use crate::settings::store::{SettingsStore, UpdateError};
+use crate::utils::fs::{backup_path, read_json};
+
+/// Where hotkeys get registered with the OS, Tauri's global shortcut manager
+/// outside of tests
+pub trait ShortcutRegistry: Send {
+  fn register(&mut self, accelerator: &str, action: HotkeyAction) -> anyhow::Result<()>;
+
+  fn unregister_all(&mut self) -> anyhow::Result<()>;
+}
+
+/// Keeps the registered shortcuts in sync with `Settings::hotkeys`
+pub struct HotkeyManager {
+  settings: Arc<SettingsStore>,
+  /// Only available once the app is running
+  registered: Mutex<Option<Registered>>,
+}
+
+struct Registered {
+  registry: Box<dyn ShortcutRegistry>,
+  /// What the registry holds right now
+  hotkeys: Vec<Hotkey>,
+}
+
+impl Registered {
+  /// Registers `hotkeys` in place of the current ones, which come back if
+  /// any of them fails
+  fn replace(&mut self, hotkeys: &[Hotkey]) -> Result<(), Vec<SettingsError>> {
+    if let Err(errors) = register_all(self.registry.as_mut(), &validate(hotkeys)?) {
+      if let Ok(previous) = validate(&self.hotkeys) {
+        let _ = register_all(self.registry.as_mut(), &previous);
+      }
+      return Err(errors);
+    }
+
+    self.hotkeys = hotkeys.to_vec();
+    Ok(())
+  }
+}
+
+impl HotkeyManager {
+  pub fn new(settings: Arc<SettingsStore>) -> Self {
+    Self {
+      settings,
+      registered: Mutex::new(None),
+    }
+  }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+
+  /// Registers the hotkeys from the settings, skipping the ones that fail
+  pub fn attach(&self, mut registry: Box<dyn ShortcutRegistry>) -> Result<(), Vec<SettingsError>> {
+    let hotkeys = self.settings.get().hotkeys;
+    let result = validate(&hotkeys).and_then(|parsed| register_all(registry.as_mut(), &parsed));
+
+    *self.registered.lock().unwrap() = Some(Registered { registry, hotkeys });
+    result
+  }
+
+  /// Registers and stores `hotkeys`. Nothing changes if any hotkey is
+  /// invalid, conflicts with another or is taken by another app.
+  pub fn update(&self, hotkeys: Vec<Hotkey>) -> Result<Vec<Hotkey>, UpdateError> {
+    let hotkeys = normalized(validate(&hotkeys)?);
+    let mut registered = self.registered.lock().unwrap();
+    let previous = registered.as_ref().map(|registered| registered.hotkeys.clone());
+
+    if let Some(registered) = registered.as_mut() {
+      registered.replace(&hotkeys)?;
+    }
+
+    let mut settings = self.settings.get();
+    settings.hotkeys = hotkeys.clone();
+    if let Err(err) = self.settings.update(settings) {
+      if let (Some(registered), Some(previous)) = (registered.as_mut(), previous) {
+        let _ = registered.replace(&previous);
+      }
+      return Err(err);
+    }
+
+    Ok(hotkeys)
+  }
+
+  /// Follows hotkeys edited into the settings file
+  pub fn track(self: &Arc<Self>) -> JoinHandle<()> {
+    let manager = self.clone();
+    let mut settings = self.settings.subscribe();
+
+    tokio::spawn(async move {
+      while settings.changed().await.is_ok() {
+        let hotkeys = settings.borrow_and_update().hotkeys.clone();
+
+        let result = match manager.registered.lock().unwrap().as_mut() {
+          Some(registered) if registered.hotkeys != hotkeys => registered.replace(&hotkeys),
+          _ => Ok(()),
+        };
+
+        if let Err(errors) = result {
+          for error in errors {
+            println!("[Hotkeys] {}", error);
+          }
+        }
+      }
+    })
+  }
+}
+
+/// The `hotkeys.json` of earlier versions
+#[derive(Deserialize)]
+struct LegacyHotkeys {
+  hotkeys: Vec<Hotkey>,
+}
+
+/// Moves the hotkeys from the `hotkeys.json` of earlier versions into the
+/// settings. The file is only read once, it's kept as `hotkeys.json.bak`
+/// whether or not it could be used.
+pub fn import_legacy(path: &Path, settings: &SettingsStore) -> anyhow::Result<()> {
+  let legacy = read_json::<LegacyHotkeys>(path);
+  if matches!(legacy, Ok(None)) {
+    return Ok(());
+  }
+
+  std::fs::rename(path, backup_path(path))?;
+
+  if let Some(legacy) = legacy? {
+    let mut updated = settings.get();
+    updated.hotkeys = legacy.hotkeys;
+    settings.update(updated)?;
+  }
+
+  Ok(())
+}
+
+fn register_all(registry: &mut dyn ShortcutRegistry, hotkeys: &[(Accelerator, HotkeyAction)]) -> Result<(), Vec<SettingsError>> {
+  if let Err(err) = registry.unregister_all() {
+    println!("[Hotkeys] unregister | Error: {:?}", err);
+  }
+
+  let errors = hotkeys
+    .iter()
+    .enumerate()
+    .filter_map(|(index, (accelerator, action))| {
+      registry
+        .register(&accelerator.to_string(), action.clone())
+        .err()
+        .map(|err| SettingsError {
+          field: format!("hotkeys.{index}"),
+          message: format!("{accelerator} is taken by another application ({err})"),
+        })
+    })
+    .collect::<Vec<_>>();
+
+  if errors.is_empty() {
+    Ok(())
+  } else {
+    Err(errors)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+  use std::time::Duration;
+
+  use anyhow::bail;
+
+  use super::*;
+
+  /// Pretends `taken` is registered by some other app
+  struct StubRegistry {
+    taken: &'static str,
+    registered: Arc<Mutex<Vec<String>>>,
+  }
+
+  impl ShortcutRegistry for StubRegistry {
+    fn register(&mut self, accelerator: &str, _: HotkeyAction) -> anyhow::Result<()> {
+      if accelerator == self.taken {
+        bail!("already registered");
+      }
+
+      self.registered.lock().unwrap().push(accelerator.into());
+      Ok(())
+    }
+
+    fn unregister_all(&mut self) -> anyhow::Result<()> {
+      self.registered.lock().unwrap().clear();
+      Ok(())
+    }
+  }
+
+  fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("media-widget-hotkeys-{name}-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+  }
+
+  fn hotkeys(accelerators: &[&str]) -> Vec<Hotkey> {
+    accelerators
+      .iter()
+      .map(|accelerator| Hotkey {
+        accelerator: accelerator.to_string(),
+        action: HotkeyAction::Next,
+      })
+      .collect()
+  }
+
+  fn attached(settings: &Arc<SettingsStore>) -> (Arc<HotkeyManager>, Arc<Mutex<Vec<String>>>) {
+    let registered = Arc::new(Mutex::new(vec![]));
+    let manager = HotkeyManager::new(settings.clone()).arced();
+
+    manager
+      .attach(Box::new(StubRegistry {
+        taken: "Control+Alt+T",
+        registered: registered.clone(),
+      }))
+      .unwrap();
+
+    (manager, registered)
+  }
+
+  #[test]
+  fn registers_validates_and_stores_hotkeys() {
+    let path = temp_path("update");
+    let settings = SettingsStore::load(path.clone()).arced();
+    let (manager, registered) = attached(&settings);
+    assert_eq!(registered.lock().unwrap().len(), 9);
+
+    let updated = manager.update(hotkeys(&["alt+ctrl+n", "Shift+Super+N"])).unwrap();
+    assert_eq!(updated, hotkeys(&["Control+Alt+N", "Shift+Super+N"]));
+    assert_eq!(*registered.lock().unwrap(), vec!["Control+Alt+N", "Shift+Super+N"]);
+    assert_eq!(SettingsStore::load(path.clone()).get().hotkeys, updated);
+
+    // Taken by another app, the previous hotkeys stay
+    let error = manager.update(hotkeys(&["Ctrl+Alt+P", "Ctrl+Alt+T"])).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid settings\nhotkeys.1: Control+Alt+T is taken by another application (already registered)");
+    assert_eq!(*registered.lock().unwrap(), vec!["Control+Alt+N", "Shift+Super+N"]);
+    assert_eq!(settings.get().hotkeys, updated);
+
+    assert!(manager.update(hotkeys(&["Ctrl+N", "Control+N"])).is_err());
+
+    std::fs::remove_file(&path).unwrap();
+  }
+
+  #[tokio::test]
+  async fn follows_the_settings() {
+    let path = temp_path("follow");
+    let settings = SettingsStore::load(path.clone()).arced();
+    let (manager, registered) = attached(&settings);
+    let tracker = manager.track();
+
+    let mut edited = settings.get();
+    edited.hotkeys = hotkeys(&["Ctrl+Alt+N"]);
+    settings.update(edited).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(2), async {
+      while *registered.lock().unwrap() != vec!["Control+Alt+N"] {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+      }
+    })
+    .await
+    .unwrap();
+
+    tracker.abort();
+    std::fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn imports_the_legacy_file() {
+    let path = temp_path("import-settings");
+    let legacy = temp_path("import-legacy");
+    let settings = SettingsStore::load(path.clone());
+
+    std::fs::write(&legacy, r#"{"hotkeys": [{"accelerator": "Ctrl+Alt+N", "action": {"kind": "next"}}]}"#).unwrap();
+    import_legacy(&legacy, &settings).unwrap();
+    assert_eq!(settings.get().hotkeys, hotkeys(&["Ctrl+Alt+N"]));
+    assert!(!legacy.exists());
+
+    // Only once
+    import_legacy(&legacy, &settings).unwrap();
+    assert_eq!(settings.get().hotkeys, hotkeys(&["Ctrl+Alt+N"]));
+
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(backup_path(&legacy)).unwrap();
+  }
+}
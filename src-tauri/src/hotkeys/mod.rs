@@ -0,0 +1,5 @@
+pub mod accelerator;
+pub mod config;
+pub mod dispatcher;
+pub mod manager;
+pub mod shortcuts;
\ No newline at end of file
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use tauri::GlobalShortcutManager;
+
+use super::config::HotkeyAction;
+use super::manager::ShortcutRegistry;
+
+/// Registers hotkeys through Tauri's global shortcut API
+pub struct TauriShortcuts<M: GlobalShortcutManager> {
+  manager: M,
+  on_action: Arc<dyn Fn(&HotkeyAction) + Send + Sync>,
+}
+
+impl<M: GlobalShortcutManager> TauriShortcuts<M> {
+  pub fn new(manager: M, on_action: Arc<dyn Fn(&HotkeyAction) + Send + Sync>) -> Self { Self { manager, on_action } }
+}
+
+impl<M: GlobalShortcutManager> ShortcutRegistry for TauriShortcuts<M> {
+  fn register(&mut self, accelerator: &str, action: HotkeyAction) -> anyhow::Result<()> {
+    let on_action = self.on_action.clone();
+    self.manager.register(accelerator, move || on_action(&action))?;
+    Ok(())
+  }
+
+  fn unregister_all(&mut self) -> anyhow::Result<()> {
+    self.manager.unregister_all()?;
+    Ok(())
+  }
+}
@@ -0,0 +1,57 @@
+use crate::autostart::launcher::AUTOSTART_ARG;
+use crate::utils::spotify_uri::SpotifyUri;
+
+/// What a launch asks of the running instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstanceCommand {
+  /// Brings the widget to the front
+  Show,
+  /// Hides the widget when it's showing, otherwise brings it to the front
+  Toggle,
+  /// Opens a `spotify:` URI or an open.spotify.com link in the desktop app
+  Open(SpotifyUri),
+}
+
+/// Reads the commands out of the launch arguments, without the executable,
+/// anything unknown is skipped
+pub fn parse_args(args: &[String]) -> Vec<InstanceCommand> {
+  args
+    .iter()
+    .filter_map(|arg| match arg.as_str() {
+      "--show" => Some(InstanceCommand::Show),
+      "--toggle" => Some(InstanceCommand::Toggle),
+      // Only matters to the instance it starts
+      AUTOSTART_ARG => None,
+      arg => match arg.parse() {
+        Ok(uri) => Some(InstanceCommand::Open(uri)),
+        Err(err) => {
+          println!("[Instance] Ignoring argument '{}': {}", arg, err);
+          None
+        },
+      },
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn args(line: &str) -> Vec<String> { line.split_whitespace().map(String::from).collect() }
+
+  #[test]
+  fn parses_flags_and_uris() {
+    assert_eq!(parse_args(&args("")), vec![]);
+    assert_eq!(
+      parse_args(&args(
+        "--toggle spotify:track:4uLU6hMCjMI75M1A2tKUQC --verbose https://open.spotify.com/album/1DFixLWuPkv3KT3TnV35m3 --show"
+      )),
+      vec![
+        InstanceCommand::Toggle,
+        InstanceCommand::Open("spotify:track:4uLU6hMCjMI75M1A2tKUQC".parse().unwrap()),
+        InstanceCommand::Open("spotify:album:1DFixLWuPkv3KT3TnV35m3".parse().unwrap()),
+        InstanceCommand::Show,
+      ]
+    );
+  }
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::args::InstanceCommand;
+use crate::utils::opener::Opener;
+use crate::utils::window::toggle_window;
+
+/// Carries out what this and later launches asked for, once the window
+/// exists
+pub fn spawn(
+  app: AppHandle,
+  opener: Arc<dyn Opener>,
+  mut commands: mpsc::UnboundedReceiver<Vec<InstanceCommand>>,
+) -> JoinHandle<()> {
+  tokio::spawn(async move {
+    while let Some(commands) = commands.recv().await {
+      for command in commands {
+        if let Err(err) = execute(&app, opener.as_ref(), &command) {
+          println!("[Instance] {:?} | Error: {:?}", command, err);
+        }
+      }
+    }
+  })
+}
+
+fn execute(app: &AppHandle, opener: &dyn Opener, command: &InstanceCommand) -> anyhow::Result<()> {
+  let window = app.get_window("main");
+
+  match (command, window) {
+    // Always hand the 'spotify:' form to the OS so the desktop app opens
+    (InstanceCommand::Open(uri), _) => opener.open(&uri.to_uri())?,
+    (InstanceCommand::Toggle, Some(window)) => toggle_window(&window)?,
+    (InstanceCommand::Show, Some(window)) => {
+      window.show()?;
+      window.set_focus()?;
+    },
+    (_, None) => {},
+  }
+
+  Ok(())
+}
@@ -0,0 +1,148 @@
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::bail;
+
+use super::server::forward;
+
+/// How often a launch tries to reach the instance holding the lock, it may
+/// still be starting up
+const FORWARD_ATTEMPTS: u32 = 10;
+const FORWARD_RETRY: Duration = Duration::from_millis(100);
+
+/// Next to the sockets, so it's cleared with them on logout
+pub fn lock_path() -> PathBuf { crate::utils::fs::runtime_dir().join("media-widget.lock") }
+
+/// Held by the one running instance. The OS lets go of it when the process
+/// ends, however it ends, so a crash or a hard exit can't leave a stale lock
+#[derive(Debug)]
+pub struct InstanceLock {
+  _file: File,
+}
+
+impl InstanceLock {
+  /// Takes the lock unless some instance already has it
+  pub fn try_acquire(path: &Path) -> std::io::Result<Option<Self>> {
+    let Some(mut file) = lock_file(path)? else {
+      return Ok(None);
+    };
+
+    // Only informational, for whoever finds the file
+    file.set_len(0)?;
+    writeln!(file, "{}", std::process::id())?;
+
+    Ok(Some(Self { _file: file }))
+  }
+}
+
+#[cfg(unix)]
+fn lock_file(path: &Path) -> std::io::Result<Option<File>> {
+  use std::os::unix::io::AsRawFd;
+
+  // Not truncated yet, the pid in it may be the running instance's
+  let file = OpenOptions::new().write(true).create(true).truncate(false).open(path)?;
+  if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+    return Ok(Some(file));
+  }
+
+  match std::io::Error::last_os_error() {
+    err if err.kind() == ErrorKind::WouldBlock => Ok(None),
+    err => Err(err),
+  }
+}
+
+#[cfg(windows)]
+fn lock_file(path: &Path) -> std::io::Result<Option<File>> {
+  use std::os::windows::fs::OpenOptionsExt;
+
+  /// ERROR_SHARING_VIOLATION, another handle has the file open
+  const SHARING_VIOLATION: i32 = 32;
+
+  // Not sharing the file with anyone makes the handle itself the lock
+  match OpenOptions::new().write(true).create(true).truncate(false).share_mode(0).open(path) {
+    Ok(file) => Ok(Some(file)),
+    Err(err) if err.raw_os_error() == Some(SHARING_VIOLATION) => Ok(None),
+    Err(err) => Err(err),
+  }
+}
+
+pub enum Claim {
+  /// This is the only instance
+  Primary(InstanceLock),
+  /// Another instance took the arguments over
+  Forwarded,
+}
+
+/// Becomes the running instance, or hands `args` to the one that already
+/// is. The lock holder may still be starting up, or just quitting
+pub async fn claim(path: &Path, endpoint: &Path, args: &[String]) -> anyhow::Result<Claim> {
+  let mut attempt = 1;
+
+  loop {
+    if let Some(lock) = InstanceLock::try_acquire(path)? {
+      return Ok(Claim::Primary(lock));
+    }
+
+    match forward(endpoint, args).await {
+      Ok(()) => return Ok(Claim::Forwarded),
+      Err(err) if attempt == FORWARD_ATTEMPTS => bail!("The running instance isn't answering: {}", err),
+      Err(_) => tokio::time::sleep(FORWARD_RETRY).await,
+    }
+
+    attempt += 1;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("media-widget-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+  }
+
+  #[test]
+  fn only_one_holder() {
+    let path = temp_path("lock");
+
+    let lock = InstanceLock::try_acquire(&path).unwrap().unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap().trim(), std::process::id().to_string());
+    assert!(InstanceLock::try_acquire(&path).unwrap().is_none());
+
+    drop(lock);
+    assert!(InstanceLock::try_acquire(&path).unwrap().is_some());
+  }
+
+  #[tokio::test]
+  async fn ignores_a_left_over_lock_file() {
+    let path = temp_path("stale.lock");
+    let endpoint = temp_path("stale.sock");
+    std::fs::write(&path, "1\n").unwrap();
+
+    let Claim::Primary(_lock) = claim(&path, &endpoint, &[]).await.unwrap() else {
+      panic!("expected to take the lock");
+    };
+    assert_eq!(std::fs::read_to_string(&path).unwrap().trim(), std::process::id().to_string());
+  }
+
+  #[cfg(unix)]
+  #[tokio::test]
+  async fn forwards_to_the_holder() {
+    let path = temp_path("held.lock");
+    let endpoint = temp_path("held.sock");
+    let _lock = InstanceLock::try_acquire(&path).unwrap().unwrap();
+
+    let (sender, mut commands) = tokio::sync::mpsc::unbounded_channel();
+    let server = crate::instance::server::spawn(endpoint.clone(), sender).unwrap();
+
+    assert!(matches!(claim(&path, &endpoint, &["--show".into()]).await.unwrap(), Claim::Forwarded));
+    assert_eq!(commands.try_recv(), Ok(vec![crate::instance::args::InstanceCommand::Show]));
+
+    server.abort();
+    std::fs::remove_file(&endpoint).unwrap();
+  }
+}
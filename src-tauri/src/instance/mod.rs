@@ -0,0 +1,4 @@
+pub mod args;
+pub mod commands;
+pub mod lock;
+pub mod server;
\ No newline at end of file
@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::args::{parse_args, InstanceCommand};
+
+/// Where the running instance listens for later launches, apart from the
+/// control server so `ctl` and launches don't get in each other's way
+#[cfg(unix)]
+pub fn endpoint() -> PathBuf { crate::utils::fs::runtime_dir().join("media-widget-instance.sock") }
+
+#[cfg(windows)]
+pub fn endpoint() -> PathBuf { crate::utils::fs::user_pipe("media-widget-instance") }
+
+/// Hands `args` to the instance listening on `endpoint`, once it has taken
+/// them
+pub async fn forward(endpoint: &Path, args: &[String]) -> anyhow::Result<()> {
+  let stream = connect(endpoint).await?;
+  let (reader, mut writer) = tokio::io::split(stream);
+
+  let mut line = serde_json::to_vec(args)?;
+  line.push(b'\n');
+  writer.write_all(&line).await?;
+  writer.flush().await?;
+
+  match BufReader::new(reader).lines().next_line().await? {
+    Some(reply) if reply == "ok" => Ok(()),
+    Some(reply) => bail!("Unexpected reply '{}'", reply),
+    None => bail!("The running instance closed the connection"),
+  }
+}
+
+#[cfg(unix)]
+async fn connect(endpoint: &Path) -> std::io::Result<tokio::net::UnixStream> {
+  tokio::net::UnixStream::connect(endpoint).await
+}
+
+#[cfg(windows)]
+async fn connect(endpoint: &Path) -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+  tokio::net::windows::named_pipe::ClientOptions::new().open(endpoint)
+}
+
+/// Accepts launches on `endpoint` and passes their commands on, only the
+/// instance holding the lock should listen
+pub fn spawn(endpoint: PathBuf, commands: mpsc::UnboundedSender<Vec<InstanceCommand>>) -> anyhow::Result<JoinHandle<()>> {
+  let listener = listen(&endpoint)?;
+
+  println!("[InstanceServer] listening on {:?}", endpoint);
+
+  Ok(tokio::spawn(accept(listener, endpoint, commands)))
+}
+
+#[cfg(unix)]
+fn listen(endpoint: &Path) -> anyhow::Result<tokio::net::UnixListener> {
+  use std::os::unix::fs::PermissionsExt;
+
+  // We hold the lock, so whatever is there is left over from a crash
+  if endpoint.exists() {
+    std::fs::remove_file(endpoint)?;
+  }
+
+  let listener = tokio::net::UnixListener::bind(endpoint)?;
+  std::fs::set_permissions(endpoint, std::fs::Permissions::from_mode(0o600))?;
+  Ok(listener)
+}
+
+#[cfg(unix)]
+async fn accept(listener: tokio::net::UnixListener, _endpoint: PathBuf, commands: mpsc::UnboundedSender<Vec<InstanceCommand>>) {
+  loop {
+    match listener.accept().await {
+      Ok((stream, _)) => {
+        tokio::spawn(serve(stream, commands.clone()));
+      },
+      Err(err) => println!("[InstanceServer] accept | Error: {}", err),
+    }
+  }
+}
+
+#[cfg(windows)]
+fn listen(endpoint: &Path) -> anyhow::Result<tokio::net::windows::named_pipe::NamedPipeServer> {
+  use tokio::net::windows::named_pipe::ServerOptions;
+
+  Ok(ServerOptions::new().first_pipe_instance(true).create(endpoint)?)
+}
+
+#[cfg(windows)]
+async fn accept(
+  mut server: tokio::net::windows::named_pipe::NamedPipeServer,
+  endpoint: PathBuf,
+  commands: mpsc::UnboundedSender<Vec<InstanceCommand>>,
+) {
+  use tokio::net::windows::named_pipe::ServerOptions;
+
+  loop {
+    if let Err(err) = server.connect().await {
+      println!("[InstanceServer] accept | Error: {}", err);
+      continue;
+    }
+
+    // A new instance has to exist before the next client can connect
+    let next = match ServerOptions::new().create(&endpoint) {
+      Ok(next) => next,
+      Err(err) => {
+        println!("[InstanceServer] Error: {}", err);
+        break;
+      },
+    };
+
+    tokio::spawn(serve(std::mem::replace(&mut server, next), commands.clone()));
+  }
+}
+
+async fn serve<S: AsyncRead + AsyncWrite>(stream: S, commands: mpsc::UnboundedSender<Vec<InstanceCommand>>) {
+  if let Err(err) = serve_connection(stream, commands).await {
+    println!("[InstanceServer] Error: {}", err);
+  }
+}
+
+async fn serve_connection<S: AsyncRead + AsyncWrite>(
+  stream: S,
+  commands: mpsc::UnboundedSender<Vec<InstanceCommand>>,
+) -> anyhow::Result<()> {
+  let (reader, mut writer) = tokio::io::split(stream);
+  let Some(line) = BufReader::new(reader).lines().next_line().await? else {
+    return Ok(());
+  };
+
+  let args: Vec<String> = serde_json::from_str(&line)?;
+  println!("[InstanceServer] launched again with {:?}", args);
+
+  // Launching it again without anything to do brings it up
+  let mut launched = parse_args(&args);
+  if launched.is_empty() {
+    launched.push(InstanceCommand::Show);
+  }
+  commands.send(launched)?;
+
+  writer.write_all(b"ok\n").await?;
+  writer.flush().await?;
+  Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn forwards_launch_arguments() {
+    let endpoint = std::env::temp_dir().join(format!("media-widget-instance-test-{}.sock", std::process::id()));
+    let (sender, mut commands) = mpsc::unbounded_channel();
+    let server = spawn(endpoint.clone(), sender).unwrap();
+
+    forward(&endpoint, &["--toggle".into()]).await.unwrap();
+    forward(&endpoint, &[]).await.unwrap();
+
+    // The reply only comes once the commands are passed on
+    assert_eq!(commands.try_recv(), Ok(vec![InstanceCommand::Toggle]));
+    assert_eq!(commands.try_recv(), Ok(vec![InstanceCommand::Show]));
+
+    server.abort();
+    std::fs::remove_file(&endpoint).unwrap();
+  }
+}
@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+
+use super::lrc::{parse, Lyrics};
+use super::provider::{LyricsProvider, LyricsQuery};
+use crate::utils::title::normalize_title;
+
+/// How deep to look below each directory, enough for `Artist/Album/Song.lrc`
+const MAX_DEPTH: usize = 3;
+
+/// Finds `.lrc` files named `Artist - Title.lrc`, or `Title.lrc` below a
+/// directory named after the artist
+pub struct LocalLyricsProvider {
+  directories: Vec<PathBuf>,
+}
+
+impl LocalLyricsProvider {
+  pub fn new(directories: Vec<PathBuf>) -> Self { Self { directories } }
+}
+
+#[async_trait]
+impl LyricsProvider for LocalLyricsProvider {
+  fn name(&self) -> &'static str { "local" }
+
+  async fn find(&self, query: &LyricsQuery) -> anyhow::Result<Option<Lyrics>> {
+    let directories = self.directories.clone();
+    let query = query.clone();
+
+    let Some(path) = tokio::task::spawn_blocking(move || find_file(&directories, &query)).await? else {
+      return Ok(None);
+    };
+
+    println!("[Lyrics] local | {:?}", path);
+    Ok(Some(parse(&tokio::fs::read_to_string(&path).await?)?))
+  }
+}
+
+/// Lowercase words without punctuation, so "AC/DC" matches "ac dc" and
+/// "Don't Stop" matches "dont stop"
+pub fn normalize(text: &str) -> String {
+  text
+    .to_lowercase()
+    .replace(['\'', '’'], "")
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+fn find_file(directories: &[PathBuf], query: &LyricsQuery) -> Option<PathBuf> {
+  let title = normalize(&query.title);
+  let mut artists = query.artists.iter().map(|artist| normalize(artist)).collect::<Vec<_>>();
+  artists.push(normalize(&query.artists.join(", ")));
+
+  let matches = |path: &Path| {
+    let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+      return false;
+    };
+
+    match stem.split_once(" - ") {
+      Some((artist, file_title)) => {
+        normalize(&normalize_title(file_title).base_title) == title && artists.contains(&normalize(artist))
+      },
+      // `Artist/Title.lrc` or `Artist/Album/Title.lrc`
+      None => {
+        normalize(&normalize_title(stem).base_title) == title
+          && path
+            .ancestors()
+            .skip(1)
+            .take(2)
+            .filter_map(|ancestor| ancestor.file_name())
+            .any(|name| artists.contains(&normalize(&name.to_string_lossy())))
+      },
+    }
+  };
+
+  directories.iter().find_map(|directory| walk(directory, 0, &matches))
+}
+
+fn walk(directory: &Path, depth: usize, matches: &dyn Fn(&Path) -> bool) -> Option<PathBuf> {
+  let mut subdirectories = vec![];
+
+  for entry in std::fs::read_dir(directory).ok()?.flatten() {
+    let path = entry.path();
+
+    if path.is_dir() {
+      subdirectories.push(path);
+    } else if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("lrc")) && matches(&path) {
+      return Some(path);
+    }
+  }
+
+  if depth >= MAX_DEPTH {
+    return None;
+  }
+
+  subdirectories
+    .iter()
+    .find_map(|subdirectory| walk(subdirectory, depth + 1, matches))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn query(artists: &[&str], title: &str) -> LyricsQuery {
+    LyricsQuery {
+      artists: artists.iter().map(|artist| artist.to_string()).collect(),
+      title: title.into(),
+      album: "".into(),
+    }
+  }
+
+  #[test]
+  fn normalizes_names() {
+    assert_eq!(normalize("AC/DC"), "ac dc");
+    assert_eq!(normalize("  Don't   Stop  Me Now! "), "dont stop me now");
+    assert_eq!(normalize("Beyoncé"), "beyoncé");
+  }
+
+  #[tokio::test]
+  async fn finds_files_by_artist_and_title() {
+    let root = std::env::temp_dir().join(format!("media-widget-lyrics-{}", std::process::id()));
+    let nested = root.join("Queen").join("Jazz");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(root.join("AC_DC - Back in Black (2003 Remaster).LRC"), "[00:01.00]Back in black").unwrap();
+    std::fs::write(nested.join("Don't Stop Me Now.lrc"), "[00:02.00]Tonight").unwrap();
+    std::fs::write(root.join("Someone Else - Tonight.lrc"), "[00:03.00]Wrong").unwrap();
+
+    let provider = LocalLyricsProvider::new(vec![root.join("missing"), root.clone()]);
+    let first_line = |lyrics: Option<Lyrics>| lyrics.map(|lyrics| lyrics.lines[0].text.clone());
+
+    assert_eq!(
+      first_line(provider.find(&query(&["AC/DC"], "Back In Black")).await.unwrap()),
+      Some("Back in black".into())
+    );
+    assert_eq!(
+      first_line(provider.find(&query(&["Queen"], "Don’t Stop Me Now")).await.unwrap()),
+      Some("Tonight".into())
+    );
+    assert_eq!(provider.find(&query(&["Queen"], "Tonight")).await.unwrap(), None);
+
+    std::fs::remove_dir_all(&root).unwrap();
+  }
+}
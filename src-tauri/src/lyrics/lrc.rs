@@ -0,0 +1,231 @@
+use anyhow::bail;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// `[01:23.45]`, also `[01:23]`, `[01:23:45]` and `[01:23.456]`
+static LINE_TIME: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[(\d+):(\d{1,2})(?:[.:](\d{1,3}))?\]").unwrap());
+
+/// Enhanced LRC word timestamp, `<01:23.45>`
+static WORD_TIME: Lazy<Regex> = Lazy::new(|| Regex::new(r"<(\d+):(\d{1,2})(?:[.:](\d{1,3}))?>").unwrap());
+
+/// `[ar:Artist]`, `[offset:+250]`, ...
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[([a-zA-Z#]+):(.*)\]$").unwrap());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Lyrics {
+  pub title: Option<String>,
+  pub artist: Option<String>,
+  pub album: Option<String>,
+  /// Sorted by `start_ms`, with the file's offset already applied
+  pub lines: Vec<LyricsLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct LyricsLine {
+  pub start_ms: i64,
+  /// Empty for instrumental breaks
+  pub text: String,
+  /// Only present in enhanced LRC
+  pub words: Vec<LyricsWord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct LyricsWord {
+  pub start_ms: i64,
+  pub text: String,
+}
+
+impl Lyrics {
+  /// Index of the line being sung at `position_ms`, `None` before the first
+  pub fn line_at(&self, position_ms: i64) -> Option<usize> {
+    self
+      .lines
+      .partition_point(|line| line.start_ms <= position_ms)
+      .checked_sub(1)
+  }
+
+  /// When the line after `index` starts
+  pub fn next_start_ms(&self, index: Option<usize>) -> Option<i64> {
+    let next = index.map_or(0, |index| index + 1);
+    self.lines.get(next).map(|line| line.start_ms)
+  }
+}
+
+fn millis(captures: &regex::Captures) -> i64 {
+  let minutes: i64 = captures[1].parse().unwrap_or(0);
+  let seconds: i64 = captures[2].parse().unwrap_or(0);
+  // ".5" is tenths, ".45" hundredths and ".456" milliseconds
+  let fraction = captures
+    .get(3)
+    .map(|fraction| format!("{:0<3}", fraction.as_str()).parse().unwrap_or(0))
+    .unwrap_or(0);
+
+  (minutes * 60 + seconds) * 1000 + fraction
+}
+
+/// Parses LRC, skipping lines it doesn't understand like players do. A
+/// positive `[offset:]` makes the lyrics appear earlier.
+pub fn parse(text: &str) -> anyhow::Result<Lyrics> {
+  let mut lyrics = Lyrics::default();
+  let mut offset_ms = 0;
+
+  for raw in text.lines() {
+    let mut rest = raw.trim();
+    let mut times = vec![];
+
+    while let Some(captures) = LINE_TIME.captures(rest) {
+      times.push(millis(&captures));
+      rest = &rest[captures.get(0).unwrap().end()..];
+    }
+
+    if times.is_empty() {
+      if let Some(captures) = TAG.captures(rest) {
+        let value = captures[2].trim().to_string();
+        match captures[1].to_lowercase().as_str() {
+          "ti" => lyrics.title = Some(value),
+          "ar" => lyrics.artist = Some(value),
+          "al" => lyrics.album = Some(value),
+          "offset" => offset_ms = value.trim_start_matches('+').parse().unwrap_or(0),
+          _ => {},
+        }
+      }
+      continue;
+    }
+
+    let (text, words) = parse_words(rest.trim());
+    for start_ms in times {
+      lyrics.lines.push(LyricsLine {
+        start_ms,
+        text: text.clone(),
+        words: words
+          .iter()
+          .map(|(start, text)| LyricsWord {
+            start_ms: start.unwrap_or(start_ms),
+            text: text.clone(),
+          })
+          .collect(),
+      });
+    }
+  }
+
+  if lyrics.lines.is_empty() {
+    bail!("No timed lines");
+  }
+
+  for line in &mut lyrics.lines {
+    line.start_ms = (line.start_ms - offset_ms).max(0);
+    for word in &mut line.words {
+      word.start_ms = (word.start_ms - offset_ms).max(0);
+    }
+  }
+
+  // Repeated lines like `[00:10][01:10]Chorus` come out of order
+  lyrics.lines.sort_by_key(|line| line.start_ms);
+
+  Ok(lyrics)
+}
+
+/// Splits `<00:01.00>Some <00:01.50>words` into the plain text and its timed
+/// words. Text before the first timestamp starts with the line.
+fn parse_words(text: &str) -> (String, Vec<(Option<i64>, String)>) {
+  if !WORD_TIME.is_match(text) {
+    return (text.to_string(), vec![]);
+  }
+
+  let mut words = vec![];
+  let mut start = None;
+  let mut last_end = 0;
+
+  for captures in WORD_TIME.captures_iter(text) {
+    let time = captures.get(0).unwrap();
+    let word = &text[last_end..time.start()];
+    if !word.is_empty() {
+      words.push((start, word.to_string()));
+    }
+
+    start = Some(millis(&captures));
+    last_end = time.end();
+  }
+
+  // A trailing timestamp only marks when the last word ends
+  if last_end < text.len() {
+    words.push((start, text[last_end..].to_string()));
+  }
+
+  let plain = words.iter().map(|(_, word)| word.as_str()).collect::<String>();
+  (plain.trim().to_string(), words)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn starts(lyrics: &Lyrics) -> Vec<(i64, &str)> {
+    lyrics.lines.iter().map(|line| (line.start_ms, line.text.as_str())).collect()
+  }
+
+  #[test]
+  fn parses_simple_lrc() {
+    let lyrics = parse(
+      "[ti:Song]\n[ar:Artist]\n[length: 03:20]\n\n[00:12.34]First line\n[00:15.5]Second line\n[00:20]\n[00:21:05] Third  \nnot a lyric\n",
+    )
+    .unwrap();
+
+    assert_eq!(lyrics.title.as_deref(), Some("Song"));
+    assert_eq!(lyrics.artist.as_deref(), Some("Artist"));
+    assert_eq!(starts(&lyrics), vec![
+      (12_340, "First line"),
+      (15_500, "Second line"),
+      (20_000, ""),
+      (21_050, "Third"),
+    ]);
+  }
+
+  #[test]
+  fn expands_repeated_lines_and_applies_the_offset() {
+    let lyrics = parse("[offset:+500]\n[00:30.00][01:30.00]Chorus\n[00:00.20]Intro\n[01:00.00]Verse\n").unwrap();
+
+    assert_eq!(starts(&lyrics), vec![(0, "Intro"), (29_500, "Chorus"), (59_500, "Verse"), (89_500, "Chorus")]);
+
+    let later = parse("[offset:-250]\n[00:01.00]Line\n").unwrap();
+    assert_eq!(later.lines[0].start_ms, 1_250);
+  }
+
+  #[test]
+  fn parses_enhanced_word_timestamps() {
+    let lyrics = parse("[offset:100]\n[00:10.00]<00:10.00>Hello <00:10.50>there <00:11.25>world<00:12.00>\n").unwrap();
+    let line = &lyrics.lines[0];
+
+    assert_eq!(line.text, "Hello there world");
+    assert_eq!(
+      line.words.iter().map(|word| (word.start_ms, word.text.as_str())).collect::<Vec<_>>(),
+      vec![(9_900, "Hello "), (10_400, "there "), (11_150, "world")]
+    );
+
+    let untimed_start = parse("[00:05.00]Oh <00:06.00>yeah").unwrap();
+    assert_eq!(untimed_start.lines[0].words[0].start_ms, 5_000);
+  }
+
+  #[test]
+  fn finds_the_current_line() {
+    let lyrics = parse("[00:01.00]One\n[00:02.00]Two\n[00:03.00]Three\n").unwrap();
+
+    assert_eq!(lyrics.line_at(500), None);
+    assert_eq!(lyrics.line_at(1_000), Some(0));
+    assert_eq!(lyrics.line_at(2_999), Some(1));
+    assert_eq!(lyrics.line_at(60_000), Some(2));
+
+    assert_eq!(lyrics.next_start_ms(None), Some(1_000));
+    assert_eq!(lyrics.next_start_ms(Some(1)), Some(3_000));
+    assert_eq!(lyrics.next_start_ms(Some(2)), None);
+  }
+
+  #[test]
+  fn rejects_untimed_text() {
+    assert!(parse("[ar:Artist]\nJust some words\n").is_err());
+  }
+}
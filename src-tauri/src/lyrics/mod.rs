@@ -0,0 +1,4 @@
+pub mod local;
+pub mod lrc;
+pub mod provider;
+pub mod tracker;
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use super::local::LocalLyricsProvider;
+use super::lrc::Lyrics;
+use crate::media::lib::MediaSessionData;
+use crate::settings::schema::LyricsSettings;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LyricsQuery {
+  pub artists: Vec<String>,
+  /// Without version suffixes, "Song - 2011 Remaster" is just "Song"
+  pub title: String,
+  pub album: String,
+}
+
+impl From<&MediaSessionData> for LyricsQuery {
+  fn from(data: &MediaSessionData) -> Self {
+    Self {
+      artists: data.artists.clone(),
+      title: data.normalized_title.base_title.clone(),
+      album: data.album.clone(),
+    }
+  }
+}
+
+/// A source of synchronized lyrics, e.g. local files or a web service
+#[async_trait]
+pub trait LyricsProvider: Send + Sync {
+  fn name(&self) -> &'static str;
+
+  /// `Ok(None)` when the provider has nothing for this track
+  async fn find(&self, query: &LyricsQuery) -> anyhow::Result<Option<Lyrics>>;
+}
+
+pub fn providers(settings: &LyricsSettings) -> Vec<Arc<dyn LyricsProvider>> {
+  let mut providers: Vec<Arc<dyn LyricsProvider>> = vec![];
+
+  if !settings.directories.is_empty() {
+    let directories = settings.directories.iter().map(PathBuf::from).collect();
+    providers.push(Arc::new(LocalLyricsProvider::new(directories)));
+  }
+
+  providers
+}
+
+/// Asks each provider in order, the first one with lyrics wins. A failing
+/// provider is skipped rather than hiding the others' results.
+pub async fn find(providers: &[Arc<dyn LyricsProvider>], query: &LyricsQuery) -> Option<Lyrics> {
+  for provider in providers {
+    match provider.find(query).await {
+      Ok(Some(lyrics)) => return Some(lyrics),
+      Ok(None) => continue,
+      Err(err) => println!("[Lyrics] {} | Error: {}", provider.name(), err),
+    }
+  }
+
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use anyhow::anyhow;
+
+  use super::*;
+  use crate::lyrics::lrc::parse;
+
+  struct Fixed(Option<&'static str>);
+
+  #[async_trait]
+  impl LyricsProvider for Fixed {
+    fn name(&self) -> &'static str { "fixed" }
+
+    async fn find(&self, _: &LyricsQuery) -> anyhow::Result<Option<Lyrics>> {
+      match self.0 {
+        Some(text) => Ok(Some(parse(text)?)),
+        None => Ok(None),
+      }
+    }
+  }
+
+  struct Failing;
+
+  #[async_trait]
+  impl LyricsProvider for Failing {
+    fn name(&self) -> &'static str { "failing" }
+
+    async fn find(&self, _: &LyricsQuery) -> anyhow::Result<Option<Lyrics>> { Err(anyhow!("unreachable")) }
+  }
+
+  #[tokio::test]
+  async fn asks_providers_in_order() {
+    let query = LyricsQuery::from(&MediaSessionData::fixture("Song", &["A"]));
+    let providers: Vec<Arc<dyn LyricsProvider>> = vec![
+      Arc::new(Fixed(None)),
+      Arc::new(Failing),
+      Arc::new(Fixed(Some("[00:01.00]Second"))),
+      Arc::new(Fixed(Some("[00:01.00]Third"))),
+    ];
+
+    assert_eq!(find(&providers, &query).await.unwrap().lines[0].text, "Second");
+    assert_eq!(find(&providers[..2], &query).await, None);
+  }
+}
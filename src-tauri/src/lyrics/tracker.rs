@@ -0,0 +1,269 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::lrc::{Lyrics, LyricsWord};
+use super::provider::{find, LyricsProvider, LyricsQuery};
+use crate::media::lib::MediaEvent;
+use crate::media::position::PlaybackPosition;
+use crate::utils::time::now_ms;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrentLine {
+  pub index: u32,
+  pub text: String,
+  pub start_ms: i64,
+  /// When the next line starts, `None` for the last line
+  pub end_ms: Option<i64>,
+  pub words: Vec<LyricsWord>,
+}
+
+/// The lyrics of the current track, and where playback is in them
+#[derive(Debug, Clone, Default)]
+pub struct LyricsState {
+  position: PlaybackPosition,
+  query: Option<LyricsQuery>,
+  lyrics: Option<Arc<Lyrics>>,
+}
+
+impl LyricsState {
+  pub fn new() -> Self { Self::default() }
+
+  /// Returns the query to look up when the track changed
+  pub fn handle(&mut self, event: &MediaEvent, now: i64) -> Option<LyricsQuery> {
+    match event {
+      MediaEvent::MediaPropertiesChanged(data) => {
+        let query = LyricsQuery::from(data);
+        // Properties are sent again when only the artwork changes
+        if self.query.as_ref() == Some(&query) {
+          return None;
+        }
+
+        self.position.reset(now);
+        self.query = Some(query.clone());
+        self.lyrics = None;
+        return Some(query);
+      },
+      MediaEvent::PlaybackInfoChanged(data) => self.position.set_playing(data.is_playing, now),
+      MediaEvent::TimelinePropertiesChanged(data) => self.position.set_timeline(data, now),
+      MediaEvent::Disconnect(_) => *self = Self::new(),
+      _ => {},
+    }
+
+    None
+  }
+
+  /// Ignores lyrics for a track that's no longer playing
+  pub fn set_lyrics(&mut self, query: &LyricsQuery, lyrics: Option<Lyrics>) {
+    if self.query.as_ref() == Some(query) {
+      self.lyrics = lyrics.map(Arc::new);
+    }
+  }
+
+  pub fn current(&self, now: i64) -> Option<CurrentLine> {
+    let lyrics = self.lyrics.as_ref()?;
+    let index = lyrics.line_at(self.position.position_ms(now))?;
+    let line = &lyrics.lines[index];
+
+    Some(CurrentLine {
+      index: index as u32,
+      text: line.text.clone(),
+      start_ms: line.start_ms,
+      end_ms: lyrics.next_start_ms(Some(index)),
+      words: line.words.clone(),
+    })
+  }
+
+  /// How long until the next line, while playing
+  pub fn next_change_in(&self, now: i64) -> Option<Duration> {
+    if !self.position.is_playing() {
+      return None;
+    }
+
+    let lyrics = self.lyrics.as_ref()?;
+    let position = self.position.position_ms(now);
+    let next = lyrics.next_start_ms(lyrics.line_at(position))?;
+
+    Some(Duration::from_millis((next - position).max(0) as u64))
+  }
+}
+
+/// Looks up lyrics whenever the track changes and publishes the line being
+/// sung, waking up exactly when the next one starts
+pub struct LyricsTracker {
+  current: watch::Receiver<Option<CurrentLine>>,
+  task: JoinHandle<()>,
+}
+
+impl LyricsTracker {
+  pub fn spawn(mut events: broadcast::Receiver<MediaEvent>, providers: Vec<Arc<dyn LyricsProvider>>) -> Self {
+    println!("[LyricsTracker] spawn");
+
+    let (sender, current) = watch::channel(None);
+    let providers = Arc::new(providers);
+
+    let task = tokio::spawn(async move {
+      let mut state = LyricsState::new();
+      let mut lookup: Option<JoinHandle<(LyricsQuery, Option<Lyrics>)>> = None;
+
+      loop {
+        let next_change = state.next_change_in(now_ms());
+
+        tokio::select! {
+          event = events.recv() => match event {
+            Ok(event) => {
+              if let Some(query) = state.handle(&event, now_ms()) {
+                if let Some(previous) = lookup.take() {
+                  previous.abort();
+                }
+
+                let providers = providers.clone();
+                lookup = Some(tokio::spawn(async move {
+                  let lyrics = find(&providers, &query).await;
+                  (query, lyrics)
+                }));
+              }
+            },
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+          },
+          result = async { lookup.as_mut().unwrap().await }, if lookup.is_some() => {
+            lookup = None;
+            if let Ok((query, lyrics)) = result {
+              state.set_lyrics(&query, lyrics);
+            }
+          },
+          _ = tokio::time::sleep(next_change.unwrap_or_default()), if next_change.is_some() => {},
+        }
+
+        let line = state.current(now_ms());
+        sender.send_if_modified(|current| {
+          if *current == line {
+            return false;
+          }
+
+          *current = line;
+          true
+        });
+      }
+    });
+
+    Self { current, task }
+  }
+
+  pub fn subscribe(&self) -> watch::Receiver<Option<CurrentLine>> { self.current.clone() }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+}
+
+impl Drop for LyricsTracker {
+  fn drop(&mut self) { self.task.abort(); }
+}
+
+#[cfg(test)]
+mod tests {
+  use async_trait::async_trait;
+
+  use super::*;
+  use crate::lyrics::lrc::parse;
+  use crate::media::lib::{MediaPlaybackData, MediaSessionData, MediaTimelineData};
+
+  const LRC: &str = "[00:00.00]\n[00:01.00]One\n[00:02.00]Two\n";
+
+  struct Fixed;
+
+  #[async_trait]
+  impl LyricsProvider for Fixed {
+    fn name(&self) -> &'static str { "fixed" }
+
+    async fn find(&self, query: &LyricsQuery) -> anyhow::Result<Option<Lyrics>> {
+      Ok((query.title == "Song").then(|| parse(LRC).unwrap()))
+    }
+  }
+
+  fn text(line: Option<CurrentLine>) -> Option<String> { line.map(|line| line.text) }
+
+  #[test]
+  fn follows_the_extrapolated_position() {
+    let mut state = LyricsState::new();
+    let query = state
+      .handle(&MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A"])), 0)
+      .unwrap();
+    state.set_lyrics(&query, Some(parse(LRC).unwrap()));
+    state.handle(&MediaEvent::TimelinePropertiesChanged(MediaTimelineData::fixture(500, 3_000)), 0);
+
+    assert_eq!(text(state.current(10_000)), Some("".into()));
+    assert_eq!(state.next_change_in(0), None);
+
+    state.handle(&MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing: true }), 0);
+    assert_eq!(state.next_change_in(0), Some(Duration::from_millis(500)));
+    assert_eq!(
+      state.current(600),
+      Some(CurrentLine {
+        index: 1,
+        text: "One".into(),
+        start_ms: 1_000,
+        end_ms: Some(2_000),
+        words: vec![],
+      })
+    );
+    assert_eq!(state.next_change_in(1_600), None);
+
+    // Same track again, e.g. new artwork
+    assert_eq!(
+      state.handle(&MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A"])), 0),
+      None
+    );
+    assert!(state.current(0).is_some());
+
+    state.handle(&MediaEvent::Disconnect("Spotify.exe".into()), 0);
+    assert_eq!(state.current(0), None);
+  }
+
+  #[test]
+  fn ignores_lyrics_for_a_previous_track() {
+    let mut state = LyricsState::new();
+    let first = state
+      .handle(&MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("First", &["A"])), 0)
+      .unwrap();
+    state.handle(&MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Second", &["A"])), 0);
+
+    state.set_lyrics(&first, Some(parse(LRC).unwrap()));
+    assert_eq!(state.current(0), None);
+  }
+
+  #[tokio::test]
+  async fn publishes_line_changes() {
+    let (events, events_rx) = broadcast::channel(16);
+    let tracker = LyricsTracker::spawn(events_rx, vec![Arc::new(Fixed)]);
+    let mut current = tracker.subscribe();
+
+    events
+      .send(MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A"])))
+      .unwrap();
+    events
+      .send(MediaEvent::TimelinePropertiesChanged(MediaTimelineData::fixture(900, 3_000)))
+      .unwrap();
+    events
+      .send(MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing: true }))
+      .unwrap();
+
+    let mut seen = vec![];
+    tokio::time::timeout(Duration::from_secs(5), async {
+      while seen.last() != Some(&Some("One".to_string())) {
+        current.changed().await.unwrap();
+        seen.push(text(current.borrow().clone()));
+      }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(seen, vec![Some("".into()), Some("One".into())]);
+  }
+}
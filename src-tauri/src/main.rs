@@ -3,54 +3,345 @@
   windows_subsystem = "windows"
 )]
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use tauri::{CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu};
-use tokio::sync::broadcast;
+use tauri::{Manager, SystemTray, SystemTrayEvent, WindowEvent};
+use tokio::sync::{broadcast, mpsc};
+
+mod api;
+use crate::api::server::ApiConfig;
+
+mod autostart;
+use crate::autostart::launcher::AUTOSTART_ARG;
+
+mod control;
+use crate::control::server::ControlHandler;
+
+mod discord;
+
+mod ducking;
+
+mod history;
+use crate::history::store::HistoryStore;
+
+mod hotkeys;
+use crate::hotkeys::config::HotkeyAction;
+use crate::hotkeys::dispatcher::HotkeyDispatcher;
+use crate::hotkeys::manager::HotkeyManager;
+use crate::hotkeys::shortcuts::TauriShortcuts;
+
+mod instance;
+use crate::instance::lock::Claim;
+
+mod lyrics;
+use crate::lyrics::tracker::LyricsTracker;
 
 mod media;
 use crate::media::lib::MediaEvent;
 use crate::media::manager::MediaManager;
 
+mod mqtt;
+use crate::mqtt::bridge::MqttConfig;
+
+mod network;
+use crate::network::monitor::{ConnectivityMonitor, MonitorConfig};
+
+mod notifications;
+
+mod now_playing;
+use crate::now_playing::exporter::NowPlayingExporter;
+
+mod scrobble;
+use crate::scrobble::client::ScrobbleConfig;
+use crate::scrobble::queue::ScrobbleQueue;
+
+mod settings;
+use crate::settings::store::SettingsStore;
+
+mod placement;
+use crate::placement::mode::ModeController;
+use crate::placement::store::PositionStore;
+use crate::placement::tracker::PlacementTracker;
+
 mod router;
 use router::Ctx;
 
+mod timer;
+use crate::timer::controller::SleepTimer;
+
+mod tray;
+use crate::tray::controller::TrayController;
+
 mod utils;
 
+mod visibility;
+use crate::visibility::controller::VisibilityController;
+
+mod volume;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-  let tray_menu = SystemTrayMenu::new()
-		.add_item(CustomMenuItem::new("quit", "Quit"));
-	
+  // `ctl` talks to the running instance instead of starting one
+  let args = std::env::args().collect::<Vec<_>>();
+  if args.get(1).map(String::as_str) == Some("ctl") {
+    std::process::exit(control::cli::run(&args[2..]).await);
+  }
+
+  // A second launch hands its arguments to the running instance instead
+  let instance_endpoint = instance::server::endpoint();
+  // Held until the process ends, the OS releases it however that happens
+  let _instance_lock = match instance::lock::claim(&instance::lock::lock_path(), &instance_endpoint, &args[1..]).await? {
+    Claim::Primary(lock) => lock,
+    Claim::Forwarded => {
+      println!("[Instance] Forwarded {:?} to the running instance", &args[1..]);
+      return Ok(());
+    },
+  };
+
+  let (launches, instance_commands) = mpsc::unbounded_channel();
+  launches.send(instance::args::parse_args(&args[1..]))?;
+  if let Err(err) = instance::server::spawn(instance_endpoint, launches) {
+    println!("[InstanceServer] Error: {:?}", err);
+  }
+
 	let router = router::new();
+  let context = tauri::generate_context!();
   let event_bus = Arc::new(broadcast::channel::<MediaEvent>(1024));
+
+  let data_dir = tauri::api::path::app_data_dir(context.config())
+    .ok_or_else(|| anyhow::anyhow!("Unable to resolve the app data directory"))?;
+  let config_dir = tauri::api::path::app_config_dir(context.config())
+    .ok_or_else(|| anyhow::anyhow!("Unable to resolve the app config directory"))?;
+
+  let settings = SettingsStore::load(config_dir.join("settings.json")).arced();
+  settings.watch(Duration::from_secs(1));
+  // The sections below only take effect on the next start
+  let initial = settings.get();
+
+  let placement = PlacementTracker::new(
+    PositionStore::load(config_dir.join("window-position.json")).arced(),
+    settings.clone(),
+  )
+  .arced();
+  let placement_moves = placement.clone();
+
+  let modes = ModeController::new(settings.clone()).arced();
+  modes.track();
+
+  let launched_at_login = args.iter().any(|arg| arg == AUTOSTART_ARG);
+  match autostart::launcher::system_launcher(&context.config().tauri.bundle.identifier, std::env::current_exe()?) {
+    Ok(launcher) => {
+      autostart::startup::follow_settings(settings.subscribe(), launcher);
+    },
+    Err(err) => println!("[Autostart] Error: {:?}", err),
+  }
+
+  let opener = utils::opener::system_opener();
+  let network = ConnectivityMonitor::spawn(MonitorConfig::new(initial.network.probe_targets)).arced();
+
+  // Subscribe before the manager connects so the first session is recorded
+  let history = match HistoryStore::open(&data_dir.join("history.sqlite3")) {
+    Ok(history) => {
+      let history = history.arced();
+      history::recorder::spawn(event_bus.clone(), history.clone());
+      history
+    },
+    Err(err) => {
+      // Nothing is recorded, the history just stays empty
+      println!("[History] open | Error: {:?}", err);
+      HistoryStore::open_in_memory()?.arced()
+    },
+  };
+
+  // Scrobbling, MQTT and the API keep files of their own, read once here:
+  // they hold tokens and passwords, and the settings are handed to the
+  // webview and to anyone the API lets in through `settings.get`. A config
+  // that can't be read leaves its feature off rather than the app
+  let scrobble_config = ScrobbleConfig::load(&config_dir.join("scrobbling.json")).unwrap_or_else(|err| {
+    println!("[Scrobbler] load | Error: {:?}", err);
+    ScrobbleConfig::default()
+  });
+  match ScrobbleQueue::open(&data_dir.join("scrobble-queue.sqlite3")) {
+    Ok(queue) => {
+      scrobble::scrobbler::spawn(event_bus.clone(), network.subscribe(), queue.arced(), scrobble_config.services());
+    },
+    Err(err) => println!("[Scrobbler] open | Error: {:?}", err),
+  }
+
+  discord::publisher::spawn(event_bus.clone(), initial.discord);
+
+  let exporter = initial.now_playing.and_then(|now_playing| match NowPlayingExporter::new(now_playing) {
+    Ok(exporter) => Some(exporter),
+    Err(err) => {
+      println!("[NowPlayingExporter] Error: {:?}", err);
+      None
+    },
+  });
+  now_playing::exporter::spawn(event_bus.clone(), exporter);
+
+  let focused = Arc::new(AtomicBool::new(false));
+  if initial.notifications.enabled || initial.notifications.apps.values().any(|enabled| *enabled) {
+    let cache_dir = tauri::api::path::app_cache_dir(context.config())
+      .ok_or_else(|| anyhow::anyhow!("Unable to resolve the app cache directory"))?;
+    std::fs::create_dir_all(&cache_dir)?;
+
+    match notifications::notifier::system_notifier(&cache_dir, &context.config().tauri.bundle.identifier).await {
+      Ok(notifier) => {
+        notifications::notifier::spawn(event_bus.clone(), notifier, initial.notifications, focused.clone());
+      },
+      Err(err) => println!("[TrackNotifier] Error: {:?}", err),
+    }
+  }
+
+  let lyrics = LyricsTracker::spawn(
+    event_bus.0.subscribe(),
+    lyrics::provider::providers(&initial.lyrics),
+  )
+  .arced();
+
+  if let Err(err) = hotkeys::manager::import_legacy(&config_dir.join("hotkeys.json"), &settings) {
+    println!("[Hotkeys] import | Error: {:?}", err);
+  }
+  let hotkeys = HotkeyManager::new(settings.clone()).arced();
+  hotkeys.track();
+  let hotkey_events = event_bus.0.subscribe();
+
+  let control_events = event_bus.0.subscribe();
+  let mqtt_events = event_bus.0.subscribe();
+  let tray_events = event_bus.0.subscribe();
+  let timer_events = event_bus.0.subscribe();
+  let mut startup_events = event_bus.0.subscribe();
+  let ducking_events = event_bus.0.subscribe();
+  let visibility_events = event_bus.0.subscribe();
+  let (audio_activity, audio_activity_receiver) = mpsc::unbounded_channel();
 	
-  let manager = MediaManager::new(event_bus.clone())?
+  let manager = MediaManager::new(event_bus.clone(), settings.clone(), audio_activity)?
     .build()?
     .arced();
 
+  let has_session = manager.get_session().is_some();
+  let visibility = VisibilityController::spawn(visibility_events, settings.subscribe(), has_session).arced();
+
+  if let Err(err) = control::server::spawn(
+    ControlHandler::new(manager.clone(), event_bus.clone()).arced(),
+    control_events,
+  ) {
+    println!("[ControlServer] Error: {:?}", err);
+  }
+
+  let hotkey_dispatcher = HotkeyDispatcher::new(manager.clone()).arced();
+  hotkey_dispatcher.track(hotkey_events);
+
+  let mqtt_config = MqttConfig::load(&config_dir.join("mqtt.json")).unwrap_or_else(|err| {
+    println!("[MqttBridge] load | Error: {:?}", err);
+    None
+  });
+  mqtt::bridge::spawn(mqtt_config, manager.clone(), mqtt_events);
+
+  ducking::controller::spawn(manager.clone(), ducking_events, audio_activity_receiver, settings.subscribe());
+
+  let timer = SleepTimer::spawn(manager.clone(), timer_events, settings.subscribe()).arced();
+
+  // Starts out on top, as in tauri.conf.json
+  let tray = TrayController::new(manager.clone(), opener.clone(), config_dir.clone(), true).arced();
+  let tray_clicks = tray.clone();
+
+  let instance_opener = opener.clone();
+
+  let ctx = Ctx {
+    manager,
+    event_bus,
+    opener,
+    network,
+    history,
+    lyrics,
+    hotkeys: hotkeys.clone(),
+    settings,
+    modes: modes.clone(),
+    visibility: visibility.clone(),
+    timer,
+  };
+
+  // Same procedures for scripts and other tools, when enabled
+  let api_config = ApiConfig::load(&config_dir.join("api.json")).unwrap_or_else(|err| {
+    println!("[Api] load | Error: {:?}", err);
+    None
+  });
+  api::server::spawn(router.clone(), ctx.clone(), api_config);
+
   tauri::Builder::default()
-		.plugin(rspc::integrations::tauri::plugin(router, move || Ctx {
-      manager: Arc::clone(&manager),
-      event_bus: Arc::clone(&event_bus),
-    }))
-    .system_tray(SystemTray::new().with_menu(tray_menu))
-    .on_system_tray_event(|_, event| if let SystemTrayEvent::MenuItemClick { id, .. } = event {
-			match id.as_str() {
-				"quit" => std::process::exit(0),
-				"about" => todo!(),
-				_ => {}
-			}
+		.plugin(rspc::integrations::tauri::plugin(router, move || ctx.clone()))
+    .system_tray(SystemTray::new().with_menu(tray.menu()))
+    .on_window_event(move |event| match event.event() {
+      WindowEvent::Focused(is_focused) => focused.store(*is_focused, Ordering::Relaxed),
+      WindowEvent::Moved(_) => placement_moves.moved(event.window()),
+      _ => {},
+    })
+    .on_system_tray_event(move |app, event| if let SystemTrayEvent::MenuItemClick { id, .. } = event {
+      if let Err(err) = tray_clicks.on_click(app, &id) {
+        println!("[Tray] {} | Error: {:?}", id, err);
+      }
     })
-    .setup(|app| {
+    .setup(move |app| {
       if let Some(window) = app.get_window("main") {
         utils::window::apply_window_blur(&window);
-				window.show().unwrap();
+        if let Err(err) = placement.restore(&window, modes.mode()) {
+          println!("[Placement] restore | Error: {:?}", err);
+        }
+        placement.follow_mode(window.clone(), modes.subscribe());
+
+        #[cfg(windows)]
+        if let Ok(hwnd) = window.hwnd() {
+          visibility.attach(Arc::new(crate::visibility::fullscreen::ForegroundFullscreen::new(hwnd.0)));
+        }
+        crate::visibility::window::follow_visibility(window.clone(), visibility.subscribe());
+
+        if launched_at_login {
+          let window = window.clone();
+          tokio::spawn(async move {
+            if autostart::startup::player_found(&mut startup_events).await {
+              if let Err(err) = window.show() {
+                println!("[Autostart] show | Error: {:?}", err);
+              }
+            }
+          });
+        } else if visibility.visibility().visible {
+          // Otherwise `follow_visibility` shows it once a player shows up
+          if let Err(err) = window.show() {
+            println!("[Visibility] show | Error: {:?}", err);
+          }
+        }
+      }
+
+      tray.spawn(app.handle(), tray_events);
+      instance::commands::spawn(app.handle(), instance_opener, instance_commands);
+
+      let handle = app.handle();
+      let shortcuts = TauriShortcuts::new(app.global_shortcut_manager(), Arc::new(move |action: &HotkeyAction| {
+        let result = match action {
+          HotkeyAction::ToggleWindow => match handle.get_window("main") {
+            Some(window) => utils::window::toggle_window(&window).map_err(anyhow::Error::from),
+            None => Ok(()),
+          },
+          action => hotkey_dispatcher.dispatch(action),
+        };
+
+        if let Err(err) = result {
+          println!("[Hotkeys] {:?} | Error: {}", action, err);
+        }
+      }));
+
+      if let Err(errors) = hotkeys.attach(Box::new(shortcuts)) {
+        for error in errors {
+          println!("[Hotkeys] {}", error);
+        }
       }
 
       Ok(())
     })
-    .run(tauri::generate_context!())
+    .run(context)
     .expect("error while running tauri application");
 
 	Ok(())
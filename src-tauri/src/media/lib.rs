@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use tailwind_palette::TailwindPalette;
 use tokio::sync::broadcast;
 
+use crate::utils::title::NormalizedTitle;
+
 pub type EventBus = (broadcast::Sender<MediaEvent>, broadcast::Receiver<MediaEvent>);
 
 #[derive(Debug, Serialize, PartialEq, Clone, rspc::Type)]
@@ -12,7 +14,7 @@ pub enum MediaEvent {
   MediaPropertiesChanged(MediaSessionData),
   PlaybackInfoChanged(MediaPlaybackData),
   TimelinePropertiesChanged(MediaTimelineData),
-  VolumeChanged(f32),
+  VolumeChanged(MediaVolumeData),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, rspc::Type)]
@@ -21,6 +23,14 @@ pub struct MediaPlaybackData {
   pub is_playing: bool,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaVolumeData {
+  /// 0 while muted
+  pub level: f32,
+  pub muted: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, rspc::Type)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaTimelineData {
@@ -29,6 +39,17 @@ pub struct MediaTimelineData {
   pub timeline_position: usize,
 }
 
+/// Windows timeline values are in 100ns ticks
+pub const TICKS_PER_MS: usize = 10_000;
+
+impl MediaTimelineData {
+  pub fn duration_ms(&self) -> usize {
+    self.timeline_end_time.saturating_sub(self.timeline_start_time) / TICKS_PER_MS
+  }
+
+  pub fn position_ms(&self) -> usize { self.timeline_position / TICKS_PER_MS }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, rspc::Type)]
 #[serde(rename_all = "camelCase")]
 pub struct ThumbnailData {
@@ -47,7 +68,47 @@ pub struct MediaSessionData {
   pub is_previous_enabled: bool,
   pub is_next_enabled: bool,
   pub title: String,
+  pub normalized_title: NormalizedTitle,
   pub artists: Vec<String>,
 	pub album: String,
   pub thumbnail: ThumbnailData,
 }
+
+#[cfg(test)]
+impl MediaSessionData {
+  pub fn fixture(title: &str, artists: &[&str]) -> Self {
+    Self {
+      is_play_enabled: true,
+      is_pause_enabled: true,
+      is_play_or_pause_enabled: true,
+      is_previous_enabled: true,
+      is_next_enabled: true,
+      title: title.into(),
+      normalized_title: crate::utils::title::normalize_title(title),
+      artists: artists.iter().map(|artist| artist.to_string()).collect(),
+      album: "Album".into(),
+      thumbnail: ThumbnailData {
+        base64: "".into(),
+        palette: TailwindPalette::new("rgb(92,80,160)").unwrap(),
+        prominant_color: (92, 80, 160),
+        average_color: (92, 80, 160),
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+impl MediaTimelineData {
+  pub fn fixture(position_ms: usize, duration_ms: usize) -> Self {
+    Self {
+      timeline_start_time: 0,
+      timeline_end_time: duration_ms * TICKS_PER_MS,
+      timeline_position: position_ms * TICKS_PER_MS,
+    }
+  }
+}
+
+#[cfg(test)]
+impl MediaVolumeData {
+  pub fn fixture(level: f32) -> Self { Self { level, muted: false } }
+}
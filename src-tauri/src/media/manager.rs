@@ -8,17 +8,28 @@ use windows::Media::Control::{
   SessionsChangedEventArgs,
 };
 
+use tokio::sync::mpsc;
+
 use super::lib::EventBus;
 use super::session::Session;
+use crate::ducking::rules::AudioActivity;
+use crate::settings::store::SettingsStore;
 
 pub struct MediaManager {
   event_bus: Arc<EventBus>,
+  settings: Arc<SettingsStore>,
+  /// Handed to every session, for ducking
+  audio_activity: mpsc::UnboundedSender<AudioActivity>,
   manager: GlobalSystemMediaTransportControlsSessionManager,
   session: Arc<Mutex<Option<Session>>>,
 }
 
 impl MediaManager {
-  pub fn new(event_bus: Arc<EventBus>) -> windows::core::Result<Self> {
+  pub fn new(
+    event_bus: Arc<EventBus>,
+    settings: Arc<SettingsStore>,
+    audio_activity: mpsc::UnboundedSender<AudioActivity>,
+  ) -> windows::core::Result<Self> {
     println!("[MediaManager] new");
     let manager = GlobalSystemMediaTransportControlsSessionManager::RequestAsync()?.get()?;
 
@@ -26,6 +37,8 @@ impl MediaManager {
 			manager,
 			session: Arc::new(Mutex::new(None)),
       event_bus,
+      settings,
+      audio_activity,
     })
   }
 
@@ -41,6 +54,8 @@ impl MediaManager {
       // let inner = self.inner.clone();
 			let session = self.session.clone();
       let event_bus = self.event_bus.clone();
+      let settings = self.settings.clone();
+      let audio_activity = self.audio_activity.clone();
 
       move |sender, _| {
         let Some(manager) = sender else {
@@ -54,7 +69,7 @@ impl MediaManager {
         if let Some(active_session) = sessions
 					.borrow()
 					.into_iter()
-					.filter(has_spotify)
+					.filter(|session| is_player(session, &settings))
 					.collect::<Vec<GlobalSystemMediaTransportControlsSession>>()
 					.first() {
 						if session.lock().unwrap().as_ref().is_some() {
@@ -62,13 +77,13 @@ impl MediaManager {
 							return Ok(())
 						}
 
-						if let Ok(new_session) = Session::new(active_session.clone(), event_bus.clone()).build() {
+						if let Ok(new_session) = Session::new(active_session.clone(), event_bus.clone(), settings.clone(), audio_activity.clone()).build() {
 							*session.lock().unwrap() = Some(new_session);
 						}
 						return Ok(())
 					}
 					
-				// If Spotify is no longer active and 'session' is still allocated
+				// If the player is no longer active and 'session' is still allocated
 				// send disconnect event and deallocate
 				if let Ok(mut session) = session.lock() {
 					if let Some(media_session) = session.as_ref() {
@@ -95,6 +110,6 @@ impl MediaManager {
   pub fn arced(self) -> Arc<Self> { Arc::new(self) }
 }
 
-fn has_spotify(session: &GlobalSystemMediaTransportControlsSession) -> bool {
-  session.SourceAppUserModelId().unwrap() == "Spotify.exe"
+fn is_player(session: &GlobalSystemMediaTransportControlsSession, settings: &SettingsStore) -> bool {
+  settings.get().is_player(&session.SourceAppUserModelId().unwrap().to_string())
 }
@@ -1,3 +1,4 @@
 pub mod manager;
+pub mod position;
 pub mod session;
 pub mod lib;
\ No newline at end of file
@@ -0,0 +1,86 @@
+use super::lib::MediaTimelineData;
+
+/// Extrapolates the playback position between timeline updates, which only
+/// arrive every few seconds (or on seek). Time is unix ms.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlaybackPosition {
+  is_playing: bool,
+  position_ms: i64,
+  /// When `position_ms` was last anchored
+  anchored_at: i64,
+  duration_ms: Option<i64>,
+}
+
+impl PlaybackPosition {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn is_playing(&self) -> bool { self.is_playing }
+
+  pub fn duration_ms(&self) -> Option<i64> { self.duration_ms }
+
+  pub fn set_timeline(&mut self, data: &MediaTimelineData, now: i64) {
+    let duration_ms = data.duration_ms() as i64;
+
+    self.duration_ms = (duration_ms > 0).then_some(duration_ms);
+    self.position_ms = data.position_ms() as i64;
+    self.anchored_at = now;
+  }
+
+  pub fn set_playing(&mut self, is_playing: bool, now: i64) {
+    self.position_ms = self.position_ms(now);
+    self.anchored_at = now;
+    self.is_playing = is_playing;
+  }
+
+  /// A new track starts from the beginning until its timeline arrives
+  pub fn reset(&mut self, now: i64) {
+    self.position_ms = 0;
+    self.anchored_at = now;
+    self.duration_ms = None;
+  }
+
+  pub fn position_ms(&self, now: i64) -> i64 {
+    let elapsed = if self.is_playing { (now - self.anchored_at).max(0) } else { 0 };
+    let position = self.position_ms + elapsed;
+
+    match self.duration_ms {
+      Some(duration) => position.min(duration),
+      None => position,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn extrapolates_while_playing() {
+    let mut position = PlaybackPosition::new();
+    position.set_timeline(&MediaTimelineData::fixture(10_000, 60_000), 0);
+
+    assert_eq!(position.position_ms(5_000), 10_000);
+
+    position.set_playing(true, 5_000);
+    assert_eq!(position.position_ms(8_000), 13_000);
+
+    position.set_playing(false, 10_000);
+    assert_eq!(position.position_ms(20_000), 15_000);
+
+    position.set_playing(true, 20_000);
+    assert_eq!(position.position_ms(1_000_000), 60_000);
+  }
+
+  #[test]
+  fn re_anchors_on_timeline_updates() {
+    let mut position = PlaybackPosition::new();
+    position.set_playing(true, 0);
+    position.set_timeline(&MediaTimelineData::fixture(30_000, 60_000), 1_000);
+
+    assert_eq!(position.position_ms(2_000), 31_000);
+
+    position.reset(3_000);
+    assert_eq!(position.position_ms(4_000), 1_000);
+    assert_eq!(position.duration_ms(), None);
+  }
+}
@@ -1,4 +1,7 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use futures::Future;
 use tailwind_palette::TailwindPalette;
@@ -10,22 +13,33 @@ use windows::Media::Control::{
   PlaybackInfoChangedEventArgs,
   TimelinePropertiesChangedEventArgs,
 };
+use tokio::sync::mpsc;
+use windows::Win32::Media::Audio::{AudioSessionStateActive, AudioSessionStateExpired};
 use windows_volume_mixer::events::EventCallbacks;
 use windows_volume_mixer::{AudioSessionControl, AudioSessionManager};
 
 use super::lib::EventBus;
+use crate::ducking::rules::AudioActivity;
 use crate::media::lib::{
   MediaEvent,
   MediaPlaybackData,
   MediaSessionData,
   MediaTimelineData,
+  MediaVolumeData,
   ThumbnailData,
 };
-use crate::utils::spotify::get_all_artists;
+use crate::settings::store::SettingsStore;
+use crate::utils::spotify::CreditParser;
 use crate::utils::thumbnail::get_thumbnail_data;
+use crate::utils::title::normalize_title;
+use crate::volume::curve::{capped, faded, stepped};
+use crate::volume::mute::MuteState;
 
 type ThreadSafeOption<T> = Arc<Mutex<Option<T>>>;
 
+/// How often a fade moves the volume
+const FADE_STEP: Duration = Duration::from_millis(50);
+
 // https://internals.rust-lang.org/t/return-type-annotation-of-async-block/12561/14
 trait Outputting: Sized {
 	fn outputting<O>(self) -> Self
@@ -43,15 +57,24 @@ pub struct Session {
   playback_info_event_token: EventRegistrationToken,
   timeline_properties_event_token: EventRegistrationToken,
   pub event_bus: Arc<EventBus>,
+  settings: Arc<SettingsStore>,
 	#[allow(dead_code)]
 	audio_manager: Option<AudioSessionManager>,
-	audio_control: ThreadSafeOption<AudioSessionControl>
+	audio_control: ThreadSafeOption<AudioSessionControl>,
+	mute: Arc<Mutex<MuteState>>,
+	/// Bumped whenever the volume is set, a fade stops once it changes
+	fades: Arc<AtomicU64>,
+	/// Other apps' audio sessions by id, for ducking, until they expire
+	audio_activity: mpsc::UnboundedSender<AudioActivity>,
+	other_audio: Arc<Mutex<BTreeMap<u64, AudioSessionControl>>>,
 }
 
 impl Session {
   pub fn new(
     controls: GlobalSystemMediaTransportControlsSession,
     event_bus: Arc<EventBus>,
+    settings: Arc<SettingsStore>,
+    audio_activity: mpsc::UnboundedSender<AudioActivity>,
   ) -> Self {
     println!("[MediaSession] new");
 
@@ -61,8 +84,13 @@ impl Session {
       playback_info_event_token: EventRegistrationToken::default(),
       timeline_properties_event_token: EventRegistrationToken::default(),
       event_bus,
+      settings,
 			audio_manager: None,
-			audio_control: Arc::new(Mutex::new(None))
+			audio_control: Arc::new(Mutex::new(None)),
+			mute: Arc::new(Mutex::new(MuteState::new())),
+			fades: Arc::new(AtomicU64::new(0)),
+			audio_activity,
+			other_audio: Arc::new(Mutex::new(BTreeMap::new())),
     }
   }
 
@@ -76,6 +104,7 @@ impl Session {
       MediaPropertiesChangedEventArgs,
     >::new({
 			let event_sender = self.event_bus.0.clone();
+			let settings = self.settings.clone();
 		
 			self.invoke_playback_info_handler();
 			self.invoke_timeline_properties_handler();
@@ -89,30 +118,29 @@ impl Session {
 					let props = sender.TryGetMediaPropertiesAsync()?.await?;
 					let playback = sender.GetPlaybackInfo()?;
 					let controls = playback.Controls()?;
+					let settings = settings.get();
+					let fallback_color = settings.appearance.fallback_color;
 
 					// Wait for thumbnail to be encoded
-					let (thumbnail_palette, average_color, base64) = get_thumbnail_data(props.Thumbnail())
+					let (thumbnail_palette, average_color, base64) = get_thumbnail_data(props.Thumbnail(), settings.player.cover_crop)
 						.await
 						.unwrap_or((None, None, "".into()));
 
 					let prominant_color = thumbnail_palette
 						.as_ref()
-						.map(|palette| palette.most_prominent_color().unwrap_or((92,80,160)))
-						.unwrap_or((92,80,160));
+						.map(|palette| palette.most_prominent_color().unwrap_or(fallback_color))
+						.unwrap_or(fallback_color);
 
-					let palette = Some(prominant_color)
-						.as_ref()
-						.map(|(r, g, b)| {
-							TailwindPalette::new(format!("rgb({},{},{})", r, g, b).as_str()).unwrap()
-						})
-						.unwrap_or(TailwindPalette::new("rgb(92,80,160)").unwrap());
+					let (r, g, b) = prominant_color;
+					let palette = TailwindPalette::new(format!("rgb({},{},{})", r, g, b).as_str()).unwrap();
 
 					let average_color = average_color
 						.as_ref()
 						.map(|a| (a.r, a.g, a.b))
-						.unwrap_or((92,80,160));
+						.unwrap_or(fallback_color);
 
-					let artists = get_all_artists(props.Artist()?.to_string().as_str(), props.Title()?.to_string().as_str())?;
+					let credits = CreditParser::with_exceptions(&settings.credits.exceptions)
+						.parse(props.Artist()?.to_string().as_str(), props.Title()?.to_string().as_str());
 
 					event_sender.send(MediaEvent::MediaPropertiesChanged(
 						MediaSessionData {
@@ -122,7 +150,8 @@ impl Session {
 							is_previous_enabled: controls.IsPreviousEnabled()?,
 							is_next_enabled: controls.IsNextEnabled()?,
 							title: props.Title()?.to_string(),
-							artists,
+							normalized_title: normalize_title(&credits.title),
+							artists: credits.artists,
 							album: props.AlbumTitle()?.to_string(),
 							thumbnail: ThumbnailData {
 								base64,
@@ -245,24 +274,70 @@ impl Session {
 				let app_name = self.controls.SourceAppUserModelId().unwrap().to_string();
 				let event_sender = self.event_bus.0.clone();
 				let audio_control = audio_control.clone();
+				let mute = self.mute.clone();
+				let audio_activity = self.audio_activity.clone();
+				let other_audio = self.other_audio.clone();
+				let next_audio_id = AtomicU64::new(0);
 
 				audio_manager.on_session_created(move |session| {
 					let event_sender = event_sender.clone();
+					let mute = mute.clone();
 
 					if let Ok(process_name) = session.process_name() {
 						println!("Session: {process_name}");
 						
+						// Anything else playing may duck the player, until it stops.
+						// Sessions that already exist are reported too, most of
+						// them idle
 						if process_name != app_name {
+							let id = next_audio_id.fetch_add(1, Ordering::Relaxed);
+							let started = AudioActivity::Started { session: id, process: process_name.clone() };
+							if session.state().is_ok_and(|state| state == AudioSessionStateActive) {
+								audio_activity.send(started.clone()).ok();
+							}
+
+							let audio_activity = audio_activity.clone();
+							let expired = other_audio.clone();
+							let registered = session.register_session_notification(
+								EventCallbacks::new()
+									.on_state_changed(move |state| {
+										let activity = match state == AudioSessionStateActive {
+											true => started.clone(),
+											false => AudioActivity::Stopped { session: id },
+										};
+										audio_activity.send(activity).ok();
+
+										// Expired sessions never come back. Dropping one unregisters
+										// its notifications, which can't happen from inside them
+										if state == AudioSessionStateExpired {
+											let expired = expired.clone();
+											tauri::async_runtime::spawn(async move {
+												expired.lock().unwrap().remove(&id);
+											});
+										}
+									})
+									.build(),
+							);
+
+							match registered {
+								// Notifications stop once the session is dropped
+								Ok(_) => {
+									other_audio.lock().unwrap().insert(id, session);
+								},
+								Err(e) => println!("Session: {process_name} | Error: {:?}", e),
+							}
 							return;
 						}
 
 						let volume = session.volume_control().get_volume();
-						event_sender.send(MediaEvent::VolumeChanged(volume)).unwrap();
+						let muted = mute.lock().unwrap().observe(volume);
+						event_sender.send(MediaEvent::VolumeChanged(MediaVolumeData { level: volume, muted })).unwrap();
 
 						session.register_session_notification(
 							EventCallbacks::new()
 								.on_volume_changed(move |volume, _, _| {
-									event_sender.send(MediaEvent::VolumeChanged(volume)).unwrap();
+									let muted = mute.lock().unwrap().observe(volume);
+									event_sender.send(MediaEvent::VolumeChanged(MediaVolumeData { level: volume, muted })).unwrap();
 								})
 								.build(),
 						).unwrap();
@@ -313,6 +388,11 @@ impl Session {
       .controls
       .RemoveTimelinePropertiesChanged(self.timeline_properties_event_token)?;
 
+    // Their sessions go with ours
+    for (id, _) in std::mem::take(&mut *self.other_audio.lock().unwrap()) {
+      self.audio_activity.send(AudioActivity::Stopped { session: id }).ok();
+    }
+
     self
       .event_bus
       .0
@@ -346,21 +426,79 @@ impl Session {
     self.controls.TryChangePlaybackPositionAsync(value).unwrap();
   }
 
-  pub fn get_volume(&self) -> f32 {
-    // println!("tried to get volume");
-
-		if let Some(session) = self.audio_control.lock().unwrap().as_ref() {
-			return session.volume_control().get_volume()
-		}
-
-    -1.0
+  /// `None` until the player's audio session shows up
+  pub fn get_volume(&self) -> Option<f32> {
+		self
+			.audio_control
+			.lock()
+			.unwrap()
+			.as_ref()
+			.map(|session| session.volume_control().get_volume())
   }
 
   pub fn set_volume(&self, volume: f32) {
-    // println!("tried to set volume: {volume}");
+		// Setting it directly takes over from a fade
+		self.fades.fetch_add(1, Ordering::Relaxed);
+		set_level(&self.audio_control, capped(volume, &self.settings.get().volume));
+  }
 
-		if let Some(session) = self.audio_control.lock().unwrap().as_ref() {
-			session.volume_control().set_volume(volume.clamp(0f32, 1f32)).unwrap();
+  pub fn is_muted(&self) -> bool { self.mute.lock().unwrap().is_muted() }
+
+  pub fn set_muted(&self, muted: bool) {
+		let Some(current) = self.get_volume() else {
+			return;
+		};
+
+		let level = match muted {
+			true => self.mute.lock().unwrap().mute(current),
+			false => self.mute.lock().unwrap().unmute(),
+		};
+		if let Some(level) = level {
+			self.set_volume(level);
 		}
   }
+
+  pub fn toggle_mute(&self) { self.set_muted(!self.is_muted()) }
+
+  /// Moves the volume `steps` steps along the configured curve, from where
+  /// it was muted if it is
+  pub fn step_volume(&self, steps: i32) {
+		let Some(current) = self.get_volume() else {
+			return;
+		};
+
+		let current = self.mute.lock().unwrap().unmute().unwrap_or(current);
+		self.set_volume(stepped(current, steps, &self.settings.get().volume));
+  }
+
+  /// Moves the volume to `volume` over `duration`, until it's set some
+  /// other way
+  pub fn fade_volume(&self, volume: f32, duration: Duration) {
+		let Some(from) = self.get_volume() else {
+			return;
+		};
+
+		let fade = self.fades.fetch_add(1, Ordering::Relaxed) + 1;
+		let fades = self.fades.clone();
+		let audio_control = self.audio_control.clone();
+		let settings = self.settings.clone();
+		let steps = (duration.as_millis() / FADE_STEP.as_millis()).max(1) as u32;
+
+		tauri::async_runtime::spawn(async move {
+			for step in 1..=steps {
+				tokio::time::sleep(FADE_STEP).await;
+				if fades.load(Ordering::Relaxed) != fade {
+					return;
+				}
+
+				set_level(&audio_control, faded(from, volume, step as f32 / steps as f32, &settings.get().volume));
+			}
+		});
+  }
+}
+
+fn set_level(audio_control: &Mutex<Option<AudioSessionControl>>, volume: f32) {
+	if let Some(session) = audio_control.lock().unwrap().as_ref() {
+		session.volume_control().set_volume(volume.clamp(0f32, 1f32)).unwrap();
+	}
 }
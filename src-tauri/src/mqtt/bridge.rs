@@ -0,0 +1,367 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use super::discovery;
+use super::state::MqttState;
+use crate::media::lib::MediaEvent;
+use crate::router::media::{MediaControl, Method};
+use crate::utils::fs::read_json;
+use crate::utils::time::now_ms;
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MqttConfig {
+  pub host: String,
+  #[serde(default = "default_port")]
+  pub port: u16,
+  #[serde(default = "default_client_id")]
+  pub client_id: String,
+  #[serde(default)]
+  pub username: Option<String>,
+  #[serde(default)]
+  pub password: Option<String>,
+  /// Every topic lives under this, e.g. `media-widget/state`
+  #[serde(default = "default_client_id")]
+  pub topic_prefix: String,
+  /// Publish Home Assistant discovery configs
+  #[serde(default = "default_discovery")]
+  pub discovery: bool,
+  #[serde(default = "default_discovery_prefix")]
+  pub discovery_prefix: String,
+}
+
+fn default_port() -> u16 { 1883 }
+
+fn default_client_id() -> String { "media-widget".into() }
+
+fn default_discovery() -> bool { true }
+
+fn default_discovery_prefix() -> String { "homeassistant".into() }
+
+impl MqttConfig {
+  /// A missing file leaves MQTT disabled
+  pub fn load(path: &Path) -> anyhow::Result<Option<Self>> { read_json(path) }
+
+  fn topic(&self, name: &str) -> String { format!("{}/{}", self.topic_prefix, name) }
+
+  /// Only keeps `[a-zA-Z0-9_]`, everything else becomes `_`
+  fn node_id(&self) -> String {
+    self
+      .client_id
+      .chars()
+      .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+      .collect()
+  }
+
+  fn options(&self) -> MqttOptions {
+    let mut options = MqttOptions::new(&self.client_id, &self.host, self.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(self.topic("availability"), "offline", QoS::AtLeastOnce, true));
+
+    if let Some(username) = &self.username {
+      options.set_credentials(username, self.password.clone().unwrap_or_default());
+    }
+
+    options
+  }
+}
+
+/// Commands on `<prefix>/set`: a `Method` as JSON like the rspc router takes
+/// it (`"play"`, `{"setVolume": 0.4}`), or one of the bare words
+/// `play`, `pause`, `next`, `previous`
+pub fn parse_command(payload: &[u8]) -> anyhow::Result<Method> {
+  let payload = std::str::from_utf8(payload)?.trim();
+
+  if let Ok(method) = serde_json::from_str::<Method>(payload) {
+    return match method {
+      Method::SetVolume(volume) | Method::FadeVolume { volume, .. } if !(0.0..=1.0).contains(&volume) => {
+        Err(anyhow!("Volume {volume} is not between 0 and 1"))
+      },
+      method => Ok(method),
+    };
+  }
+
+  match payload.to_lowercase().as_str() {
+    "play" => Ok(Method::Play),
+    "pause" => Ok(Method::Pause),
+    "next" => Ok(Method::Next),
+    "previous" | "prev" => Ok(Method::Previous),
+    _ => Err(anyhow!("Unknown command '{payload}'")),
+  }
+}
+
+/// Publishes the media state to retained topics and forwards commands from
+/// `<prefix>/set`, reconnecting with backoff whenever the broker goes away
+pub fn spawn(
+  config: Option<MqttConfig>,
+  control: Arc<dyn MediaControl>,
+  events: broadcast::Receiver<MediaEvent>,
+) -> Option<JoinHandle<()>> {
+  let Some(config) = config else {
+    println!("[MqttBridge] not configured");
+    return None;
+  };
+
+  println!("[MqttBridge] spawn {}:{}", config.host, config.port);
+
+  Some(tokio::spawn(run(config, control, events)))
+}
+
+async fn run(config: MqttConfig, control: Arc<dyn MediaControl>, mut events: broadcast::Receiver<MediaEvent>) {
+  let (client, mut eventloop) = AsyncClient::new(config.options(), 64);
+  let mut state = MqttState::new();
+  let mut connected = false;
+  let mut backoff = MIN_BACKOFF;
+  let mut retry_at: Option<Instant> = None;
+
+  loop {
+    tokio::select! {
+      _ = sleep_until(retry_at), if retry_at.is_some() => retry_at = None,
+      notification = eventloop.poll(), if retry_at.is_none() => match notification {
+        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+          println!("[MqttBridge] connected");
+          connected = true;
+          backoff = MIN_BACKOFF;
+          state.forget_published();
+
+          if let Err(err) = announce(&client, &config) {
+            println!("[MqttBridge] announce | Error: {}", err);
+          }
+          publish_state(&client, &config, &mut state);
+        },
+        Ok(Event::Incoming(Packet::Publish(publish))) if publish.topic == config.topic("set") => {
+          let result = parse_command(&publish.payload).and_then(|method| control.invoke(method));
+          if let Err(err) = result {
+            println!("[MqttBridge] command | Error: {}", err);
+          }
+        },
+        Ok(_) => {},
+        Err(err) => {
+          println!("[MqttBridge] Error: {}, retrying in {:?}", err, backoff);
+          connected = false;
+          retry_at = Some(Instant::now() + backoff);
+          backoff = (backoff * 2).min(MAX_BACKOFF);
+        },
+      },
+      event = events.recv() => match event {
+        Ok(event) => {
+          state.handle(&event, now_ms());
+          if connected {
+            publish_state(&client, &config, &mut state);
+          }
+        },
+        Err(RecvError::Lagged(_)) => continue,
+        Err(RecvError::Closed) => break,
+      },
+    }
+  }
+}
+
+async fn sleep_until(at: Option<Instant>) {
+  if let Some(at) = at {
+    tokio::time::sleep_until(at).await;
+  }
+}
+
+fn announce(client: &AsyncClient, config: &MqttConfig) -> anyhow::Result<()> {
+  client.try_subscribe(config.topic("set"), QoS::AtLeastOnce)?;
+  client.try_publish(config.topic("availability"), QoS::AtLeastOnce, true, "online")?;
+
+  if config.discovery {
+    for (topic, payload) in discovery::messages(&config.discovery_prefix, &config.node_id(), &config.topic_prefix) {
+      client.try_publish(topic, QoS::AtLeastOnce, true, payload.to_string())?;
+    }
+  }
+
+  Ok(())
+}
+
+fn publish_state(client: &AsyncClient, config: &MqttConfig, state: &mut MqttState) {
+  for (topic, payload) in state.changes(&config.topic_prefix, now_ms()) {
+    if let Err(err) = client.try_publish(topic, QoS::AtLeastOnce, true, payload) {
+      println!("[MqttBridge] publish | Error: {}", err);
+      // Publish everything again next time rather than leaving gaps
+      state.forget_published();
+      break;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use bytes::BytesMut;
+  use rumqttc::mqttbytes::v4::{read, ConnAck, ConnectReturnCode, PubAck, Publish, SubAck, SubscribeReasonCode};
+  use tokio::io::{AsyncReadExt, AsyncWriteExt};
+  use tokio::net::{TcpListener, TcpStream};
+  use tokio::sync::mpsc;
+
+  use super::*;
+  use crate::media::lib::MediaSessionData;
+  use crate::router::media::stub::RecordingControl;
+
+  #[test]
+  fn parses_commands() {
+    let cases: [(&str, Option<Method>); 8] = [
+      ("play", Some(Method::Play)),
+      (" PAUSE\n", Some(Method::Pause)),
+      ("prev", Some(Method::Previous)),
+      ("\"next\"", Some(Method::Next)),
+      (r#"{"setVolume": 0.4}"#, Some(Method::SetVolume(0.4))),
+      (r#"{"setPlaybackPosition": 10000}"#, Some(Method::SetPlaybackPosition(10_000))),
+      (r#"{"setVolume": 40}"#, None),
+      ("dance", None),
+    ];
+
+    for (payload, expected) in cases {
+      assert_eq!(parse_command(payload.as_bytes()).ok(), expected, "{payload}");
+    }
+  }
+
+  #[test]
+  fn sanitizes_the_node_id() {
+    let config: MqttConfig = serde_json::from_str(r#"{"host": "localhost", "clientId": "media widget.1"}"#).unwrap();
+
+    assert_eq!(config.node_id(), "media_widget_1");
+    assert_eq!(config.topic_prefix, "media-widget");
+    assert_eq!(config.port, 1883);
+  }
+
+  /// Just enough of an MQTT 3.1.1 broker for one client: reports what it
+  /// receives, and publishes what it's given to the client
+  async fn fake_broker(
+    listener: &TcpListener,
+    received: mpsc::UnboundedSender<Packet>,
+    mut outgoing: mpsc::UnboundedReceiver<Publish>,
+  ) {
+    let (mut stream, _): (TcpStream, _) = listener.accept().await.unwrap();
+    let mut buffer = BytesMut::new();
+
+    loop {
+      tokio::select! {
+        read_bytes = stream.read_buf(&mut buffer) => {
+          if read_bytes.unwrap_or(0) == 0 {
+            return;
+          }
+
+          while let Ok(packet) = read(&mut buffer, 1024 * 1024) {
+            let mut reply = BytesMut::new();
+            match &packet {
+              Packet::Connect(_) => {
+                ConnAck::new(ConnectReturnCode::Success, false).write(&mut reply).unwrap();
+              },
+              Packet::Subscribe(subscribe) => {
+                SubAck::new(subscribe.pkid, vec![SubscribeReasonCode::Success(QoS::AtLeastOnce)])
+                  .write(&mut reply)
+                  .unwrap();
+              },
+              Packet::Publish(publish) if publish.qos == QoS::AtLeastOnce => {
+                PubAck::new(publish.pkid).write(&mut reply).unwrap();
+              },
+              Packet::Disconnect => return,
+              _ => {},
+            }
+
+            stream.write_all(&reply).await.unwrap();
+            if received.send(packet).is_err() {
+              return;
+            }
+          }
+        },
+        publish = outgoing.recv() => {
+          let Some(publish) = publish else { return };
+          let mut bytes = BytesMut::new();
+          publish.write(&mut bytes).unwrap();
+          stream.write_all(&bytes).await.unwrap();
+        },
+      }
+    }
+  }
+
+  async fn next_publish(received: &mut mpsc::UnboundedReceiver<Packet>, topic: &str) -> Publish {
+    tokio::time::timeout(Duration::from_secs(5), async {
+      loop {
+        if let Some(Packet::Publish(publish)) = received.recv().await {
+          if publish.topic == topic {
+            return publish;
+          }
+        }
+      }
+    })
+    .await
+    .unwrap()
+  }
+
+  #[tokio::test]
+  async fn bridges_state_and_commands() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let config = MqttConfig {
+      host: "127.0.0.1".into(),
+      port: listener.local_addr().unwrap().port(),
+      ..serde_json::from_str(r#"{"host": ""}"#).unwrap()
+    };
+
+    let (received_tx, mut received) = mpsc::unbounded_channel();
+    let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+    let broker = tokio::spawn(async move {
+      fake_broker(&listener, received_tx.clone(), outgoing_rx).await;
+      listener
+    });
+
+    let control = Arc::new(RecordingControl::default());
+    let (events, events_rx) = broadcast::channel(16);
+    let bridge = tokio::spawn(run(config, control.clone(), events_rx));
+
+    let Some(Packet::Connect(connect)) = received.recv().await else {
+      panic!("Expected a connect");
+    };
+    let will = connect.last_will.unwrap();
+    assert_eq!((will.topic.as_str(), &will.message[..], will.retain), ("media-widget/availability", &b"offline"[..], true));
+
+    let online = next_publish(&mut received, "media-widget/availability").await;
+    assert_eq!((&online.payload[..], online.retain), (&b"online"[..], true));
+    let discovery = next_publish(&mut received, "homeassistant/button/media_widget/next/config").await;
+    assert!(discovery.retain);
+    // Nothing is playing yet
+    assert!(next_publish(&mut received, "media-widget/title").await.payload.is_empty());
+
+    events
+      .send(MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A"])))
+      .unwrap();
+    let title = next_publish(&mut received, "media-widget/title").await;
+    assert_eq!((&title.payload[..], title.retain), (&b"Song"[..], true));
+
+    outgoing.send(Publish::new("media-widget/set", QoS::AtMostOnce, "next")).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+      while control.invocations.lock().unwrap().is_empty() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+      }
+    })
+    .await
+    .unwrap();
+    assert_eq!(*control.invocations.lock().unwrap(), vec![Method::Next]);
+
+    // Drop the connection, the bridge comes back and publishes everything again
+    drop(outgoing);
+    let listener = broker.await.unwrap();
+    let (received_tx, mut received) = mpsc::unbounded_channel();
+    let (_outgoing, outgoing_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move { fake_broker(&listener, received_tx, outgoing_rx).await });
+
+    let title = next_publish(&mut received, "media-widget/title").await;
+    assert_eq!(&title.payload[..], b"Song");
+
+    bridge.abort();
+  }
+}
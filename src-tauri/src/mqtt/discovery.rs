@@ -0,0 +1,89 @@
+use serde_json::{json, Value};
+
+/// Home Assistant MQTT discovery: one retained config message per entity,
+/// all grouped under a single device
+/// https://www.home-assistant.io/integrations/mqtt/#mqtt-discovery
+pub fn messages(discovery_prefix: &str, node_id: &str, topic_prefix: &str) -> Vec<(String, Value)> {
+  let device = json!({
+    "identifiers": [node_id],
+    "name": "Media Widget",
+    "model": "media-widget",
+    "sw_version": env!("CARGO_PKG_VERSION"),
+  });
+  let availability = format!("{topic_prefix}/availability");
+  let state = format!("{topic_prefix}/state");
+  let set = format!("{topic_prefix}/set");
+
+  let mut entities = vec![
+    ("sensor", "title", json!({ "name": "Title", "state_topic": state, "value_template": "{{ value_json.title }}", "icon": "mdi:music" })),
+    ("sensor", "artists", json!({ "name": "Artists", "state_topic": state, "value_template": "{{ value_json.artists | join(', ') }}", "icon": "mdi:account-music" })),
+    ("sensor", "album", json!({ "name": "Album", "state_topic": state, "value_template": "{{ value_json.album }}", "icon": "mdi:album" })),
+    ("binary_sensor", "playing", json!({ "name": "Playing", "state_topic": format!("{topic_prefix}/playing"), "icon": "mdi:play-pause" })),
+    ("number", "volume", json!({
+      "name": "Volume",
+      "state_topic": format!("{topic_prefix}/volume"),
+      "command_topic": set,
+      "command_template": "{\"setVolume\": {{ value / 100 }}}",
+      "min": 0,
+      "max": 100,
+      "step": 1,
+      "unit_of_measurement": "%",
+      "icon": "mdi:volume-high",
+    })),
+  ];
+
+  for (command, name, icon) in [
+    ("play", "Play", "mdi:play"),
+    ("pause", "Pause", "mdi:pause"),
+    ("next", "Next", "mdi:skip-next"),
+    ("previous", "Previous", "mdi:skip-previous"),
+  ] {
+    entities.push(("button", command, json!({ "name": name, "command_topic": set, "payload_press": command, "icon": icon })));
+  }
+
+  entities
+    .into_iter()
+    .map(|(component, object_id, mut config)| {
+      config["unique_id"] = json!(format!("{node_id}_{object_id}"));
+      config["availability_topic"] = json!(availability);
+      config["device"] = device.clone();
+
+      (format!("{discovery_prefix}/{component}/{node_id}/{object_id}/config"), config)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn describes_every_entity() {
+    let messages = messages("homeassistant", "media_widget", "media-widget");
+
+    assert_eq!(
+      messages.iter().map(|(topic, _)| topic.as_str()).collect::<Vec<_>>(),
+      vec![
+        "homeassistant/sensor/media_widget/title/config",
+        "homeassistant/sensor/media_widget/artists/config",
+        "homeassistant/sensor/media_widget/album/config",
+        "homeassistant/binary_sensor/media_widget/playing/config",
+        "homeassistant/number/media_widget/volume/config",
+        "homeassistant/button/media_widget/play/config",
+        "homeassistant/button/media_widget/pause/config",
+        "homeassistant/button/media_widget/next/config",
+        "homeassistant/button/media_widget/previous/config",
+      ]
+    );
+
+    for (_, config) in &messages {
+      assert_eq!(config["availability_topic"], "media-widget/availability");
+      assert_eq!(config["device"]["identifiers"][0], "media_widget");
+    }
+
+    let (_, next) = &messages[7];
+    assert_eq!(next["command_topic"], "media-widget/set");
+    assert_eq!(next["payload_press"], "next");
+    assert_eq!(next["unique_id"], "media_widget_next");
+  }
+}
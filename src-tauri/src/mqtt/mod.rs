@@ -0,0 +1,3 @@
+pub mod bridge;
+pub mod discovery;
+pub mod state;
\ No newline at end of file
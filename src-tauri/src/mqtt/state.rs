@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::control::protocol::Status;
+use crate::control::state::ControlState;
+use crate::media::lib::MediaEvent;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverColors {
+  pub prominent: (u8, u8, u8),
+  pub average: (u8, u8, u8),
+}
+
+/// Turns media events into retained state messages, only yielding the topics
+/// whose payload changed since they were last published
+#[derive(Debug, Default)]
+pub struct MqttState {
+  status: ControlState,
+  colors: Option<CoverColors>,
+  published: HashMap<String, String>,
+}
+
+impl MqttState {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn handle(&mut self, event: &MediaEvent, now: i64) {
+    self.status.handle(event, now);
+
+    match event {
+      MediaEvent::MediaPropertiesChanged(data) => {
+        self.colors = Some(CoverColors {
+          prominent: data.thumbnail.prominant_color,
+          average: data.thumbnail.average_color,
+        })
+      },
+      MediaEvent::Disconnect(_) => self.colors = None,
+      _ => {},
+    }
+  }
+
+  /// After a reconnect everything has to be published again
+  pub fn forget_published(&mut self) { self.published.clear(); }
+
+  /// `(topic, payload)` for every state topic that changed
+  pub fn changes(&mut self, prefix: &str, now: i64) -> Vec<(String, String)> {
+    let mut changes = messages(prefix, &self.status.status(now), self.colors.as_ref());
+    changes.retain(|(topic, payload)| self.published.get(topic) != Some(payload));
+
+    for (topic, payload) in &changes {
+      self.published.insert(topic.clone(), payload.clone());
+    }
+
+    changes
+  }
+}
+
+fn messages(prefix: &str, status: &Status, colors: Option<&CoverColors>) -> Vec<(String, String)> {
+  let rgb = |color: Option<(u8, u8, u8)>| color.map(|(r, g, b)| format!("{r},{g},{b}")).unwrap_or_default();
+  let text = |value: &Option<String>| value.clone().unwrap_or_default();
+
+  let state = json!({
+    "app": status.app,
+    "title": status.title,
+    "artists": status.artists,
+    "album": status.album,
+    "isPlaying": status.is_playing,
+    "positionMs": status.position_ms,
+    "durationMs": status.duration_ms,
+    "volume": status.volume,
+    "colors": colors,
+  });
+
+  [
+    ("state", state.to_string()),
+    ("title", text(&status.title)),
+    ("artists", status.artists.join(", ")),
+    ("album", text(&status.album)),
+    ("playing", if status.is_playing { "ON" } else { "OFF" }.into()),
+    ("position", status.position_ms.map(|ms| (ms / 1000).to_string()).unwrap_or_default()),
+    ("duration", status.duration_ms.map(|ms| (ms / 1000).to_string()).unwrap_or_default()),
+    ("volume", status.volume.map(|volume| format!("{:.0}", volume * 100.0)).unwrap_or_default()),
+    ("cover/prominent", rgb(colors.map(|colors| colors.prominent))),
+    ("cover/average", rgb(colors.map(|colors| colors.average))),
+  ]
+  .into_iter()
+  .map(|(topic, payload)| (format!("{prefix}/{topic}"), payload))
+  .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::{MediaPlaybackData, MediaSessionData, MediaVolumeData};
+
+  fn topics(changes: &[(String, String)]) -> Vec<&str> { changes.iter().map(|(topic, _)| topic.as_str()).collect() }
+
+  #[test]
+  fn publishes_only_what_changed() {
+    let mut state = MqttState::new();
+    assert_eq!(state.changes("mw", 0).len(), 10);
+    assert!(state.changes("mw", 0).is_empty());
+
+    let mut data = MediaSessionData::fixture("Song", &["A", "B"]);
+    data.thumbnail.prominant_color = (1, 2, 3);
+    state.handle(&MediaEvent::MediaPropertiesChanged(data), 0);
+
+    let changes = state.changes("mw", 0);
+    let payload = |topic: &str| changes.iter().find(|(t, _)| t == topic).map(|(_, payload)| payload.as_str());
+    assert_eq!(payload("mw/title"), Some("Song"));
+    assert_eq!(payload("mw/artists"), Some("A, B"));
+    assert_eq!(payload("mw/cover/prominent"), Some("1,2,3"));
+    assert_eq!(payload("mw/playing"), None);
+
+    let state_json: serde_json::Value = serde_json::from_str(payload("mw/state").unwrap()).unwrap();
+    assert_eq!(state_json["title"], "Song");
+    assert_eq!(state_json["colors"]["prominent"], json!([1, 2, 3]));
+
+    state.handle(&MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing: true }), 0);
+    assert_eq!(topics(&state.changes("mw", 0)), vec!["mw/state", "mw/playing"]);
+
+    state.forget_published();
+    assert_eq!(state.changes("mw", 0).len(), 10);
+  }
+
+  #[test]
+  fn clears_on_disconnect() {
+    let mut state = MqttState::new();
+    state.handle(&MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A"])), 0);
+    state.handle(&MediaEvent::VolumeChanged(MediaVolumeData::fixture(0.25)), 0);
+    state.changes("mw", 0);
+
+    state.handle(&MediaEvent::Disconnect("Spotify.exe".into()), 0);
+    let changes = state.changes("mw", 0);
+
+    assert!(changes.iter().any(|(topic, payload)| topic == "mw/title" && payload.is_empty()));
+    assert!(changes.iter().any(|(topic, payload)| topic == "mw/volume" && payload.is_empty()));
+  }
+}
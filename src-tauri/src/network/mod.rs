@@ -0,0 +1,2 @@
+pub mod monitor;
+pub mod probe;
\ No newline at end of file
@@ -0,0 +1,296 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::probe::ProbeTarget;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum Connectivity {
+  /// Before the first probe finishes, assumed to be online
+  Unknown,
+  Online,
+  /// Some probes fail, or every probe is slow
+  Degraded,
+  Offline,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatus {
+  pub connectivity: Connectivity,
+  /// Latency of the fastest successful probe
+  pub latency_ms: Option<u32>,
+}
+
+impl NetworkStatus {
+  pub fn is_online(&self) -> bool { self.connectivity != Connectivity::Offline }
+}
+
+#[derive(Debug, Clone)]
+pub struct MonitorConfig {
+  /// Usually `network.probeTargets` from the settings
+  pub targets: Vec<ProbeTarget>,
+  /// Time between probes while online
+  pub interval: Duration,
+  /// Upper bound for the interval while offline, which doubles on every
+  /// failed round
+  pub max_backoff: Duration,
+  pub timeout: Duration,
+  /// Latency above which we report `Degraded`
+  pub degraded_latency: Duration,
+}
+
+impl MonitorConfig {
+  pub fn new(targets: Vec<ProbeTarget>) -> Self {
+    Self {
+      targets,
+      interval: Duration::from_secs(5),
+      max_backoff: Duration::from_secs(60),
+      timeout: Duration::from_secs(3),
+      degraded_latency: Duration::from_millis(1500),
+    }
+  }
+}
+
+/// Probes the configured targets in the background and publishes every change
+/// in connectivity, so nothing has to block on a network check.
+pub struct ConnectivityMonitor {
+  status: watch::Receiver<NetworkStatus>,
+  task: JoinHandle<()>,
+}
+
+impl ConnectivityMonitor {
+  pub fn spawn(config: MonitorConfig) -> Self {
+    println!("[ConnectivityMonitor] spawn");
+
+    let (sender, status) = watch::channel(NetworkStatus {
+      connectivity: Connectivity::Unknown,
+      latency_ms: None,
+    });
+
+    let task = tokio::spawn(async move {
+      let client = reqwest::Client::new();
+      let mut delay = config.interval;
+
+      loop {
+        let status = check(&config, &client).await;
+
+        delay = next_delay(&config, delay, &status);
+
+        sender.send_if_modified(|current| {
+          if *current == status {
+            return false;
+          }
+
+          println!("[ConnectivityMonitor] {:?}", status);
+          *current = status;
+          true
+        });
+
+        tokio::time::sleep(delay).await;
+      }
+    });
+
+    Self { status, task }
+  }
+
+  pub fn status(&self) -> NetworkStatus { self.status.borrow().clone() }
+
+  pub fn subscribe(&self) -> watch::Receiver<NetworkStatus> { self.status.clone() }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+}
+
+impl Drop for ConnectivityMonitor {
+  fn drop(&mut self) { self.task.abort(); }
+}
+
+/// Runs every probe concurrently and folds the results into one status
+pub async fn check(config: &MonitorConfig, client: &reqwest::Client) -> NetworkStatus {
+  let results = join_all(
+    config
+      .targets
+      .iter()
+      .map(|target| target.probe(client, config.timeout)),
+  )
+  .await;
+
+  let latencies = results
+    .iter()
+    .filter_map(|result| result.as_ref().ok())
+    .collect::<Vec<_>>();
+
+  let Some(fastest) = latencies.iter().min().copied() else {
+    return NetworkStatus {
+      connectivity: Connectivity::Offline,
+      latency_ms: None,
+    };
+  };
+
+  let connectivity = if latencies.len() < results.len() || *fastest > config.degraded_latency {
+    Connectivity::Degraded
+  } else {
+    Connectivity::Online
+  };
+
+  NetworkStatus {
+    connectivity,
+    latency_ms: Some(fastest.as_millis().min(u32::MAX as u128) as u32),
+  }
+}
+
+/// Doubles the delay while offline, up to `max_backoff`
+fn next_delay(config: &MonitorConfig, delay: Duration, status: &NetworkStatus) -> Duration {
+  if status.is_online() {
+    return config.interval;
+  }
+
+  (delay * 2).min(config.max_backoff).max(config.interval)
+}
+
+#[cfg(test)]
+mod tests {
+  use tokio::io::{AsyncReadExt, AsyncWriteExt};
+  use tokio::net::TcpListener;
+
+  use super::*;
+
+  fn config(targets: Vec<ProbeTarget>) -> MonitorConfig {
+    MonitorConfig {
+      interval: Duration::from_millis(20),
+      max_backoff: Duration::from_millis(80),
+      timeout: Duration::from_millis(500),
+      degraded_latency: Duration::from_secs(5),
+      ..MonitorConfig::new(targets)
+    }
+  }
+
+  /// An address nothing is listening on
+  async fn closed_address() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().to_string()
+  }
+
+  #[tokio::test]
+  async fn reports_online_when_every_probe_succeeds() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    let status = check(&config(vec![ProbeTarget::Tcp(address)]), &reqwest::Client::new()).await;
+
+    assert_eq!(status.connectivity, Connectivity::Online);
+    assert!(status.latency_ms.is_some());
+  }
+
+  #[tokio::test]
+  async fn reports_degraded_when_some_probes_fail() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    let status = check(
+      &config(vec![ProbeTarget::Tcp(address), ProbeTarget::Tcp(closed_address().await)]),
+      &reqwest::Client::new(),
+    )
+    .await;
+
+    assert_eq!(status.connectivity, Connectivity::Degraded);
+  }
+
+  #[tokio::test]
+  async fn reports_offline_when_every_probe_fails() {
+    let status = check(
+      &config(vec![ProbeTarget::Tcp(closed_address().await)]),
+      &reqwest::Client::new(),
+    )
+    .await;
+
+    assert_eq!(status, NetworkStatus {
+      connectivity: Connectivity::Offline,
+      latency_ms: None,
+    });
+  }
+
+  #[tokio::test]
+  async fn probes_dns_and_http_targets() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+      while let Ok((mut stream, _)) = listener.accept().await {
+        let mut buf = [0; 1024];
+        let _ = stream.read(&mut buf).await;
+        let _ = stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await;
+      }
+    });
+
+    let status = check(
+      &config(vec![
+        ProbeTarget::Dns("localhost".into()),
+        ProbeTarget::Http(format!("http://{address}/generate_204")),
+      ]),
+      &reqwest::Client::new(),
+    )
+    .await;
+
+    assert_eq!(status.connectivity, Connectivity::Online);
+  }
+
+  #[tokio::test]
+  async fn publishes_changes() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    let monitor = ConnectivityMonitor::spawn(config(vec![ProbeTarget::Tcp(address)]));
+    let mut status = monitor.subscribe();
+    assert_eq!(status.borrow().connectivity, Connectivity::Unknown);
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+      while !status.borrow().is_online() {
+        status.changed().await.unwrap();
+      }
+    })
+    .await
+    .unwrap();
+
+    drop(listener);
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+      while status.borrow().is_online() {
+        status.changed().await.unwrap();
+      }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(monitor.status().connectivity, Connectivity::Offline);
+  }
+
+  #[test]
+  fn backs_off_while_offline() {
+    let config = config(vec![]);
+    let offline = NetworkStatus {
+      connectivity: Connectivity::Offline,
+      latency_ms: None,
+    };
+    let online = NetworkStatus {
+      connectivity: Connectivity::Online,
+      latency_ms: Some(1),
+    };
+
+    let mut delay = config.interval;
+    let delays = (0..4)
+      .map(|_| {
+        delay = next_delay(&config, delay, &offline);
+        delay.as_millis()
+      })
+      .collect::<Vec<_>>();
+
+    assert_eq!(delays, vec![40, 80, 80, 80]);
+    assert_eq!(next_delay(&config, delay, &online), config.interval);
+  }
+}
@@ -0,0 +1,47 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tokio::net::{lookup_host, TcpStream};
+use tokio::time::timeout;
+
+/// Something to reach out to in order to decide whether we are online.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase", tag = "kind", content = "target")]
+pub enum ProbeTarget {
+  /// Open a TCP connection to `host:port`
+  Tcp(String),
+  /// Resolve a hostname
+  Dns(String),
+  /// Send a `HEAD` request and expect a non-error status
+  Http(String),
+}
+
+impl ProbeTarget {
+  /// Returns how long the probe took to succeed.
+  pub async fn probe(&self, client: &reqwest::Client, limit: Duration) -> anyhow::Result<Duration> {
+    let started = Instant::now();
+
+    timeout(limit, async {
+      match self {
+        Self::Tcp(address) => {
+          TcpStream::connect(address).await?;
+        },
+        Self::Dns(host) => {
+          if lookup_host((host.as_str(), 0)).await?.next().is_none() {
+            bail!("'{host}' did not resolve to any address");
+          }
+        },
+        Self::Http(url) => {
+          client.head(url).send().await?.error_for_status()?;
+        },
+      }
+
+      Ok(())
+    })
+    .await
+    .map_err(|_| anyhow!("{self:?} timed out after {limit:?}"))??;
+
+    Ok(started.elapsed())
+  }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use async_trait::async_trait;
+use zbus::zvariant::Value;
+use zbus::{dbus_proxy, Connection};
+
+use super::notifier::Notifier;
+use super::track::TrackNotification;
+
+/// https://specifications.freedesktop.org/notification-spec/latest/
+#[dbus_proxy(
+  interface = "org.freedesktop.Notifications",
+  default_service = "org.freedesktop.Notifications",
+  default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+  #[allow(clippy::too_many_arguments)]
+  fn notify(
+    &self,
+    app_name: &str,
+    replaces_id: u32,
+    app_icon: &str,
+    summary: &str,
+    body: &str,
+    actions: &[&str],
+    hints: HashMap<&str, Value<'_>>,
+    expire_timeout: i32,
+  ) -> zbus::Result<u32>;
+}
+
+/// Shows track changes through the desktop's notification server, replacing
+/// the previous one instead of stacking them up
+pub struct FreedesktopNotifier {
+  proxy: NotificationsProxy<'static>,
+  /// Servers take images by path, the latest cover is written here
+  cover_path: PathBuf,
+  last_id: AtomicU32,
+}
+
+impl FreedesktopNotifier {
+  pub async fn connect(cover_path: PathBuf) -> anyhow::Result<Self> {
+    Self::with_connection(Connection::session().await?, cover_path).await
+  }
+
+  pub async fn with_connection(connection: Connection, cover_path: PathBuf) -> anyhow::Result<Self> {
+    Ok(Self {
+      proxy: NotificationsProxy::new(&connection).await?,
+      cover_path,
+      last_id: AtomicU32::new(0),
+    })
+  }
+}
+
+#[async_trait]
+impl Notifier for FreedesktopNotifier {
+  async fn show(&self, notification: &TrackNotification) -> anyhow::Result<()> {
+    let mut hints = HashMap::from([
+      ("category", Value::from("x-gnome.music")),
+      ("desktop-entry", Value::from("media-widget")),
+      // Keep track changes out of the notification history
+      ("transient", Value::from(true)),
+    ]);
+
+    if let Some(png) = &notification.cover_png {
+      tokio::fs::write(&self.cover_path, png).await?;
+      hints.insert("image-path", Value::from(format!("file://{}", self.cover_path.display())));
+    }
+
+    let id = self
+      .proxy
+      .notify(
+        "Media Widget",
+        self.last_id.load(Ordering::Relaxed),
+        "",
+        &notification.title,
+        &notification.body(),
+        &[],
+        hints,
+        -1,
+      )
+      .await?;
+
+    self.last_id.store(id, Ordering::Relaxed);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use tokio::net::UnixStream;
+  use zbus::zvariant::OwnedValue;
+  use zbus::{dbus_interface, ConnectionBuilder, Guid};
+
+  use super::*;
+
+  #[derive(Debug, Clone, PartialEq)]
+  struct Received {
+    replaces_id: u32,
+    summary: String,
+    body: String,
+    image_path: Option<String>,
+  }
+
+  /// Stands in for the desktop's notification server
+  #[derive(Default)]
+  struct StubServer {
+    received: Arc<Mutex<Vec<Received>>>,
+  }
+
+  #[dbus_interface(name = "org.freedesktop.Notifications")]
+  impl StubServer {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+      &self,
+      _app_name: &str,
+      replaces_id: u32,
+      _app_icon: &str,
+      summary: &str,
+      body: &str,
+      _actions: Vec<&str>,
+      hints: HashMap<&str, OwnedValue>,
+      _expire_timeout: i32,
+    ) -> u32 {
+      let mut received = self.received.lock().unwrap();
+      received.push(Received {
+        replaces_id,
+        summary: summary.into(),
+        body: body.into(),
+        image_path: hints.get("image-path").and_then(|value| String::try_from(value.clone()).ok()),
+      });
+
+      received.len() as u32
+    }
+  }
+
+  #[tokio::test]
+  async fn sends_notifications_to_the_server() {
+    let received = Arc::new(Mutex::new(vec![]));
+    let guid = Guid::generate();
+    let (server, client) = UnixStream::pair().unwrap();
+    let stub = StubServer {
+      received: received.clone(),
+    };
+
+    let (server, client) = tokio::join!(
+      async {
+        ConnectionBuilder::unix_stream(server)
+          .server(&guid)
+          .p2p()
+          .serve_at("/org/freedesktop/Notifications", stub)?
+          .build()
+          .await
+      },
+      ConnectionBuilder::unix_stream(client).p2p().build(),
+    );
+    let (_server, client) = (server.unwrap(), client.unwrap());
+
+    let cover_path = std::env::temp_dir().join(format!("media-widget-cover-{}.png", std::process::id()));
+    let notifier = FreedesktopNotifier::with_connection(client, cover_path.clone()).await.unwrap();
+
+    let mut notification = TrackNotification {
+      title: "Song".into(),
+      artists: vec!["A".into(), "B".into()],
+      album: "Album".into(),
+      cover_png: Some(vec![0x89, b'P', b'N', b'G']),
+    };
+    notifier.show(&notification).await.unwrap();
+
+    notification.cover_png = None;
+    notifier.show(&notification).await.unwrap();
+
+    assert_eq!(*received.lock().unwrap(), vec![
+      Received {
+        replaces_id: 0,
+        summary: "Song".into(),
+        body: "A, B".into(),
+        image_path: Some(format!("file://{}", cover_path.display())),
+      },
+      Received {
+        replaces_id: 1,
+        summary: "Song".into(),
+        body: "A, B".into(),
+        image_path: None,
+      },
+    ]);
+    assert_eq!(std::fs::read(&cover_path).unwrap(), b"\x89PNG");
+
+    std::fs::remove_file(&cover_path).unwrap();
+  }
+}
@@ -0,0 +1,6 @@
+#[cfg(target_os = "linux")]
+pub mod freedesktop;
+pub mod notifier;
+#[cfg(windows)]
+pub mod toast;
+pub mod track;
\ No newline at end of file
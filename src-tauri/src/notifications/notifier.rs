@@ -0,0 +1,127 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use super::track::{TrackNotification, TrackNotifications};
+use crate::media::lib::EventBus;
+use crate::settings::schema::NotificationSettings;
+use crate::utils::time::now_ms;
+
+#[async_trait]
+pub trait Notifier: Send + Sync {
+  async fn show(&self, notification: &TrackNotification) -> anyhow::Result<()>;
+}
+
+/// The platform's notification service, `cache_dir` holds the cover art for
+/// services that only take files
+#[cfg(target_os = "linux")]
+pub async fn system_notifier(cache_dir: &Path, _app_id: &str) -> anyhow::Result<Arc<dyn Notifier>> {
+  let notifier = super::freedesktop::FreedesktopNotifier::connect(cache_dir.join("notification-cover.png")).await?;
+  Ok(Arc::new(notifier))
+}
+
+#[cfg(windows)]
+pub async fn system_notifier(cache_dir: &Path, app_id: &str) -> anyhow::Result<Arc<dyn Notifier>> {
+  Ok(Arc::new(super::toast::ToastNotifier::new(app_id, cache_dir.join("notification-cover.png"))))
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub async fn system_notifier(_cache_dir: &Path, _app_id: &str) -> anyhow::Result<Arc<dyn Notifier>> {
+  anyhow::bail!("Track notifications aren't supported on this platform")
+}
+
+/// Notifies about track changes once their properties settle, unless the
+/// widget is `focused`
+pub fn spawn(
+  event_bus: Arc<EventBus>,
+  notifier: Arc<dyn Notifier>,
+  settings: NotificationSettings,
+  focused: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+  println!("[TrackNotifier] spawn");
+
+  let mut events = event_bus.0.subscribe();
+
+  tokio::spawn(async move {
+    let mut notifications = TrackNotifications::new(settings);
+
+    loop {
+      let due_in = notifications
+        .due_at()
+        .map(|due_at| Duration::from_millis((due_at - now_ms()).max(0) as u64));
+
+      tokio::select! {
+        event = events.recv() => match event {
+          Ok(event) => notifications.handle(&event, now_ms()),
+          Err(RecvError::Lagged(_)) => continue,
+          Err(RecvError::Closed) => break,
+        },
+        _ = tokio::time::sleep(due_in.unwrap_or_default()), if due_in.is_some() => {
+          let Some(notification) = notifications.take_due(now_ms(), focused.load(Ordering::Relaxed)) else {
+            continue;
+          };
+
+          if let Err(err) = notifier.show(&notification).await {
+            println!("[TrackNotifier] Error: {:?}", err);
+          }
+        },
+      }
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Mutex;
+
+  use tokio::sync::broadcast;
+
+  use super::*;
+  use crate::media::lib::{MediaEvent, MediaSessionData};
+
+  #[derive(Default)]
+  struct Recording(Mutex<Vec<String>>);
+
+  #[async_trait]
+  impl Notifier for Recording {
+    async fn show(&self, notification: &TrackNotification) -> anyhow::Result<()> {
+      self.0.lock().unwrap().push(notification.title.clone());
+      Ok(())
+    }
+  }
+
+  #[tokio::test]
+  async fn notifies_once_the_track_settles() {
+    let event_bus = Arc::new(broadcast::channel(16));
+    let notifier = Arc::new(Recording::default());
+    let focused = Arc::new(AtomicBool::new(false));
+    let settings = NotificationSettings {
+      enabled: true,
+      debounce_ms: 50,
+      ..Default::default()
+    };
+    spawn(event_bus.clone(), notifier.clone(), settings, focused.clone());
+
+    for title in ["First", "Second"] {
+      event_bus
+        .0
+        .send(MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture(title, &["A"])))
+        .unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    focused.store(true, Ordering::Relaxed);
+    event_bus
+      .0
+      .send(MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Third", &["A"])))
+      .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    assert_eq!(*notifier.0.lock().unwrap(), vec!["Second"]);
+  }
+}
@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use windows::core::HSTRING;
+use windows::Data::Xml::Dom::XmlDocument;
+use windows::UI::Notifications::{ToastNotification, ToastNotificationManager};
+
+use super::notifier::Notifier;
+use super::track::TrackNotification;
+
+/// Replacing by tag keeps one track notification in the action center
+const TAG: &str = "track";
+
+pub struct ToastNotifier {
+  app_id: HSTRING,
+  /// Toasts take images by path, the latest cover is written here
+  cover_path: PathBuf,
+}
+
+impl ToastNotifier {
+  pub fn new(app_id: &str, cover_path: PathBuf) -> Self {
+    Self {
+      app_id: HSTRING::from(app_id),
+      cover_path,
+    }
+  }
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+#[async_trait]
+impl Notifier for ToastNotifier {
+  async fn show(&self, notification: &TrackNotification) -> anyhow::Result<()> {
+    let image = match &notification.cover_png {
+      Some(png) => {
+        tokio::fs::write(&self.cover_path, png).await?;
+        format!(
+          r#"<image placement="appLogoOverride" src="{}"/>"#,
+          escape(&self.cover_path.to_string_lossy())
+        )
+      },
+      None => String::new(),
+    };
+
+    let xml = XmlDocument::new()?;
+    xml.LoadXml(&HSTRING::from(format!(
+      r#"<toast><visual><binding template="ToastGeneric">{}<text>{}</text><text>{}</text></binding></visual><audio silent="true"/></toast>"#,
+      image,
+      escape(&notification.title),
+      escape(&notification.body()),
+    )))?;
+
+    let toast = ToastNotification::CreateToastNotification(&xml)?;
+    toast.SetTag(&HSTRING::from(TAG))?;
+    ToastNotificationManager::CreateToastNotifierWithId(&self.app_id)?.Show(&toast)?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,155 @@
+use crate::media::lib::{MediaEvent, MediaSessionData};
+use crate::settings::schema::NotificationSettings;
+
+fn enabled_for(settings: &NotificationSettings, app_id: Option<&str>) -> bool {
+  app_id
+    .and_then(|app_id| settings.apps.get(app_id))
+    .copied()
+    .unwrap_or(settings.enabled)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackNotification {
+  pub title: String,
+  pub artists: Vec<String>,
+  pub album: String,
+  /// The cropped cover art as PNG
+  pub cover_png: Option<Vec<u8>>,
+}
+
+impl TrackNotification {
+  pub fn body(&self) -> String { self.artists.join(", ") }
+
+  fn key(&self) -> (&str, &[String], &str) { (&self.title, &self.artists, &self.album) }
+}
+
+impl From<&MediaSessionData> for TrackNotification {
+  fn from(data: &MediaSessionData) -> Self {
+    Self {
+      title: data.title.clone(),
+      artists: data.artists.clone(),
+      album: data.album.clone(),
+      cover_png: base64::decode(&data.thumbnail.base64)
+        .ok()
+        .filter(|png| !png.is_empty()),
+    }
+  }
+}
+
+/// Decides when a track change is worth a notification. Properties arrive
+/// several times per track (artwork loads later, skipping through a
+/// playlist), so a notification is only due once they settle, and never
+/// twice for the same track. Time is unix ms.
+#[derive(Debug, Default)]
+pub struct TrackNotifications {
+  settings: NotificationSettings,
+  app_id: Option<String>,
+  pending: Option<(TrackNotification, i64)>,
+  last: Option<TrackNotification>,
+}
+
+impl TrackNotifications {
+  pub fn new(settings: NotificationSettings) -> Self {
+    Self {
+      settings,
+      ..Default::default()
+    }
+  }
+
+  pub fn handle(&mut self, event: &MediaEvent, now: i64) {
+    match event {
+      MediaEvent::Connect(app_id) => self.app_id = Some(app_id.clone()),
+      MediaEvent::Disconnect(_) => {
+        self.app_id = None;
+        self.pending = None;
+        self.last = None;
+      },
+      MediaEvent::MediaPropertiesChanged(data) => {
+        let notification = TrackNotification::from(data);
+        let is_last = self.last.as_ref().is_some_and(|last| last.key() == notification.key());
+
+        self.pending = (!is_last && !data.title.is_empty() && enabled_for(&self.settings, self.app_id.as_deref()))
+          .then(|| (notification, now + i64::from(self.settings.debounce_ms)));
+      },
+      _ => {},
+    }
+  }
+
+  /// When the pending notification is due
+  pub fn due_at(&self) -> Option<i64> { self.pending.as_ref().map(|(_, due_at)| *due_at) }
+
+  /// The notification to show, unless the widget is focused and already
+  /// shows the track
+  pub fn take_due(&mut self, now: i64, focused: bool) -> Option<TrackNotification> {
+    if self.due_at()? > now {
+      return None;
+    }
+
+    let (notification, _) = self.pending.take()?;
+    self.last = Some(notification.clone());
+
+    (!focused).then_some(notification)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn enabled() -> NotificationSettings {
+    NotificationSettings {
+      enabled: true,
+      ..Default::default()
+    }
+  }
+
+  fn properties(title: &str) -> MediaEvent { MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture(title, &["A", "B"])) }
+
+  #[test]
+  fn debounces_property_changes() {
+    let mut notifications = TrackNotifications::new(enabled());
+    notifications.handle(&MediaEvent::Connect("Spotify.exe".into()), 0);
+
+    notifications.handle(&properties("First"), 0);
+    notifications.handle(&properties("Second"), 1_000);
+    assert_eq!(notifications.take_due(2_000, false), None);
+
+    let shown = notifications.take_due(2_500, false).unwrap();
+    assert_eq!((shown.title.as_str(), shown.body()), ("Second", "A, B".into()));
+    assert_eq!(notifications.due_at(), None);
+
+    // The artwork finished loading
+    notifications.handle(&properties("Second"), 3_000);
+    assert_eq!(notifications.due_at(), None);
+
+    notifications.handle(&properties("Third"), 4_000);
+    assert_eq!(notifications.due_at(), Some(5_500));
+  }
+
+  #[test]
+  fn stays_quiet_while_focused() {
+    let mut notifications = TrackNotifications::new(enabled());
+
+    notifications.handle(&properties("First"), 0);
+    assert_eq!(notifications.take_due(2_000, true), None);
+
+    // Already seen in the widget
+    notifications.handle(&properties("First"), 3_000);
+    assert_eq!(notifications.take_due(10_000, false), None);
+  }
+
+  #[test]
+  fn respects_per_app_settings() {
+    let settings: NotificationSettings = serde_json::from_str(r#"{"enabled": false, "apps": {"Spotify.exe": true}}"#).unwrap();
+    let mut notifications = TrackNotifications::new(settings);
+
+    notifications.handle(&MediaEvent::Connect("chrome.exe".into()), 0);
+    notifications.handle(&properties("Video"), 0);
+    assert_eq!(notifications.due_at(), None);
+
+    notifications.handle(&MediaEvent::Disconnect("chrome.exe".into()), 0);
+    notifications.handle(&MediaEvent::Connect("Spotify.exe".into()), 0);
+    notifications.handle(&properties("Song"), 0);
+    assert_eq!(notifications.due_at(), Some(1_500));
+  }
+}
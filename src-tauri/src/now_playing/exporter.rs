@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use super::template::{Template, Value};
+use crate::media::lib::{EventBus, MediaEvent, MediaSessionData};
+use crate::settings::schema::NowPlayingSettings;
+use crate::utils::fs::write_atomic;
+
+/// Everything a template can refer to
+pub const VARIABLES: &[&str] = &["title", "baseTitle", "artist", "artists", "album", "app"];
+
+/// Writes the current track to files for streaming overlays
+#[derive(Debug)]
+pub struct NowPlayingExporter {
+  directory: PathBuf,
+  templates: Vec<(String, Template)>,
+  cover_art: Option<String>,
+}
+
+impl NowPlayingExporter {
+  /// Parses and validates every template up front
+  pub fn new(settings: NowPlayingSettings) -> anyhow::Result<Self> {
+    let mut templates = vec![];
+
+    for (name, source) in settings.templates {
+      check_file_name(&name)?;
+
+      let template = Template::parse(&source).with_context(|| format!("Invalid template for '{name}'"))?;
+      if let Some(unknown) = template.variables().find(|variable| !VARIABLES.contains(variable)) {
+        bail!("Unknown variable '{unknown}' in template for '{name}', expected one of {VARIABLES:?}");
+      }
+
+      templates.push((name, template));
+    }
+
+    if let Some(name) = &settings.cover_art {
+      check_file_name(name)?;
+    }
+
+    std::fs::create_dir_all(&settings.directory)?;
+
+    Ok(Self {
+      directory: settings.directory.into(),
+      templates,
+      cover_art: settings.cover_art,
+    })
+  }
+
+  pub fn export(&self, app: &str, data: &MediaSessionData) -> anyhow::Result<()> {
+    let variables = HashMap::from([
+      ("title", Value::from(data.title.as_str())),
+      ("baseTitle", Value::from(data.normalized_title.base_title.as_str())),
+      ("artist", Value::from(data.artists.first().map(String::as_str).unwrap_or_default())),
+      ("artists", Value::from(data.artists.as_slice())),
+      ("album", Value::from(data.album.as_str())),
+      ("app", Value::from(app)),
+    ]);
+
+    for (name, template) in &self.templates {
+      write_atomic(&self.directory.join(name), template.render(&variables).as_bytes())?;
+    }
+
+    if let Some(name) = &self.cover_art {
+      let path = self.directory.join(name);
+
+      match base64::decode(&data.thumbnail.base64) {
+        Ok(png) if !png.is_empty() => write_atomic(&path, &png)?,
+        _ => remove_if_exists(&path)?,
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Empties the text files and removes the cover art
+  pub fn clear(&self) -> anyhow::Result<()> {
+    for (name, _) in &self.templates {
+      write_atomic(&self.directory.join(name), b"")?;
+    }
+
+    if let Some(name) = &self.cover_art {
+      remove_if_exists(&self.directory.join(name))?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Only plain file names, templates must not write outside the directory
+fn check_file_name(name: &str) -> anyhow::Result<()> {
+  let path = Path::new(name);
+  if path.file_name().and_then(|file_name| file_name.to_str()) != Some(name) || name.starts_with('.') {
+    bail!("'{name}' is not a plain file name");
+  }
+
+  Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> anyhow::Result<()> {
+  match std::fs::remove_file(path) {
+    Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+    _ => Ok(()),
+  }
+}
+
+pub fn spawn(event_bus: Arc<EventBus>, exporter: Option<NowPlayingExporter>) -> Option<JoinHandle<()>> {
+  let Some(exporter) = exporter else {
+    println!("[NowPlayingExporter] not configured");
+    return None;
+  };
+
+  println!("[NowPlayingExporter] spawn");
+
+  let mut events = event_bus.0.subscribe();
+
+  Some(tokio::spawn(async move {
+    let mut app = String::new();
+
+    loop {
+      let result = match events.recv().await {
+        Ok(MediaEvent::Connect(id)) => {
+          app = id;
+          Ok(())
+        },
+        Ok(MediaEvent::MediaPropertiesChanged(data)) => exporter.export(&app, &data),
+        Ok(MediaEvent::Disconnect(_)) => exporter.clear(),
+        Ok(_) | Err(RecvError::Lagged(_)) => Ok(()),
+        Err(RecvError::Closed) => break,
+      };
+
+      if let Err(err) = result {
+        println!("[NowPlayingExporter] Error: {:?}", err);
+      }
+    }
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("media-widget-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+  }
+
+  fn settings(directory: &Path, templates: &[(&str, &str)]) -> NowPlayingSettings {
+    NowPlayingSettings {
+      directory: directory.to_string_lossy().into(),
+      templates: templates
+        .iter()
+        .map(|(name, source)| (name.to_string(), source.to_string()))
+        .collect(),
+      ..Default::default()
+    }
+  }
+
+  fn files(directory: &Path) -> Vec<String> {
+    let mut files = std::fs::read_dir(directory)
+      .unwrap()
+      .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+      .collect::<Vec<_>>();
+    files.sort();
+    files
+  }
+
+  #[test]
+  fn writes_templates_and_cover_art() {
+    let dir = temp_dir("now-playing");
+    let exporter = NowPlayingExporter::new(settings(&dir, &[
+      ("title.txt", "{{title}} — {{artists|join(\", \")}}"),
+      ("album.txt", "{{album|upper}} ({{app}})"),
+    ]))
+    .unwrap();
+
+    let mut data = MediaSessionData::fixture("Song (Live)", &["A", "B"]);
+    data.thumbnail.base64 = base64::encode(b"png");
+    exporter.export("Spotify.exe", &data).unwrap();
+
+    assert_eq!(files(&dir), vec!["album.txt", "cover.png", "title.txt"]);
+    assert_eq!(std::fs::read_to_string(dir.join("title.txt")).unwrap(), "Song (Live) — A, B");
+    assert_eq!(std::fs::read_to_string(dir.join("album.txt")).unwrap(), "ALBUM (Spotify.exe)");
+    assert_eq!(std::fs::read(dir.join("cover.png")).unwrap(), b"png");
+
+    exporter.clear().unwrap();
+    assert_eq!(files(&dir), vec!["album.txt", "title.txt"]);
+    assert_eq!(std::fs::read_to_string(dir.join("title.txt")).unwrap(), "");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn removes_stale_cover_art_without_a_thumbnail() {
+    let dir = temp_dir("now-playing-cover");
+    let exporter = NowPlayingExporter::new(settings(&dir, &[])).unwrap();
+    std::fs::write(dir.join("cover.png"), b"old").unwrap();
+
+    let mut data = MediaSessionData::fixture("Song", &["A"]);
+    data.thumbnail.base64 = String::new();
+    exporter.export("", &data).unwrap();
+
+    assert!(files(&dir).is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn rejects_invalid_configs() {
+    let dir = temp_dir("now-playing-invalid");
+
+    for templates in [
+      [("a.txt", "{{title")],
+      [("a.txt", "{{lyrics}}")],
+      [("../a.txt", "{{title}}")],
+      [("nested/a.txt", "{{title}}")],
+      [(".hidden", "{{title}}")],
+    ] {
+      assert!(NowPlayingExporter::new(settings(&dir, &templates)).is_err(), "{templates:?}");
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+  }
+}
@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// A value a template can refer to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+  Text(String),
+  List(Vec<String>),
+}
+
+impl Value {
+  fn is_empty(&self) -> bool {
+    match self {
+      Value::Text(text) => text.is_empty(),
+      Value::List(items) => items.is_empty(),
+    }
+  }
+
+  fn into_text(self) -> String {
+    match self {
+      Value::Text(text) => text,
+      Value::List(items) => items.join(", "),
+    }
+  }
+}
+
+impl From<&str> for Value {
+  fn from(text: &str) -> Self { Value::Text(text.into()) }
+}
+
+impl From<&[String]> for Value {
+  fn from(items: &[String]) -> Self { Value::List(items.to_vec()) }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+  /// Byte offset into the template source
+  pub offset: usize,
+  pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} at offset {}", self.message, self.offset)
+  }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+  Join(String),
+  First,
+  Upper,
+  Lower,
+  Default(String),
+  Truncate(usize),
+}
+
+impl Filter {
+  fn apply(&self, value: Value) -> Value {
+    match (self, value) {
+      (Filter::Join(separator), Value::List(items)) => Value::Text(items.join(separator)),
+      (Filter::First, Value::List(items)) => Value::Text(items.into_iter().next().unwrap_or_default()),
+      (Filter::Upper, value) => Value::Text(value.into_text().to_uppercase()),
+      (Filter::Lower, value) => Value::Text(value.into_text().to_lowercase()),
+      (Filter::Default(fallback), value) if value.is_empty() => Value::Text(fallback.clone()),
+      (Filter::Truncate(max), value) => {
+        let text = value.into_text();
+        if text.chars().count() <= *max {
+          return Value::Text(text);
+        }
+
+        let mut truncated = text.chars().take(max.saturating_sub(1)).collect::<String>();
+        truncated.push('…');
+        Value::Text(truncated)
+      },
+      (_, value) => value,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+  Literal(String),
+  Variable { name: String, filters: Vec<Filter> },
+}
+
+/// A parsed template like `{{title}} — {{artists|join(", ")}}`.
+///
+/// `{{ ... }}` holds a variable or a string literal (`{{"{{"}}` prints `{{`),
+/// followed by any number of `| filter` or `| filter(args)`. Lists render
+/// joined with ", " unless a filter says otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+  parts: Vec<Part>,
+}
+
+impl Template {
+  pub fn parse(source: &str) -> Result<Self, TemplateError> { Parser::new(source).parse() }
+
+  /// Every variable the template refers to, to validate them up front
+  pub fn variables(&self) -> impl Iterator<Item = &str> {
+    self.parts.iter().filter_map(|part| match part {
+      Part::Variable { name, .. } => Some(name.as_str()),
+      Part::Literal(_) => None,
+    })
+  }
+
+  /// Unknown variables render as empty
+  pub fn render(&self, variables: &HashMap<&str, Value>) -> String {
+    let mut output = String::new();
+
+    for part in &self.parts {
+      match part {
+        Part::Literal(text) => output.push_str(text),
+        Part::Variable { name, filters } => {
+          let value = variables.get(name.as_str()).cloned().unwrap_or(Value::Text(String::new()));
+          output.push_str(&filters.iter().fold(value, |value, filter| filter.apply(value)).into_text());
+        },
+      }
+    }
+
+    output
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+  Ident(String),
+  Text(String),
+  Number(usize),
+  Pipe,
+  Open,
+  Close,
+  Comma,
+  End,
+}
+
+struct Parser<'a> {
+  source: &'a str,
+  offset: usize,
+  /// Where the last token started, for error messages
+  token_start: usize,
+}
+
+impl<'a> Parser<'a> {
+  fn new(source: &'a str) -> Self {
+    Self {
+      source,
+      offset: 0,
+      token_start: 0,
+    }
+  }
+
+  fn error<T>(&self, offset: usize, message: impl Into<String>) -> Result<T, TemplateError> {
+    Err(TemplateError {
+      offset,
+      message: message.into(),
+    })
+  }
+
+  fn rest(&self) -> &'a str { &self.source[self.offset..] }
+
+  fn parse(mut self) -> Result<Template, TemplateError> {
+    let mut parts = vec![];
+
+    while !self.rest().is_empty() {
+      let Some(start) = self.rest().find("{{") else {
+        parts.push(Part::Literal(self.rest().into()));
+        break;
+      };
+
+      if start > 0 {
+        parts.push(Part::Literal(self.rest()[..start].into()));
+      }
+
+      self.offset += start + 2;
+      parts.push(self.expression(self.offset - 2)?);
+    }
+
+    // Merge adjacent literals, e.g. around `{{"{{"}}`
+    let mut merged: Vec<Part> = vec![];
+    for part in parts {
+      match (merged.last_mut(), part) {
+        (Some(Part::Literal(previous)), Part::Literal(text)) => previous.push_str(&text),
+        (_, part) => merged.push(part),
+      }
+    }
+
+    Ok(Template { parts: merged })
+  }
+
+  fn expression(&mut self, open: usize) -> Result<Part, TemplateError> {
+    let part = match self.next(open)? {
+      Token::Ident(name) => Part::Variable {
+        name,
+        filters: vec![],
+      },
+      Token::Text(text) => Part::Literal(text),
+      _ => return self.error(self.token_start, "Expected a variable or a string"),
+    };
+    let start = self.token_start;
+
+    let Part::Variable { name, mut filters } = part else {
+      return match self.next(open)? {
+        Token::End => Ok(part),
+        _ => self.error(start, "String literals can not be filtered"),
+      };
+    };
+
+    loop {
+      match self.next(open)? {
+        Token::End => break,
+        Token::Pipe => filters.push(self.filter(open)?),
+        _ => return self.error(self.token_start, "Expected `|` or `}}`"),
+      }
+    }
+
+    Ok(Part::Variable { name, filters })
+  }
+
+  fn filter(&mut self, open: usize) -> Result<Filter, TemplateError> {
+    let Token::Ident(name) = self.next(open)? else {
+      return self.error(self.token_start, "Expected a filter name");
+    };
+    let start = self.token_start;
+
+    let mut args = vec![];
+    let checkpoint = self.offset;
+    if self.next(open)? == Token::Open {
+      loop {
+        match self.next(open)? {
+          Token::Close if args.is_empty() => break,
+          token @ (Token::Text(_) | Token::Number(_)) => args.push(token),
+          _ => return self.error(self.token_start, "Expected a string or a number"),
+        }
+
+        match self.next(open)? {
+          Token::Comma => continue,
+          Token::Close => break,
+          _ => return self.error(self.token_start, "Expected `,` or `)`"),
+        }
+      }
+    } else {
+      self.offset = checkpoint;
+    }
+
+    match (name.as_str(), args.as_slice()) {
+      ("join", [Token::Text(separator)]) => Ok(Filter::Join(separator.clone())),
+      ("first", []) => Ok(Filter::First),
+      ("upper", []) => Ok(Filter::Upper),
+      ("lower", []) => Ok(Filter::Lower),
+      ("default", [Token::Text(fallback)]) => Ok(Filter::Default(fallback.clone())),
+      ("truncate", [Token::Number(max)]) => Ok(Filter::Truncate(*max)),
+      ("join" | "first" | "upper" | "lower" | "default" | "truncate", _) => {
+        self.error(start, format!("Wrong arguments for filter `{name}`"))
+      },
+      _ => self.error(start, format!("Unknown filter `{name}`")),
+    }
+  }
+
+  /// Next token inside `{{ }}`, `open` is where the tag started
+  fn next(&mut self, open: usize) -> Result<Token, TemplateError> {
+    let trimmed = self.rest().trim_start();
+    self.offset = self.source.len() - trimmed.len();
+    self.token_start = self.offset;
+
+    let mut chars = trimmed.chars();
+    let Some(first) = chars.next() else {
+      return self.error(open, "Unclosed `{{`");
+    };
+
+    let token = match first {
+      '}' if trimmed.starts_with("}}") => {
+        self.offset += 2;
+        return Ok(Token::End);
+      },
+      '|' => Token::Pipe,
+      '(' => Token::Open,
+      ')' => Token::Close,
+      ',' => Token::Comma,
+      '"' => return self.string(),
+      c if c.is_ascii_digit() => {
+        let digits = trimmed.chars().take_while(char::is_ascii_digit).collect::<String>();
+        self.offset += digits.len();
+        return match digits.parse() {
+          Ok(number) => Ok(Token::Number(number)),
+          Err(_) => self.error(self.token_start, "Number is too large"),
+        };
+      },
+      c if c.is_alphabetic() || c == '_' => {
+        let ident = trimmed
+          .chars()
+          .take_while(|c| c.is_alphanumeric() || *c == '_')
+          .collect::<String>();
+        self.offset += ident.len();
+        return Ok(Token::Ident(ident));
+      },
+      c => return self.error(self.token_start, format!("Unexpected `{c}`")),
+    };
+
+    self.offset += first.len_utf8();
+    Ok(token)
+  }
+
+  /// A double quoted string, with `\"` and `\\` escapes
+  fn string(&mut self) -> Result<Token, TemplateError> {
+    let start = self.offset;
+    let mut text = String::new();
+    let mut chars = self.rest().char_indices().skip(1);
+
+    while let Some((index, c)) = chars.next() {
+      match c {
+        '"' => {
+          self.offset += index + 1;
+          return Ok(Token::Text(text));
+        },
+        '\\' => match chars.next() {
+          Some((_, 'n')) => text.push('\n'),
+          Some((_, escaped)) => text.push(escaped),
+          None => break,
+        },
+        c => text.push(c),
+      }
+    }
+
+    self.error(start, "Unterminated string")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn variables() -> HashMap<&'static str, Value> {
+    HashMap::from([
+      ("title", Value::from("Song")),
+      ("album", Value::from("")),
+      ("artists", Value::List(vec!["A".into(), "B".into(), "C".into()])),
+    ])
+  }
+
+  fn render(source: &str) -> String { Template::parse(source).unwrap().render(&variables()) }
+
+  fn error(source: &str) -> TemplateError { Template::parse(source).unwrap_err() }
+
+  #[test]
+  fn renders_variables() {
+    let cases = [
+      ("", ""),
+      ("plain text", "plain text"),
+      ("{{title}}", "Song"),
+      ("{{ title }}!", "Song!"),
+      ("{{title}} — {{artists|join(\", \")}}", "Song — A, B, C"),
+      ("{{artists}}", "A, B, C"),
+      ("{{ artists | join(\" & \") }}", "A & B & C"),
+      ("{{artists|first}}", "A"),
+      ("{{title|upper}} {{title|lower}}", "SONG song"),
+      ("{{album|default(\"Single\")}}", "Single"),
+      ("{{title|default(\"Unknown\")}}", "Song"),
+      ("{{missing}}|", "|"),
+      ("{{artists|join(\"\")|lower}}", "abc"),
+      ("{{\"{{\"}}title}}", "{{title}}"),
+      ("{{\"say \\\"hi\\\"\"}}", "say \"hi\""),
+      ("a}}b{", "a}}b{"),
+      ("{{title}}\n{{artists|first}}", "Song\nA"),
+    ];
+
+    for (source, expected) in cases {
+      assert_eq!(render(source), expected, "{source}");
+    }
+  }
+
+  #[test]
+  fn truncates_with_an_ellipsis() {
+    assert_eq!(render("{{artists|truncate(7)}}"), "A, B, C");
+    assert_eq!(render("{{artists|truncate(5)}}"), "A, B…");
+    assert_eq!(render("{{title|truncate(0)}}"), "…");
+  }
+
+  #[test]
+  fn handles_unicode() {
+    let variables = HashMap::from([("title", Value::from("Ünïcødé 曲"))]);
+    let template = Template::parse("«{{ title | truncate(4) }}»").unwrap();
+
+    assert_eq!(template.render(&variables), "«Ünï…»");
+  }
+
+  #[test]
+  fn lists_variables() {
+    let template = Template::parse("{{title}} {{\"x\"}} {{artists|first}}").unwrap();
+
+    assert_eq!(template.variables().collect::<Vec<_>>(), vec!["title", "artists"]);
+  }
+
+  #[test]
+  fn reports_errors_with_offsets() {
+    let cases = [
+      ("{{title", 0, "Unclosed `{{`"),
+      ("ab {{ title | ", 3, "Unclosed `{{`"),
+      ("{{}}", 2, "Expected a variable or a string"),
+      ("{{title|shout}}", 8, "Unknown filter `shout`"),
+      ("{{title|join}}", 8, "Wrong arguments for filter `join`"),
+      ("{{title|truncate(\"5\")}}", 8, "Wrong arguments for filter `truncate`"),
+      ("{{title|join(\", \"}}", 17, "Expected `,` or `)`"),
+      ("{{title title}}", 8, "Expected `|` or `}}`"),
+      ("{{\"x\"|upper}}", 2, "String literals can not be filtered"),
+      ("{{\"x}}", 2, "Unterminated string"),
+      ("{{ti-tle}}", 4, "Unexpected `-`"),
+    ];
+
+    for (source, offset, message) in cases {
+      assert_eq!(error(source), TemplateError {
+        offset,
+        message: message.into(),
+      }, "{source}");
+    }
+  }
+}
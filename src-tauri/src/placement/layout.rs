@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+
+use crate::settings::schema::SnapMode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Point {
+  pub x: i32,
+  pub y: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Size {
+  pub width: i32,
+  pub height: i32,
+}
+
+/// A monitor's bounds, in physical pixels on the virtual desktop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+  pub x: i32,
+  pub y: i32,
+  pub width: i32,
+  pub height: i32,
+}
+
+impl Rect {
+  fn right(&self) -> i32 { self.x + self.width }
+
+  fn bottom(&self) -> i32 { self.y + self.height }
+
+  fn contains(&self, point: Point) -> bool {
+    (self.x..self.right()).contains(&point.x) && (self.y..self.bottom()).contains(&point.y)
+  }
+
+  fn overlap(&self, position: Point, size: Size) -> i64 {
+    let width = (self.right().min(position.x + size.width) - self.x.max(position.x)).max(0);
+    let height = (self.bottom().min(position.y + size.height) - self.y.max(position.y)).max(0);
+    width as i64 * height as i64
+  }
+
+  fn distance_squared(&self, point: Point) -> i64 {
+    let dx = (self.x - point.x).max(point.x - self.right()).max(0) as i64;
+    let dy = (self.y - point.y).max(point.y - self.bottom()).max(0) as i64;
+    dx * dx + dy * dy
+  }
+
+  /// Moves `position` so a window of `size` fits, or at least its top left
+  /// corner does when it's bigger than the monitor
+  fn clamp(&self, position: Point, size: Size) -> Point {
+    Point {
+      x: position.x.min(self.right() - size.width).max(self.x),
+      y: position.y.min(self.bottom() - size.height).max(self.y),
+    }
+  }
+}
+
+/// Identifies a monitor arrangement, the same monitors in the same places
+/// always give the same key whatever order they're listed in
+pub fn layout_key(monitors: &[Rect]) -> String {
+  let mut monitors = monitors.to_vec();
+  monitors.sort_by_key(|monitor| (monitor.x, monitor.y));
+
+  monitors
+    .iter()
+    .map(|monitor| format!("{}x{}@{},{}", monitor.width, monitor.height, monitor.x, monitor.y))
+    .collect::<Vec<_>>()
+    .join(";")
+}
+
+/// Where a window of `size` should go: `saved` if it's mostly on screen,
+/// otherwise pulled onto the closest monitor. Without a saved position the
+/// window is centered on the first monitor.
+pub fn visible_position(saved: Option<Point>, size: Size, monitors: &[Rect]) -> Option<Point> {
+  let first = monitors.first()?;
+
+  let Some(saved) = saved else {
+    return Some(Point {
+      x: first.x + (first.width - size.width) / 2,
+      y: first.y + (first.height - size.height) / 2,
+    });
+  };
+
+  let visible = monitors.iter().map(|monitor| monitor.overlap(saved, size)).sum::<i64>();
+  let area = size.width as i64 * size.height as i64;
+  if visible * 2 >= area {
+    return Some(saved);
+  }
+
+  let center = center(saved, size);
+  let closest = monitors
+    .iter()
+    .min_by_key(|monitor| monitor.distance_squared(center))
+    .unwrap_or(first);
+
+  Some(closest.clamp(saved, size))
+}
+
+/// Pulls the window against the edges of the monitor it's on when it's
+/// within `distance` of them. With `SnapMode::Corners` it only snaps when
+/// it's close to both edges of a corner.
+pub fn snap(position: Point, size: Size, monitors: &[Rect], mode: SnapMode, distance: i32) -> Point {
+  if mode == SnapMode::Off {
+    return position;
+  }
+
+  let Some(monitor) = monitor_at(center(position, size), monitors) else {
+    return position;
+  };
+
+  let snap_axis = |start: i32, length: i32, low: i32, high: i32| {
+    if (start - low).abs() <= distance {
+      Some(low)
+    } else if (start + length - high).abs() <= distance {
+      Some(high - length)
+    } else {
+      None
+    }
+  };
+
+  let x = snap_axis(position.x, size.width, monitor.x, monitor.right());
+  let y = snap_axis(position.y, size.height, monitor.y, monitor.bottom());
+
+  match (mode, x, y) {
+    (SnapMode::Corners, Some(x), Some(y)) => Point { x, y },
+    (SnapMode::Corners, ..) => position,
+    (_, x, y) => Point {
+      x: x.unwrap_or(position.x),
+      y: y.unwrap_or(position.y),
+    },
+  }
+}
+
+/// Where a window at `position` goes when it's resized from `from` to `to`,
+/// keeping the corner that's closest to a corner of its monitor in place
+pub fn anchored_resize(position: Point, from: Size, to: Size, monitors: &[Rect]) -> Point {
+  let center = center(position, from);
+  let Some(monitor) = monitor_at(center, monitors) else {
+    return position;
+  };
+
+  let anchored = Point {
+    x: if center.x > monitor.x + monitor.width / 2 {
+      position.x + from.width - to.width
+    } else {
+      position.x
+    },
+    y: if center.y > monitor.y + monitor.height / 2 {
+      position.y + from.height - to.height
+    } else {
+      position.y
+    },
+  };
+
+  monitor.clamp(anchored, to)
+}
+
+fn center(position: Point, size: Size) -> Point {
+  Point {
+    x: position.x + size.width / 2,
+    y: position.y + size.height / 2,
+  }
+}
+
+/// The monitor `point` is on, or the closest one when it's on none
+fn monitor_at(point: Point, monitors: &[Rect]) -> Option<&Rect> {
+  monitors
+    .iter()
+    .find(|monitor| monitor.contains(point))
+    .or_else(|| monitors.iter().min_by_key(|monitor| monitor.distance_squared(point)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const LAPTOP: Rect = Rect {
+    x: 0,
+    y: 0,
+    width: 1920,
+    height: 1080,
+  };
+  const EXTERNAL: Rect = Rect {
+    x: 1920,
+    y: -360,
+    width: 2560,
+    height: 1440,
+  };
+  const WIDGET: Size = Size {
+    width: 364,
+    height: 84,
+  };
+
+  fn point(x: i32, y: i32) -> Point { Point { x, y } }
+
+  #[test]
+  fn keys_layouts_by_arrangement() {
+    assert_eq!(layout_key(&[EXTERNAL, LAPTOP]), "1920x1080@0,0;2560x1440@1920,-360");
+    assert_eq!(layout_key(&[LAPTOP, EXTERNAL]), layout_key(&[EXTERNAL, LAPTOP]));
+    assert_ne!(layout_key(&[LAPTOP]), layout_key(&[LAPTOP, EXTERNAL]));
+  }
+
+  #[test]
+  fn keeps_windows_on_screen() {
+    // Docked, on the external monitor
+    assert_eq!(visible_position(Some(point(3000, 900)), WIDGET, &[LAPTOP, EXTERNAL]), Some(point(3000, 900)));
+
+    // Undocked, the external monitor is gone
+    assert_eq!(visible_position(Some(point(3000, 900)), WIDGET, &[LAPTOP]), Some(point(1556, 900)));
+
+    // Half off the bottom edge is still reachable
+    assert_eq!(visible_position(Some(point(100, 1038)), WIDGET, &[LAPTOP]), Some(point(100, 1038)));
+    assert_eq!(visible_position(Some(point(100, 1050)), WIDGET, &[LAPTOP]), Some(point(100, 996)));
+
+    assert_eq!(visible_position(None, WIDGET, &[LAPTOP, EXTERNAL]), Some(point(778, 498)));
+    assert_eq!(visible_position(None, WIDGET, &[]), None);
+  }
+
+  #[test]
+  fn snaps_to_edges_and_corners() {
+    let monitors = [LAPTOP, EXTERNAL];
+
+    assert_eq!(snap(point(10, 500), WIDGET, &monitors, SnapMode::Edges, 16), point(0, 500));
+    assert_eq!(snap(point(1550, 1000), WIDGET, &monitors, SnapMode::Edges, 16), point(1556, 996));
+    // Snaps to the edges of the monitor it's on
+    assert_eq!(snap(point(1930, -350), WIDGET, &monitors, SnapMode::Edges, 16), point(1920, -360));
+    assert_eq!(snap(point(500, 500), WIDGET, &monitors, SnapMode::Edges, 16), point(500, 500));
+
+    assert_eq!(snap(point(10, 500), WIDGET, &monitors, SnapMode::Corners, 16), point(10, 500));
+    assert_eq!(snap(point(10, 1000), WIDGET, &monitors, SnapMode::Corners, 16), point(0, 996));
+
+    assert_eq!(snap(point(10, 500), WIDGET, &monitors, SnapMode::Off, 16), point(10, 500));
+  }
+
+  #[test]
+  fn resizes_from_the_nearest_corner() {
+    let expanded = Size {
+      width: 364,
+      height: 364,
+    };
+
+    // Top left stays put
+    assert_eq!(anchored_resize(point(20, 20), WIDGET, expanded, &[LAPTOP]), point(20, 20));
+    // Bottom right grows up and to the left
+    assert_eq!(anchored_resize(point(1500, 980), WIDGET, expanded, &[LAPTOP]), point(1500, 700));
+    // And shrinks back to where it was
+    assert_eq!(anchored_resize(point(1500, 700), expanded, WIDGET, &[LAPTOP]), point(1500, 980));
+
+    let mini = Size {
+      width: 84,
+      height: 84,
+    };
+    assert_eq!(anchored_resize(point(1536, 40), WIDGET, mini, &[LAPTOP]), point(1816, 40));
+    // Kept on screen when there's no room to grow into
+    assert_eq!(anchored_resize(point(10, 1000), WIDGET, expanded, &[LAPTOP]), point(10, 716));
+  }
+}
@@ -0,0 +1,4 @@
+pub mod layout;
+pub mod mode;
+pub mod store;
+pub mod tracker;
\ No newline at end of file
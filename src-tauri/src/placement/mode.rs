@@ -0,0 +1,153 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::settings::schema::{DisplayMode, Settings, WindowSettings};
+use crate::settings::store::{SettingsStore, UpdateError};
+
+/// Logical size of the window in `mode`
+pub fn logical_size(mode: DisplayMode, window: &WindowSettings) -> (u32, u32) {
+  match mode {
+    DisplayMode::Mini => (window.height, window.height),
+    DisplayMode::Compact => (window.width, window.height),
+    DisplayMode::Expanded => (window.width, window.expanded_height),
+  }
+}
+
+/// The mode the window is shown in, `auto_expand` temporarily expands it
+/// while it's hovered
+pub fn effective_mode(window: &WindowSettings, hovered: bool) -> DisplayMode {
+  if window.auto_expand && hovered {
+    return DisplayMode::Expanded;
+  }
+
+  window.mode
+}
+
+/// Owns the display mode: the one picked is kept in the settings, the one
+/// shown also depends on whether the cursor is over the window
+pub struct ModeController {
+  settings: Arc<SettingsStore>,
+  hovered: AtomicBool,
+  mode: watch::Sender<DisplayMode>,
+}
+
+impl ModeController {
+  pub fn new(settings: Arc<SettingsStore>) -> Self {
+    let mode = effective_mode(&settings.get().window, false);
+
+    Self {
+      settings,
+      hovered: AtomicBool::new(false),
+      mode: watch::channel(mode).0,
+    }
+  }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+
+  pub fn mode(&self) -> DisplayMode { *self.mode.borrow() }
+
+  pub fn subscribe(&self) -> watch::Receiver<DisplayMode> { self.mode.subscribe() }
+
+  pub fn set_mode(&self, mode: DisplayMode) -> Result<DisplayMode, UpdateError> {
+    let mut settings = self.settings.get();
+    settings.window.mode = mode;
+    self.refresh(&self.settings.update(settings)?);
+
+    Ok(self.mode())
+  }
+
+  pub fn set_hovered(&self, hovered: bool) -> DisplayMode {
+    self.hovered.store(hovered, Ordering::Relaxed);
+    self.refresh(&self.settings.get());
+
+    self.mode()
+  }
+
+  /// Follows mode changes made through the settings file
+  pub fn track(self: &Arc<Self>) -> JoinHandle<()> {
+    let controller = self.clone();
+    let mut settings = self.settings.subscribe();
+
+    tokio::spawn(async move {
+      while settings.changed().await.is_ok() {
+        let current = settings.borrow_and_update().clone();
+        controller.refresh(&current);
+      }
+    })
+  }
+
+  fn refresh(&self, settings: &Settings) {
+    let mode = effective_mode(&settings.window, self.hovered.load(Ordering::Relaxed));
+    self.mode.send_if_modified(|current| {
+      if *current == mode {
+        return false;
+      }
+
+      *current = mode;
+      true
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::*;
+
+  fn store(name: &str) -> (Arc<SettingsStore>, std::path::PathBuf) {
+    let path = std::env::temp_dir().join(format!("media-widget-mode-{name}-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    (SettingsStore::load(path.clone()).arced(), path)
+  }
+
+  #[test]
+  fn sizes_follow_the_window_settings() {
+    let window = WindowSettings::default();
+
+    assert_eq!(logical_size(DisplayMode::Mini, &window), (84, 84));
+    assert_eq!(logical_size(DisplayMode::Compact, &window), (364, 84));
+    assert_eq!(logical_size(DisplayMode::Expanded, &window), (364, 364));
+  }
+
+  #[test]
+  fn expands_while_hovered() {
+    let (settings, path) = store("hover");
+    let controller = ModeController::new(settings.clone());
+    let mut modes = controller.subscribe();
+
+    assert_eq!(controller.set_hovered(true), DisplayMode::Compact);
+    assert!(!modes.has_changed().unwrap());
+
+    let mut updated = settings.get();
+    updated.window.auto_expand = true;
+    settings.update(updated).unwrap();
+    assert_eq!(controller.set_hovered(true), DisplayMode::Expanded);
+    assert_eq!(controller.set_hovered(false), DisplayMode::Compact);
+
+    assert_eq!(controller.set_mode(DisplayMode::Mini).unwrap(), DisplayMode::Mini);
+    assert_eq!(*modes.borrow_and_update(), DisplayMode::Mini);
+    assert_eq!(settings.get().window.mode, DisplayMode::Mini);
+
+    std::fs::remove_file(&path).unwrap();
+  }
+
+  #[tokio::test]
+  async fn follows_the_settings_file() {
+    let (settings, path) = store("track");
+    let controller = ModeController::new(settings.clone()).arced();
+    let mut modes = controller.subscribe();
+    controller.track();
+
+    std::fs::write(&path, r#"{"version": 1, "window": {"mode": "expanded"}}"#).unwrap();
+    settings.reload().unwrap();
+
+    tokio::time::timeout(Duration::from_secs(1), modes.changed()).await.unwrap().unwrap();
+    assert_eq!(*modes.borrow(), DisplayMode::Expanded);
+
+    std::fs::remove_file(&path).unwrap();
+  }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::layout::{layout_key, visible_position, Point, Rect, Size};
+use crate::utils::fs::{backup_path, read_json, write_atomic};
+
+/// The last window position on every monitor layout, so docking and
+/// undocking a laptop each bring back their own spot
+pub struct PositionStore {
+  path: PathBuf,
+  positions: Mutex<HashMap<String, Point>>,
+}
+
+impl PositionStore {
+  /// A missing file means nothing was saved yet. So does a file that can't
+  /// be used, it's kept next to it before the next save replaces it
+  pub fn load(path: PathBuf) -> Self {
+    let positions = read_json(&path).unwrap_or_else(|err| {
+      println!("[Placement] load | Error: {:?}", err);
+      if let Err(err) = std::fs::copy(&path, backup_path(&path)) {
+        println!("[Placement] backup | Error: {:?}", err);
+      }
+
+      None
+    });
+
+    Self {
+      positions: Mutex::new(positions.unwrap_or_default()),
+      path,
+    }
+  }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+
+  /// Where a window of `size` goes on the current `monitors`
+  pub fn position(&self, monitors: &[Rect], size: Size) -> Option<Point> {
+    let saved = self.positions.lock().unwrap().get(&layout_key(monitors)).copied();
+    visible_position(saved, size, monitors)
+  }
+
+  pub fn remember(&self, monitors: &[Rect], position: Point) -> anyhow::Result<()> {
+    let mut positions = self.positions.lock().unwrap();
+    if positions.insert(layout_key(monitors), position) == Some(position) {
+      return Ok(());
+    }
+
+    save(&self.path, &positions)
+  }
+}
+
+fn save(path: &Path, positions: &HashMap<String, Point>) -> anyhow::Result<()> {
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+
+  write_atomic(path, serde_json::to_string_pretty(positions)?.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn remembers_a_position_per_layout() {
+    let path = std::env::temp_dir().join(format!("media-widget-positions-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let laptop = Rect {
+      x: 0,
+      y: 0,
+      width: 1920,
+      height: 1080,
+    };
+    let external = Rect {
+      x: 1920,
+      y: 0,
+      width: 2560,
+      height: 1440,
+    };
+    let size = Size {
+      width: 364,
+      height: 84,
+    };
+
+    let store = PositionStore::load(path.clone());
+    store.remember(&[laptop], Point { x: 20, y: 980 }).unwrap();
+    store.remember(&[laptop, external], Point { x: 4000, y: 40 }).unwrap();
+
+    let store = PositionStore::load(path.clone());
+    assert_eq!(store.position(&[laptop], size), Some(Point { x: 20, y: 980 }));
+    assert_eq!(store.position(&[external, laptop], size), Some(Point { x: 4000, y: 40 }));
+    // Never seen this layout, so centered on the first monitor
+    assert_eq!(store.position(&[external], size), Some(Point { x: 3018, y: 678 }));
+
+    std::fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn starts_over_from_a_broken_file() {
+    let path = std::env::temp_dir().join(format!("media-widget-broken-positions-{}.json", std::process::id()));
+    std::fs::write(&path, "{").unwrap();
+
+    let store = PositionStore::load(path.clone());
+    let monitor = Rect {
+      x: 0,
+      y: 0,
+      width: 1920,
+      height: 1080,
+    };
+    store.remember(&[monitor], Point { x: 20, y: 980 }).unwrap();
+
+    assert_eq!(std::fs::read_to_string(backup_path(&path)).unwrap(), "{");
+    assert_eq!(PositionStore::load(path.clone()).positions.lock().unwrap().len(), 1);
+
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(backup_path(&path)).unwrap();
+  }
+}
@@ -0,0 +1,202 @@
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tauri::{PhysicalPosition, PhysicalSize, Runtime, Window};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::layout::{anchored_resize, snap, Point, Rect, Size};
+use super::mode::logical_size;
+use super::store::PositionStore;
+use crate::settings::schema::DisplayMode;
+use crate::settings::store::SettingsStore;
+use crate::utils::time::now_ms;
+
+/// How long the window has to stay put before its position is saved
+const SETTLE_DELAY: Duration = Duration::from_millis(250);
+
+/// Sizes and places the window: restores it where it was left on the
+/// current monitors, resizes it for the display mode and snaps and remembers
+/// it whenever it's dragged somewhere else
+pub struct PlacementTracker {
+  store: Arc<PositionStore>,
+  settings: Arc<SettingsStore>,
+  /// Bumped on every move, only the last one of a drag gets saved
+  moves: Arc<AtomicU64>,
+  /// Moves until then (unix ms) come from resizing for a mode, not from the
+  /// user, the anchored spot isn't saved
+  resizing_until: AtomicI64,
+}
+
+impl PlacementTracker {
+  pub fn new(store: Arc<PositionStore>, settings: Arc<SettingsStore>) -> Self {
+    Self {
+      store,
+      settings,
+      moves: Arc::new(AtomicU64::new(0)),
+      resizing_until: AtomicI64::new(0),
+    }
+  }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+
+  /// Sizes the window for `mode` and puts it back where it was left
+  pub fn restore<R: Runtime>(&self, window: &Window<R>, mode: DisplayMode) -> tauri::Result<()> {
+    let size = physical_size(window, logical_size(mode, &self.settings.get().window))?;
+    window.set_size(PhysicalSize::new(size.width, size.height))?;
+
+    let monitors = monitors(window)?;
+    if let Some(Point { x, y }) = self.store.position(&monitors, size) {
+      window.set_position(PhysicalPosition::new(x, y))?;
+    }
+
+    Ok(())
+  }
+
+  /// Resizes the window whenever the mode or the sizes change, anchored to
+  /// its nearest corner
+  pub fn follow_mode<R: Runtime>(
+    self: &Arc<Self>,
+    window: Window<R>,
+    mut modes: watch::Receiver<DisplayMode>,
+  ) -> JoinHandle<()> {
+    let tracker = self.clone();
+    let mut settings = self.settings.subscribe();
+
+    tokio::spawn(async move {
+      loop {
+        tokio::select! {
+          changed = modes.changed() => if changed.is_err() { break },
+          changed = settings.changed() => if changed.is_err() { break },
+        }
+
+        let mode = *modes.borrow_and_update();
+        let size = logical_size(mode, &settings.borrow_and_update().window);
+        if let Err(err) = tracker.resize(&window, size) {
+          println!("[Placement] resize | Error: {:?}", err);
+        }
+      }
+    })
+  }
+
+  fn resize<R: Runtime>(&self, window: &Window<R>, logical: (u32, u32)) -> anyhow::Result<()> {
+    let to = physical_size(window, logical)?;
+    let from = window.outer_size()?;
+    let from = Size {
+      width: from.width as i32,
+      height: from.height as i32,
+    };
+    if from == to {
+      return Ok(());
+    }
+
+    let position = window.outer_position()?;
+    let position = anchored_resize(Point { x: position.x, y: position.y }, from, to, &monitors(window)?);
+
+    // The moves only arrive once the event loop gets to them
+    self
+      .resizing_until
+      .store(now_ms() + SETTLE_DELAY.as_millis() as i64, Ordering::Relaxed);
+
+    // Whichever comes first, the window shouldn't reach past the screen edge
+    // in between
+    if to.width > from.width || to.height > from.height {
+      window.set_position(PhysicalPosition::new(position.x, position.y))?;
+      window.set_size(PhysicalSize::new(to.width, to.height))?;
+    } else {
+      window.set_size(PhysicalSize::new(to.width, to.height))?;
+      window.set_position(PhysicalPosition::new(position.x, position.y))?;
+    }
+
+    Ok(())
+  }
+
+  /// Snaps right away, so the window sticks to the edge while it's dragged,
+  /// and saves the position once the drag is over
+  pub fn moved<R: Runtime>(self: &Arc<Self>, window: &Window<R>) {
+    if now_ms() < self.resizing_until.load(Ordering::Relaxed) {
+      return;
+    }
+
+    if let Err(err) = self.snap(window) {
+      println!("[Placement] snap | Error: {:?}", err);
+    }
+
+    let tracker = self.clone();
+    let window = window.clone();
+    let current = self.moves.fetch_add(1, Ordering::Relaxed) + 1;
+
+    tokio::spawn(async move {
+      tokio::time::sleep(SETTLE_DELAY).await;
+      if tracker.moves.load(Ordering::Relaxed) != current {
+        return;
+      }
+
+      if let Err(err) = tracker.remember(&window) {
+        println!("[Placement] Error: {:?}", err);
+      }
+    });
+  }
+
+  /// Setting the position moves the window again, which snaps to the same
+  /// spot and stops there
+  fn snap<R: Runtime>(&self, window: &Window<R>) -> tauri::Result<()> {
+    let position = window.outer_position()?;
+    let size = window.outer_size()?;
+
+    let window_settings = self.settings.get().window;
+    let position = Point {
+      x: position.x,
+      y: position.y,
+    };
+    let snapped = snap(
+      position,
+      Size {
+        width: size.width as i32,
+        height: size.height as i32,
+      },
+      &monitors(window)?,
+      window_settings.snap,
+      window_settings.snap_distance as i32,
+    );
+
+    if snapped != position {
+      window.set_position(PhysicalPosition::new(snapped.x, snapped.y))?;
+    }
+
+    Ok(())
+  }
+
+  fn remember<R: Runtime>(&self, window: &Window<R>) -> anyhow::Result<()> {
+    let position = window.outer_position()?;
+    self.store.remember(&monitors(window)?, Point {
+      x: position.x,
+      y: position.y,
+    })
+  }
+}
+
+fn physical_size<R: Runtime>(window: &Window<R>, (width, height): (u32, u32)) -> tauri::Result<Size> {
+  let scale = window.scale_factor()?;
+
+  Ok(Size {
+    width: (width as f64 * scale).round() as i32,
+    height: (height as f64 * scale).round() as i32,
+  })
+}
+
+fn monitors<R: Runtime>(window: &Window<R>) -> tauri::Result<Vec<Rect>> {
+  Ok(
+    window
+      .available_monitors()?
+      .iter()
+      .map(|monitor| Rect {
+        x: monitor.position().x,
+        y: monitor.position().y,
+        width: monitor.size().width as i32,
+        height: monitor.size().height as i32,
+      })
+      .collect(),
+  )
+}
@@ -0,0 +1,19 @@
+use rspc::{Error, ErrorCode};
+
+use super::RouterBuilder;
+use crate::history::store::HistoryRange;
+
+fn internal_error(err: anyhow::Error) -> Error { Error::new(ErrorCode::InternalServerError, err.to_string()) }
+
+pub(crate) fn history_router() -> RouterBuilder {
+  <RouterBuilder>::new()
+    .query("recent", |t| {
+      t(|ctx, range: HistoryRange| ctx.history.recent(&range).map_err(internal_error))
+    })
+    .query("topTracks", |t| {
+      t(|ctx, range: HistoryRange| ctx.history.top_tracks(&range).map_err(internal_error))
+    })
+    .query("topArtists", |t| {
+      t(|ctx, range: HistoryRange| ctx.history.top_artists(&range).map_err(internal_error))
+    })
+}
\ No newline at end of file
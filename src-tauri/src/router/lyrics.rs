@@ -0,0 +1,18 @@
+use super::RouterBuilder;
+
+pub(crate) fn lyrics_router() -> RouterBuilder {
+  <RouterBuilder>::new().subscription("currentLine", |t| {
+    t(|ctx, _input: ()| {
+      async_stream::stream! {
+        let mut current = ctx.lyrics.subscribe();
+        let line = current.borrow().clone();
+        yield line;
+
+        while current.changed().await.is_ok() {
+          let line = current.borrow().clone();
+          yield line;
+        }
+      }
+    })
+  })
+}
\ No newline at end of file
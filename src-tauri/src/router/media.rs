@@ -1,19 +1,87 @@
-use rspc::Type;
+use std::time::Duration;
+
+use rspc::{Error, ErrorCode, Type};
 use serde::{Deserialize, Serialize};
 
 use crate::media::lib::MediaEvent;
+use crate::media::manager::MediaManager;
+use crate::media::session::Session;
 
 use super::RouterBuilder;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
 #[serde(rename_all = "camelCase")]
-enum Method {
+pub enum Method {
   Play,
   Pause,
   Next,
   Previous,
+  /// In 100ns ticks, like the timeline
   SetPlaybackPosition(usize),
   SetVolume(f32),
+  SetMuted(bool),
+  ToggleMute,
+  /// Steps up, or down when negative, by the configured step and curve
+  StepVolume(i32),
+  #[serde(rename_all = "camelCase")]
+  FadeVolume { volume: f32, duration_ms: u32 },
+}
+
+impl Method {
+  pub fn apply(self, session: &Session) {
+    match self {
+      Method::Play => session.play(),
+      Method::Pause => session.pause(),
+      Method::Next => session.skip_next(),
+      Method::Previous => session.skip_previous(),
+      Method::SetPlaybackPosition(position) => session.set_playback_position(position as i64),
+      Method::SetVolume(volume) => session.set_volume(volume),
+      Method::SetMuted(muted) => session.set_muted(muted),
+      Method::ToggleMute => session.toggle_mute(),
+      Method::StepVolume(steps) => session.step_volume(steps),
+      Method::FadeVolume { volume, duration_ms } => {
+        session.fade_volume(volume, Duration::from_millis(duration_ms as u64))
+      },
+    };
+  }
+}
+
+/// Anything that can control playback outside of the frontend (the control
+/// socket, MQTT, hotkeys...) goes through this, so it can be stubbed in tests
+pub trait MediaControl: Send + Sync {
+  fn invoke(&self, method: Method) -> anyhow::Result<()>;
+}
+
+impl MediaControl for MediaManager {
+  fn invoke(&self, method: Method) -> anyhow::Result<()> {
+    match self.get_session().as_ref() {
+      Some(session) => {
+        method.apply(session);
+        Ok(())
+      },
+      None => Err(anyhow::anyhow!("No active media session")),
+    }
+  }
+}
+
+#[cfg(test)]
+pub mod stub {
+  use std::sync::Mutex;
+
+  use super::{MediaControl, Method};
+
+  /// Records every method instead of controlling a real session
+  #[derive(Default)]
+  pub struct RecordingControl {
+    pub invocations: Mutex<Vec<Method>>,
+  }
+
+  impl MediaControl for RecordingControl {
+    fn invoke(&self, method: Method) -> anyhow::Result<()> {
+      self.invocations.lock().unwrap().push(method);
+      Ok(())
+    }
+  }
 }
 
 #[derive(Serialize, Deserialize, rspc::Type)]
@@ -28,24 +96,15 @@ pub(crate) fn media_router() -> RouterBuilder {
     .mutation("invokeMethod", |t| {
       t(|ctx, method: Method| {
         if let Some(session) = ctx.manager.get_session().as_ref() {
-          match method {
-            Method::Play => session.play(),
-            Method::Pause => session.pause(),
-            Method::Next => session.skip_next(),
-            Method::Previous => session.skip_previous(),
-            Method::SetPlaybackPosition(position) => session.set_playback_position(position as i64),
-            Method::SetVolume(volume) => session.set_volume(volume),
-          };
+          method.apply(session);
         }
       })
     })
     .query("getVolume", |t| {
-      t(|ctx, _: ()| {
-        if let Some(session) = ctx.manager.get_session().as_ref() {
-          return session.get_volume()
-        }
-
-        0.5
+      t(|ctx, _: ()| match ctx.manager.get_session().as_ref() {
+        // `None` until the player's audio session is bound
+        Some(session) => Ok(session.get_volume()),
+        None => Err(Error::new(ErrorCode::NotFound, "No active media session".into())),
       })
     })
     .subscription("sessionChanged", |t| {
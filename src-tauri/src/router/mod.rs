@@ -1,22 +1,52 @@
+pub mod history;
+pub mod lyrics;
 pub mod media;
+pub mod settings;
 pub mod spotify;
 pub mod network;
+pub mod timer;
+pub mod visibility;
+pub mod window;
 
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use rspc::Config;
 
+use self::history::history_router;
+use self::lyrics::lyrics_router;
 use self::network::network_router;
 use self::spotify::spotify_router;
 use self::media::media_router;
+use self::settings::settings_router;
+use self::timer::timer_router;
+use self::visibility::visibility_router;
+use self::window::window_router;
+use crate::history::store::HistoryStore;
+use crate::hotkeys::manager::HotkeyManager;
+use crate::lyrics::tracker::LyricsTracker;
 use crate::media::lib::EventBus;
 use crate::media::manager::MediaManager;
+use crate::network::monitor::ConnectivityMonitor;
+use crate::placement::mode::ModeController;
+use crate::settings::store::SettingsStore;
+use crate::timer::controller::SleepTimer;
+use crate::utils::opener::Opener;
+use crate::visibility::controller::VisibilityController;
 
-
+#[derive(Clone)]
 pub struct Ctx {
   pub manager: Arc<MediaManager>,
   pub event_bus: Arc<EventBus>,
+  pub opener: Arc<dyn Opener>,
+  pub network: Arc<ConnectivityMonitor>,
+  pub history: Arc<HistoryStore>,
+  pub lyrics: Arc<LyricsTracker>,
+  pub hotkeys: Arc<HotkeyManager>,
+  pub settings: Arc<SettingsStore>,
+  pub modes: Arc<ModeController>,
+  pub visibility: Arc<VisibilityController>,
+  pub timer: Arc<SleepTimer>,
 }
 
 pub type Router = rspc::Router<Ctx>;
@@ -30,6 +60,12 @@ pub(crate) fn new() -> Arc<Router> {
     .merge("media.", media_router())
     .merge("spotify.", spotify_router())
     .merge("network.", network_router())
+    .merge("history.", history_router())
+    .merge("lyrics.", lyrics_router())
+    .merge("settings.", settings_router())
+    .merge("window.", window_router())
+    .merge("visibility.", visibility_router())
+    .merge("timer.", timer_router())
     .build()
     .arced()
 }
@@ -3,6 +3,20 @@ use super::RouterBuilder;
 pub(crate) fn network_router() -> RouterBuilder {
   <RouterBuilder>::new()
 		.query("status", |t| {
-      t(|_ctx, _: ()| online::check(None).is_ok())
+      t(|ctx, _: ()| ctx.network.status().is_online())
+    })
+		.subscription("statusChanged", |t| {
+      t(|ctx, _input: ()| {
+        async_stream::stream! {
+          let mut status = ctx.network.subscribe();
+          let current = status.borrow().clone();
+          yield current;
+
+          while status.changed().await.is_ok() {
+            let current = status.borrow().clone();
+            yield current;
+          }
+        }
+      })
     })
 }
\ No newline at end of file
@@ -0,0 +1,44 @@
+use rspc::{Error, ErrorCode};
+
+use super::RouterBuilder;
+use crate::hotkeys::config::Hotkey;
+use crate::settings::schema::{Settings, SettingsError};
+use crate::settings::store::UpdateError;
+
+pub(crate) fn invalid_settings(errors: Vec<SettingsError>) -> Error {
+  let messages = errors.iter().map(SettingsError::to_string).collect::<Vec<_>>();
+  Error::new(ErrorCode::BadRequest, messages.join("\n"))
+}
+
+pub(crate) fn update_error(err: UpdateError) -> Error {
+  match err {
+    UpdateError::Invalid(errors) => invalid_settings(errors),
+    err => Error::new(ErrorCode::InternalServerError, err.to_string()),
+  }
+}
+
+pub(crate) fn settings_router() -> RouterBuilder {
+  <RouterBuilder>::new()
+    .query("get", |t| t(|ctx, _: ()| ctx.settings.get()))
+    .mutation("update", |t| {
+      t(|ctx, settings: Settings| ctx.settings.update(settings).map_err(update_error))
+    })
+    .subscription("changed", |t| {
+      t(|ctx, _input: ()| {
+        async_stream::stream! {
+          let mut settings = ctx.settings.subscribe();
+          let current = settings.borrow().clone();
+          yield current;
+
+          while settings.changed().await.is_ok() {
+            let current = settings.borrow().clone();
+            yield current;
+          }
+        }
+      })
+    })
+    // Unlike `update`, also fails when another app has taken a hotkey
+    .mutation("hotkeys", |t| {
+      t(|ctx, hotkeys: Vec<Hotkey>| ctx.hotkeys.update(hotkeys).map_err(update_error))
+    })
+}
\ No newline at end of file
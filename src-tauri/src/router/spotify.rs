@@ -1,29 +1,69 @@
+use rspc::{Error, ErrorCode};
+
 use super::RouterBuilder;
+use crate::utils::opener::Opener;
+use crate::utils::spotify_uri::SpotifyUri;
+
+fn parse_uri(uri: &str) -> Result<SpotifyUri, Error> {
+  uri
+    .parse::<SpotifyUri>()
+    .map_err(|err| Error::new(ErrorCode::BadRequest, err.to_string()))
+}
+
+fn invoke_uri(opener: &dyn Opener, uri: &str) -> Result<(), Error> {
+  // Always hand the 'spotify:' form to the OS so the desktop app opens
+  let uri = parse_uri(uri)?;
 
-use windows::Win32::Foundation::HWND;
-use windows::Win32::UI::Shell::ShellExecuteW;
-use windows::Win32::UI::WindowsAndMessaging::SW_SHOW;
-use windows::core::{PCWSTR, HSTRING};
-use windows::w;
+  opener
+    .open(&uri.to_uri())
+    .map_err(|err| Error::new(ErrorCode::InternalServerError, err.to_string()))
+}
 
 pub(crate) fn spotify_router() -> RouterBuilder {
   <RouterBuilder>::new()
+		.query("parseUri", |t| {
+      t(|_ctx, uri: String| parse_uri(&uri))
+    })
 		.mutation("invokeUri", |t| {
-      t(|_ctx, uri: String| {
-				if !uri.starts_with("spotify:") {
-					return;
-				}
-
-        unsafe {
-					ShellExecuteW(
-						HWND(0),
-						w!("open"),
-						&HSTRING::from(uri),
-						PCWSTR::null(),
-						PCWSTR::null(),
-						SW_SHOW
-					);
-				}
-      })
+      t(|ctx, uri: String| invoke_uri(ctx.opener.as_ref(), &uri))
     })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::opener::stub::RecordingOpener;
+
+  #[test]
+  fn opens_the_spotify_uri_form() {
+    let opener = RecordingOpener::default();
+
+    invoke_uri(&opener, "https://open.spotify.com/intl-de/track/4uLU6hMCjMI75M1A2tKUQC?si=abc").unwrap();
+    invoke_uri(&opener, "spotify:album:4uLU6hMCjMI75M1A2tKUQC").unwrap();
+
+    assert_eq!(
+      *opener.invocations.lock().unwrap(),
+      vec!["spotify:track:4uLU6hMCjMI75M1A2tKUQC", "spotify:album:4uLU6hMCjMI75M1A2tKUQC"]
+    );
+  }
+
+  #[test]
+  fn never_opens_malformed_input() {
+    let opener = RecordingOpener::default();
+
+    assert!(invoke_uri(&opener, "calc.exe").is_err());
+    assert!(invoke_uri(&opener, "spotify:track:../../etc").is_err());
+    assert!(opener.invocations.lock().unwrap().is_empty());
+  }
+
+  #[test]
+  fn surfaces_launch_failures() {
+    let opener = RecordingOpener {
+      fail: true,
+      ..Default::default()
+    };
+
+    assert!(invoke_uri(&opener, "spotify:track:4uLU6hMCjMI75M1A2tKUQC").is_err());
+    assert_eq!(opener.invocations.lock().unwrap().len(), 1);
+  }
+}
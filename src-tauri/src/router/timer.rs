@@ -0,0 +1,27 @@
+use rspc::{Error, ErrorCode};
+
+use super::RouterBuilder;
+use crate::timer::countdown::SleepAfter;
+
+pub(crate) fn timer_router() -> RouterBuilder {
+  <RouterBuilder>::new()
+    .mutation("start", |t| {
+      t(|ctx, after: SleepAfter| ctx.timer.start(after).map_err(|message| Error::new(ErrorCode::BadRequest, message)))
+    })
+    .mutation("cancel", |t| t(|ctx, _: ()| ctx.timer.cancel()))
+    .query("remaining", |t| t(|ctx, _: ()| ctx.timer.remaining()))
+    .subscription("countdown", |t| {
+      t(|ctx, _input: ()| {
+        async_stream::stream! {
+          let mut status = ctx.timer.subscribe();
+          let current = *status.borrow();
+          yield current;
+
+          while status.changed().await.is_ok() {
+            let current = *status.borrow();
+            yield current;
+          }
+        }
+      })
+    })
+}
\ No newline at end of file
@@ -0,0 +1,20 @@
+use super::RouterBuilder;
+
+pub(crate) fn visibility_router() -> RouterBuilder {
+  <RouterBuilder>::new()
+    .query("status", |t| t(|ctx, _: ()| ctx.visibility.visibility()))
+    .subscription("changed", |t| {
+      t(|ctx, _input: ()| {
+        async_stream::stream! {
+          let mut visibility = ctx.visibility.subscribe();
+          let current = *visibility.borrow();
+          yield current;
+
+          while visibility.changed().await.is_ok() {
+            let current = *visibility.borrow();
+            yield current;
+          }
+        }
+      })
+    })
+}
\ No newline at end of file
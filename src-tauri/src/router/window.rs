@@ -0,0 +1,26 @@
+use super::settings::update_error;
+use super::RouterBuilder;
+use crate::settings::schema::DisplayMode;
+
+pub(crate) fn window_router() -> RouterBuilder {
+  <RouterBuilder>::new()
+    .query("mode", |t| t(|ctx, _: ()| ctx.modes.mode()))
+    .mutation("setMode", |t| {
+      t(|ctx, mode: DisplayMode| ctx.modes.set_mode(mode).map_err(update_error))
+    })
+    .mutation("setHovered", |t| t(|ctx, hovered: bool| ctx.modes.set_hovered(hovered)))
+    .subscription("modeChanged", |t| {
+      t(|ctx, _input: ()| {
+        async_stream::stream! {
+          let mut mode = ctx.modes.subscribe();
+          let current = *mode.borrow();
+          yield current;
+
+          while mode.changed().await.is_ok() {
+            let current = *mode.borrow();
+            yield current;
+          }
+        }
+      })
+    })
+}
\ No newline at end of file
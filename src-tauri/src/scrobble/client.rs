@@ -0,0 +1,113 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::lastfm::{LastFmClient, LastFmConfig};
+use super::listenbrainz::{ListenBrainzClient, ListenBrainzConfig};
+use crate::utils::fs::read_json;
+
+/// A service that doesn't answer must not hold up the queue
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrobbleTrack {
+  pub artists: Vec<String>,
+  pub title: String,
+  pub album: String,
+  pub duration_secs: Option<u32>,
+}
+
+impl ScrobbleTrack {
+  /// Services only take one artist, the rest are usually in the title
+  pub fn artist(&self) -> &str { self.artists.first().map(String::as_str).unwrap_or_default() }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Scrobble {
+  pub track: ScrobbleTrack,
+  /// When the track started playing, unix time in seconds
+  pub timestamp: i64,
+}
+
+#[derive(Debug)]
+pub enum SubmitError {
+  /// The service is unreachable, struggling or doesn't take our
+  /// credentials, try again later
+  Retry(String),
+  /// The service refused this submission, retrying won't help
+  Rejected(String),
+}
+
+impl fmt::Display for SubmitError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Retry(message) => write!(f, "temporary failure: {message}"),
+      Self::Rejected(message) => write!(f, "rejected: {message}"),
+    }
+  }
+}
+
+impl std::error::Error for SubmitError {}
+
+impl From<reqwest::Error> for SubmitError {
+  fn from(err: reqwest::Error) -> Self { Self::Retry(err.to_string()) }
+}
+
+impl SubmitError {
+  /// 429 and 5xx are worth retrying, and so are 401 and 403 since a fixed
+  /// token makes them go through. Any other error status is final.
+  pub fn from_status(status: reqwest::StatusCode, body: String) -> Self {
+    use reqwest::StatusCode;
+
+    if status.is_server_error()
+      || matches!(status, StatusCode::TOO_MANY_REQUESTS | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+    {
+      return Self::Retry(format!("{status}: {body}"));
+    }
+
+    Self::Rejected(format!("{status}: {body}"))
+  }
+}
+
+#[async_trait]
+pub trait ScrobbleService: Send + Sync {
+  /// Stable name used to key queued scrobbles
+  fn name(&self) -> &'static str;
+
+  async fn now_playing(&self, track: &ScrobbleTrack) -> Result<(), SubmitError>;
+
+  async fn scrobble(&self, scrobble: &Scrobble) -> Result<(), SubmitError>;
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrobbleConfig {
+  pub listenbrainz: Option<ListenBrainzConfig>,
+  pub lastfm: Option<LastFmConfig>,
+}
+
+impl ScrobbleConfig {
+  /// A missing file leaves scrobbling disabled
+  pub fn load(path: &Path) -> anyhow::Result<Self> { Ok(read_json(path)?.unwrap_or_default()) }
+
+  pub fn services(&self) -> Vec<Arc<dyn ScrobbleService>> {
+    let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build().unwrap_or_default();
+    let mut services: Vec<Arc<dyn ScrobbleService>> = vec![];
+
+    if let Some(config) = self.listenbrainz.clone() {
+      services.push(Arc::new(ListenBrainzClient::new(client.clone(), config)));
+    }
+
+    if let Some(config) = self.lastfm.clone() {
+      services.push(Arc::new(LastFmClient::new(client, config)));
+    }
+
+    services
+  }
+}
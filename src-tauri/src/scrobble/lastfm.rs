@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::client::{Scrobble, ScrobbleService, ScrobbleTrack, SubmitError};
+
+/// Error codes Last.fm asks clients to retry later
+/// https://www.last.fm/api/errorcodes
+const RETRYABLE_ERRORS: &[i64] = &[11, 16, 29];
+
+/// Bad credentials fail every scrobble alike, they are kept until the
+/// config is fixed
+const AUTH_ERRORS: &[i64] = &[4, 9, 10, 13, 14, 26];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LastFmConfig {
+  #[serde(default = "default_base_url")]
+  pub base_url: String,
+  pub api_key: String,
+  pub api_secret: String,
+  pub session_key: String,
+}
+
+fn default_base_url() -> String { "https://ws.audioscrobbler.com".into() }
+
+/// https://www.last.fm/api/scrobbling
+pub struct LastFmClient {
+  client: reqwest::Client,
+  config: LastFmConfig,
+}
+
+impl LastFmClient {
+  pub fn new(client: reqwest::Client, config: LastFmConfig) -> Self { Self { client, config } }
+
+  async fn call(&self, method: &str, mut params: BTreeMap<&str, String>) -> Result<(), SubmitError> {
+    params.insert("method", method.into());
+    params.insert("api_key", self.config.api_key.clone());
+    params.insert("sk", self.config.session_key.clone());
+    params.insert("api_sig", sign(&params, &self.config.api_secret));
+    params.insert("format", "json".into());
+
+    let response = self
+      .client
+      .post(format!("{}/2.0/", self.config.base_url.trim_end_matches('/')))
+      .form(&params)
+      .send()
+      .await?;
+
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+
+    // Errors come back as `{"error": 9, "message": "..."}`, sometimes with a
+    // success status
+    let error = serde_json::from_str::<Value>(&body)
+      .ok()
+      .and_then(|body| body.get("error").and_then(Value::as_i64));
+
+    match error {
+      Some(code) if RETRYABLE_ERRORS.contains(&code) || AUTH_ERRORS.contains(&code) => Err(SubmitError::Retry(body)),
+      Some(_) => Err(SubmitError::Rejected(body)),
+      None if !status.is_success() => Err(SubmitError::from_status(status, body)),
+      None => Ok(()),
+    }
+  }
+}
+
+fn track_params(track: &ScrobbleTrack) -> BTreeMap<&'static str, String> {
+  let mut params = BTreeMap::from([
+    ("artist", track.artist().to_string()),
+    ("track", track.title.clone()),
+    ("album", track.album.clone()),
+  ]);
+
+  if let Some(duration) = track.duration_secs {
+    params.insert("duration", duration.to_string());
+  }
+
+  params
+}
+
+/// md5 of every parameter as `<name><value>` sorted by name, followed by the
+/// shared secret
+fn sign(params: &BTreeMap<&str, String>, secret: &str) -> String {
+  let mut signature = params
+    .iter()
+    .filter(|(name, _)| !matches!(**name, "format" | "callback"))
+    .map(|(name, value)| format!("{name}{value}"))
+    .collect::<String>();
+  signature.push_str(secret);
+
+  format!("{:x}", md5::compute(signature))
+}
+
+#[async_trait]
+impl ScrobbleService for LastFmClient {
+  fn name(&self) -> &'static str { "lastfm" }
+
+  async fn now_playing(&self, track: &ScrobbleTrack) -> Result<(), SubmitError> {
+    self.call("track.updateNowPlaying", track_params(track)).await
+  }
+
+  async fn scrobble(&self, scrobble: &Scrobble) -> Result<(), SubmitError> {
+    let mut params = track_params(&scrobble.track);
+    params.insert("timestamp", scrobble.timestamp.to_string());
+
+    self.call("track.scrobble", params).await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::mock_http::MockServer;
+
+  fn client(server: &MockServer) -> LastFmClient {
+    LastFmClient::new(reqwest::Client::new(), LastFmConfig {
+      base_url: server.url.clone(),
+      api_key: "key".into(),
+      api_secret: "secret".into(),
+      session_key: "session".into(),
+    })
+  }
+
+  fn scrobble() -> Scrobble {
+    Scrobble {
+      track: ScrobbleTrack {
+        artists: vec!["A".into()],
+        title: "Song".into(),
+        album: "Album".into(),
+        duration_secs: None,
+      },
+      timestamp: 1_700_000_000,
+    }
+  }
+
+  fn form(body: &str) -> BTreeMap<String, String> { serde_urlencoded::from_str(body).unwrap() }
+
+  #[test]
+  fn signs_sorted_parameters() {
+    let params = BTreeMap::from([("b", "2".to_string()), ("a", "1".into()), ("format", "json".into())]);
+
+    assert_eq!(sign(&params, "secret"), format!("{:x}", md5::compute("a1b2secret")));
+  }
+
+  #[tokio::test]
+  async fn submits_signed_scrobbles() {
+    let server = MockServer::start().await;
+    client(&server).scrobble(&scrobble()).await.unwrap();
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/2.0/");
+
+    let params = form(&request.body);
+    assert_eq!(params["method"], "track.scrobble");
+    assert_eq!(params["artist"], "A");
+    assert_eq!(params["track"], "Song");
+    assert_eq!(params["timestamp"], "1700000000");
+    assert_eq!(params["sk"], "session");
+    assert_eq!(params["format"], "json");
+
+    let unsigned = params
+      .iter()
+      .filter(|(name, _)| *name != "api_sig")
+      .map(|(name, value)| (name.as_str(), value.clone()))
+      .collect::<BTreeMap<_, _>>();
+    assert_eq!(params["api_sig"], sign(&unsigned, "secret"));
+  }
+
+  #[tokio::test]
+  async fn classifies_errors() {
+    let server = MockServer::start().await;
+    server.respond(200, r#"{"error": 16, "message": "Temporarily unavailable"}"#);
+    server.respond(403, r#"{"error": 9, "message": "Invalid session key"}"#);
+    server.respond(200, r#"{"error": 6, "message": "Invalid parameters"}"#);
+    server.respond(502, "Bad Gateway");
+
+    let client = client(&server);
+    assert!(matches!(client.scrobble(&scrobble()).await, Err(SubmitError::Retry(_))));
+    assert!(matches!(client.scrobble(&scrobble()).await, Err(SubmitError::Retry(_))));
+    assert!(matches!(client.scrobble(&scrobble()).await, Err(SubmitError::Rejected(_))));
+    assert!(matches!(client.now_playing(&scrobble().track).await, Err(SubmitError::Retry(_))));
+  }
+}
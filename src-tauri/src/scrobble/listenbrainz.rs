@@ -0,0 +1,156 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::client::{Scrobble, ScrobbleService, ScrobbleTrack, SubmitError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListenBrainzConfig {
+  #[serde(default = "default_base_url")]
+  pub base_url: String,
+  pub token: String,
+}
+
+fn default_base_url() -> String { "https://api.listenbrainz.org".into() }
+
+/// https://listenbrainz.readthedocs.io/en/latest/users/api/core.html#post--1-submit-listens
+pub struct ListenBrainzClient {
+  client: reqwest::Client,
+  config: ListenBrainzConfig,
+}
+
+impl ListenBrainzClient {
+  pub fn new(client: reqwest::Client, config: ListenBrainzConfig) -> Self { Self { client, config } }
+
+  async fn submit(&self, listen_type: &str, listen: Value) -> Result<(), SubmitError> {
+    let response = self
+      .client
+      .post(format!("{}/1/submit-listens", self.config.base_url.trim_end_matches('/')))
+      .header("Authorization", format!("Token {}", self.config.token))
+      .json(&json!({
+        "listen_type": listen_type,
+        "payload": [listen],
+      }))
+      .send()
+      .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+      return Err(SubmitError::from_status(status, response.text().await.unwrap_or_default()));
+    }
+
+    Ok(())
+  }
+}
+
+fn track_metadata(track: &ScrobbleTrack) -> Value {
+  let mut additional_info = json!({
+    "artist_names": track.artists,
+    "media_player": "Spotify",
+    "submission_client": "media-widget",
+    "submission_client_version": env!("CARGO_PKG_VERSION"),
+  });
+
+  if let Some(duration) = track.duration_secs {
+    additional_info["duration"] = json!(duration);
+  }
+
+  json!({
+    "artist_name": track.artist(),
+    "track_name": track.title,
+    "release_name": track.album,
+    "additional_info": additional_info,
+  })
+}
+
+#[async_trait]
+impl ScrobbleService for ListenBrainzClient {
+  fn name(&self) -> &'static str { "listenbrainz" }
+
+  async fn now_playing(&self, track: &ScrobbleTrack) -> Result<(), SubmitError> {
+    self
+      .submit("playing_now", json!({ "track_metadata": track_metadata(track) }))
+      .await
+  }
+
+  async fn scrobble(&self, scrobble: &Scrobble) -> Result<(), SubmitError> {
+    self
+      .submit(
+        "single",
+        json!({
+          "listened_at": scrobble.timestamp,
+          "track_metadata": track_metadata(&scrobble.track),
+        }),
+      )
+      .await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::mock_http::MockServer;
+
+  fn client(server: &MockServer) -> ListenBrainzClient {
+    ListenBrainzClient::new(reqwest::Client::new(), ListenBrainzConfig {
+      base_url: server.url.clone(),
+      token: "secret".into(),
+    })
+  }
+
+  fn scrobble() -> Scrobble {
+    Scrobble {
+      track: ScrobbleTrack {
+        artists: vec!["A".into(), "B".into()],
+        title: "Song".into(),
+        album: "Album".into(),
+        duration_secs: Some(200),
+      },
+      timestamp: 1_700_000_000,
+    }
+  }
+
+  #[tokio::test]
+  async fn submits_listens() {
+    let server = MockServer::start().await;
+    client(&server).scrobble(&scrobble()).await.unwrap();
+
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/1/submit-listens");
+    assert_eq!(request.header("authorization"), Some("Token secret"));
+
+    let body: Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(body["listen_type"], "single");
+    assert_eq!(body["payload"][0]["listened_at"], 1_700_000_000);
+    assert_eq!(body["payload"][0]["track_metadata"]["artist_name"], "A");
+    assert_eq!(body["payload"][0]["track_metadata"]["track_name"], "Song");
+    assert_eq!(body["payload"][0]["track_metadata"]["release_name"], "Album");
+    assert_eq!(body["payload"][0]["track_metadata"]["additional_info"]["artist_names"], json!(["A", "B"]));
+    assert_eq!(body["payload"][0]["track_metadata"]["additional_info"]["duration"], 200);
+  }
+
+  #[tokio::test]
+  async fn submits_now_playing_without_timestamp() {
+    let server = MockServer::start().await;
+    client(&server).now_playing(&scrobble().track).await.unwrap();
+
+    let body: Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+    assert_eq!(body["listen_type"], "playing_now");
+    assert!(body["payload"][0].get("listened_at").is_none());
+  }
+
+  #[tokio::test]
+  async fn classifies_errors() {
+    let server = MockServer::start().await;
+    server.respond(503, "{}");
+    server.respond(401, r#"{"error": "Invalid authorization token."}"#);
+    server.respond(400, r#"{"error": "Invalid JSON document submitted."}"#);
+
+    let client = client(&server);
+    assert!(matches!(client.scrobble(&scrobble()).await, Err(SubmitError::Retry(_))));
+    assert!(matches!(client.scrobble(&scrobble()).await, Err(SubmitError::Retry(_))));
+    assert!(matches!(client.scrobble(&scrobble()).await, Err(SubmitError::Rejected(_))));
+  }
+}
@@ -0,0 +1,6 @@
+pub mod client;
+pub mod lastfm;
+pub mod listenbrainz;
+pub mod queue;
+pub mod scrobbler;
+pub mod tracker;
\ No newline at end of file
@@ -0,0 +1,204 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::client::Scrobble;
+
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedScrobble {
+  pub id: i64,
+  pub service: String,
+  pub scrobble: Scrobble,
+  pub attempts: u32,
+}
+
+/// Scrobbles waiting to be submitted, kept on disk so nothing is lost while
+/// offline or across restarts
+pub struct ScrobbleQueue {
+  connection: Mutex<Connection>,
+}
+
+impl ScrobbleQueue {
+  pub fn open(path: &Path) -> anyhow::Result<Self> {
+    println!("[ScrobbleQueue] open {:?}", path);
+
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+
+    Self::from_connection(Connection::open(path)?)
+  }
+
+  #[cfg(test)]
+  pub fn open_in_memory() -> anyhow::Result<Self> { Self::from_connection(Connection::open_in_memory()?) }
+
+  fn from_connection(connection: Connection) -> anyhow::Result<Self> {
+    connection.execute_batch(
+      "CREATE TABLE IF NOT EXISTS queue (
+        id INTEGER PRIMARY KEY,
+        service TEXT NOT NULL,
+        scrobble TEXT NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        next_attempt_at INTEGER NOT NULL
+      );
+      CREATE INDEX IF NOT EXISTS queue_next_attempt_at ON queue (next_attempt_at);",
+    )?;
+
+    Ok(Self {
+      connection: Mutex::new(connection),
+    })
+  }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+
+  pub fn push(&self, service: &str, scrobble: &Scrobble, now: i64) -> anyhow::Result<()> {
+    self.connection.lock().unwrap().execute(
+      "INSERT INTO queue (service, scrobble, next_attempt_at) VALUES (?1, ?2, ?3)",
+      params![service, serde_json::to_string(scrobble)?, now],
+    )?;
+
+    Ok(())
+  }
+
+  /// Oldest scrobbles first, only those whose backoff has elapsed
+  pub fn due(&self, now: i64, limit: u32) -> anyhow::Result<Vec<QueuedScrobble>> {
+    let connection = self.connection.lock().unwrap();
+    let mut statement = connection.prepare(
+      "SELECT id, service, scrobble, attempts FROM queue
+       WHERE next_attempt_at <= ?1
+       ORDER BY id
+       LIMIT ?2",
+    )?;
+
+    let rows = statement
+      .query_map(params![now, limit], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?, row.get(3)?))
+      })?
+      .collect::<Result<Vec<_>, _>>()?;
+
+    rows
+      .into_iter()
+      .map(|(id, service, scrobble, attempts)| {
+        Ok(QueuedScrobble {
+          id,
+          service,
+          scrobble: serde_json::from_str(&scrobble)?,
+          attempts,
+        })
+      })
+      .collect()
+  }
+
+  pub fn remove(&self, id: i64) -> anyhow::Result<()> {
+    self
+      .connection
+      .lock()
+      .unwrap()
+      .execute("DELETE FROM queue WHERE id = ?1", params![id])?;
+
+    Ok(())
+  }
+
+  /// Pushes the next attempt back exponentially
+  pub fn retry_later(&self, scrobble: &QueuedScrobble, now: i64) -> anyhow::Result<()> {
+    let attempts = scrobble.attempts + 1;
+
+    self.connection.lock().unwrap().execute(
+      "UPDATE queue SET attempts = ?1, next_attempt_at = ?2 WHERE id = ?3",
+      params![attempts, now.saturating_add(backoff(attempts).as_millis() as i64), scrobble.id],
+    )?;
+
+    Ok(())
+  }
+
+  /// When the earliest queued scrobble can be attempted
+  pub fn next_attempt_at(&self) -> anyhow::Result<Option<i64>> {
+    Ok(
+      self
+        .connection
+        .lock()
+        .unwrap()
+        .query_row("SELECT min(next_attempt_at) FROM queue", [], |row| row.get(0))
+        .optional()?
+        .flatten(),
+    )
+  }
+}
+
+pub fn backoff(attempts: u32) -> Duration {
+  BASE_BACKOFF
+    .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+    .min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::scrobble::client::ScrobbleTrack;
+
+  fn scrobble(title: &str) -> Scrobble {
+    Scrobble {
+      track: ScrobbleTrack {
+        artists: vec!["A".into()],
+        title: title.into(),
+        album: "Album".into(),
+        duration_secs: Some(200),
+      },
+      timestamp: 0,
+    }
+  }
+
+  #[test]
+  fn backs_off_exponentially() {
+    assert_eq!(
+      (1..=9).map(|attempts| backoff(attempts).as_secs()).collect::<Vec<_>>(),
+      vec![30, 60, 120, 240, 480, 960, 1920, 3600, 3600]
+    );
+    assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+  }
+
+  #[test]
+  fn retries_after_backoff() {
+    let queue = ScrobbleQueue::open_in_memory().unwrap();
+    queue.push("lastfm", &scrobble("A"), 0).unwrap();
+    queue.push("lastfm", &scrobble("B"), 0).unwrap();
+
+    let due = queue.due(0, 10).unwrap();
+    assert_eq!(due.iter().map(|queued| queued.scrobble.track.title.as_str()).collect::<Vec<_>>(), vec!["A", "B"]);
+
+    queue.retry_later(&due[0], 0).unwrap();
+    queue.remove(due[1].id).unwrap();
+
+    assert!(queue.due(29_999, 10).unwrap().is_empty());
+    assert_eq!(queue.next_attempt_at().unwrap(), Some(30_000));
+
+    let due = queue.due(30_000, 10).unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].attempts, 1);
+
+    queue.remove(due[0].id).unwrap();
+    assert_eq!(queue.next_attempt_at().unwrap(), None);
+  }
+
+  #[test]
+  fn survives_reopening() {
+    let path = std::env::temp_dir().join(format!("media-widget-queue-{}.sqlite3", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    ScrobbleQueue::open(&path)
+      .unwrap()
+      .push("listenbrainz", &scrobble("A"), 0)
+      .unwrap();
+
+    let due = ScrobbleQueue::open(&path).unwrap().due(0, 10).unwrap();
+    assert_eq!(due[0].service, "listenbrainz");
+    assert_eq!(due[0].scrobble, scrobble("A"));
+
+    std::fs::remove_file(&path).unwrap();
+  }
+}
@@ -0,0 +1,272 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch, Notify};
+use tokio::task::JoinHandle;
+
+use super::client::{ScrobbleService, SubmitError};
+use super::queue::ScrobbleQueue;
+use super::tracker::ScrobbleTracker;
+use crate::media::lib::{EventBus, MediaEvent};
+use crate::network::monitor::NetworkStatus;
+use crate::utils::time::now_ms;
+
+/// How long to sleep when there is nothing to wait for
+const IDLE: Duration = Duration::from_secs(60 * 60);
+
+/// How many queued scrobbles to submit per round
+const BATCH_SIZE: u32 = 50;
+
+type Services = Arc<Vec<Arc<dyn ScrobbleService>>>;
+
+/// Announces "now playing" and queues scrobbles from the media events, and
+/// submits the queue whenever we are online
+pub fn spawn(
+  event_bus: Arc<EventBus>,
+  network: watch::Receiver<NetworkStatus>,
+  queue: Arc<ScrobbleQueue>,
+  services: Vec<Arc<dyn ScrobbleService>>,
+) -> Option<(JoinHandle<()>, JoinHandle<()>)> {
+  if services.is_empty() {
+    println!("[Scrobbler] no services configured");
+    return None;
+  }
+
+  println!("[Scrobbler] spawn");
+
+  let services = Arc::new(services);
+  let wake = Arc::new(Notify::new());
+
+  Some((
+    tokio::spawn(track(
+      event_bus.0.subscribe(),
+      network.clone(),
+      queue.clone(),
+      services.clone(),
+      wake.clone(),
+    )),
+    tokio::spawn(submit(network, queue, services, wake)),
+  ))
+}
+
+async fn track(
+  mut events: broadcast::Receiver<MediaEvent>,
+  network: watch::Receiver<NetworkStatus>,
+  queue: Arc<ScrobbleQueue>,
+  services: Services,
+  wake: Arc<Notify>,
+) {
+  let mut tracker = ScrobbleTracker::new();
+
+  loop {
+    let poll_in = tracker
+      .next_poll_in(now_ms())
+      .map(|ms| Duration::from_millis(ms as u64))
+      .unwrap_or(IDLE);
+
+    tokio::select! {
+      event = events.recv() => {
+        let event = match event {
+          Ok(event) => event,
+          Err(RecvError::Lagged(_)) => continue,
+          Err(RecvError::Closed) => break,
+        };
+
+        // "Now playing" is only useful right now, so it is never queued. It's
+        // sent on the side so a slow service doesn't hold up the events
+        if let Some(track) = tracker.handle(&event, now_ms()) {
+          if network.borrow().is_online() {
+            for service in services.iter() {
+              let (service, track) = (service.clone(), track.clone());
+              tokio::spawn(async move {
+                if let Err(err) = service.now_playing(&track).await {
+                  println!("[Scrobbler] {} now playing | Error: {}", service.name(), err);
+                }
+              });
+            }
+          }
+        }
+      },
+      _ = tokio::time::sleep(poll_in) => {},
+    }
+
+    if let Some(scrobble) = tracker.poll(now_ms()) {
+      for service in services.iter() {
+        if let Err(err) = queue.push(service.name(), &scrobble, now_ms()) {
+          println!("[Scrobbler] queue | Error: {:?}", err);
+        }
+      }
+
+      wake.notify_one();
+    }
+  }
+}
+
+async fn submit(
+  mut network: watch::Receiver<NetworkStatus>,
+  queue: Arc<ScrobbleQueue>,
+  services: Services,
+  wake: Arc<Notify>,
+) {
+  let mut watching_network = true;
+
+  loop {
+    let online = network.borrow().is_online();
+    if online {
+      if let Err(err) = flush(&queue, &services, now_ms()).await {
+        println!("[Scrobbler] flush | Error: {:?}", err);
+      }
+    }
+
+    let wait = next_round_in(online, queue.next_attempt_at().ok().flatten(), now_ms());
+
+    tokio::select! {
+      _ = wake.notified() => {},
+      changed = network.changed(), if watching_network => watching_network = changed.is_ok(),
+      _ = tokio::time::sleep(wait.unwrap_or(IDLE)), if wait.is_some() => {},
+    }
+  }
+}
+
+/// How long to wait before the next submit round. Offline nothing can be
+/// submitted, so only a network change or a new scrobble wakes the loop.
+fn next_round_in(online: bool, next_attempt_at: Option<i64>, now: i64) -> Option<Duration> {
+  if !online {
+    return None;
+  }
+
+  let wait = next_attempt_at.map(|at| Duration::from_millis((at - now).max(0) as u64));
+  Some(wait.unwrap_or(IDLE))
+}
+
+/// Submits every due scrobble once, pushing temporary failures back in the
+/// queue and dropping rejected ones
+pub async fn flush(queue: &ScrobbleQueue, services: &[Arc<dyn ScrobbleService>], now: i64) -> anyhow::Result<()> {
+  for queued in queue.due(now, BATCH_SIZE)? {
+    let Some(service) = services.iter().find(|service| service.name() == queued.service) else {
+      println!("[Scrobbler] dropping scrobble for removed service '{}'", queued.service);
+      queue.remove(queued.id)?;
+      continue;
+    };
+
+    match service.scrobble(&queued.scrobble).await {
+      Ok(()) => queue.remove(queued.id)?,
+      Err(SubmitError::Rejected(message)) => {
+        println!("[Scrobbler] {} rejected scrobble: {}", service.name(), message);
+        queue.remove(queued.id)?;
+      },
+      Err(SubmitError::Retry(message)) => {
+        println!("[Scrobbler] {} will retry: {}", service.name(), message);
+        queue.retry_later(&queued, now)?;
+      },
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::future::Future;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  use super::*;
+  use crate::network::monitor::Connectivity;
+  use crate::scrobble::client::{Scrobble, ScrobbleTrack};
+  use crate::scrobble::listenbrainz::{ListenBrainzClient, ListenBrainzConfig};
+  use crate::utils::mock_http::MockServer;
+
+  fn scrobble(title: &str) -> Scrobble {
+    Scrobble {
+      track: ScrobbleTrack {
+        artists: vec!["A".into()],
+        title: title.into(),
+        album: "Album".into(),
+        duration_secs: Some(200),
+      },
+      timestamp: 0,
+    }
+  }
+
+  fn services(server: &MockServer) -> Vec<Arc<dyn ScrobbleService>> {
+    vec![Arc::new(ListenBrainzClient::new(reqwest::Client::new(), ListenBrainzConfig {
+      base_url: server.url.clone(),
+      token: "secret".into(),
+    }))]
+  }
+
+  fn status(connectivity: Connectivity) -> NetworkStatus {
+    NetworkStatus {
+      connectivity,
+      latency_ms: None,
+    }
+  }
+
+  #[tokio::test]
+  async fn keeps_failed_scrobbles_queued() {
+    let server = MockServer::start().await;
+    server.respond(503, "{}");
+    server.respond(400, "{}");
+
+    let queue = ScrobbleQueue::open_in_memory().unwrap();
+    queue.push("listenbrainz", &scrobble("A"), 0).unwrap();
+    queue.push("listenbrainz", &scrobble("B"), 0).unwrap();
+    queue.push("lastfm", &scrobble("C"), 0).unwrap();
+
+    flush(&queue, &services(&server), 0).await.unwrap();
+
+    // A failed temporarily, B was rejected, C has no service anymore
+    let remaining = queue.due(i64::MAX, 10).unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].scrobble, scrobble("A"));
+    assert!(queue.due(0, 10).unwrap().is_empty());
+
+    flush(&queue, &services(&server), i64::MAX).await.unwrap();
+    assert_eq!(queue.next_attempt_at().unwrap(), None);
+    assert_eq!(server.requests().len(), 3);
+  }
+
+  #[tokio::test]
+  async fn submits_the_queue_once_online() {
+    let server = MockServer::start().await;
+    let queue = ScrobbleQueue::open_in_memory().unwrap().arced();
+    queue.push("listenbrainz", &scrobble("A"), 0).unwrap();
+
+    let (network, status_receiver) = watch::channel(status(Connectivity::Offline));
+    let mut submitter = Box::pin(submit(
+      status_receiver,
+      queue.clone(),
+      Arc::new(services(&server)),
+      Arc::new(Notify::new()),
+    ));
+
+    // Counts how often the loop gets woken up, a due scrobble must not make
+    // it spin while offline
+    let polls = Arc::new(AtomicUsize::new(0));
+    let task = tokio::spawn({
+      let polls = polls.clone();
+      std::future::poll_fn(move |cx| {
+        polls.fetch_add(1, Ordering::SeqCst);
+        submitter.as_mut().poll(cx)
+      })
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(server.requests().is_empty());
+    assert_eq!(polls.load(Ordering::SeqCst), 1);
+
+    network.send(status(Connectivity::Online)).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+      while queue.next_attempt_at().unwrap().is_some() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+      }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(server.requests().len(), 1);
+    task.abort();
+  }
+}
@@ -0,0 +1,287 @@
+use super::client::{Scrobble, ScrobbleTrack};
+use crate::media::lib::{MediaEvent, MediaSessionData};
+
+/// Tracks shorter than this are never scrobbled
+const MIN_DURATION_MS: i64 = 30_000;
+
+/// A track is scrobbled after half its length or this long, whichever is first
+const MAX_THRESHOLD_MS: i64 = 4 * 60 * 1000;
+
+/// Position (ms) below which the same track coming back after it has been
+/// scrobbled is treated as it being played again
+const REPLAY_POSITION_MS: usize = 3_000;
+
+#[derive(Debug)]
+struct CurrentTrack {
+  track: ScrobbleTrack,
+  started_at: i64,
+  listened_ms: i64,
+  playing_since: Option<i64>,
+  duration_ms: Option<i64>,
+  announced: bool,
+  scrobbled: bool,
+}
+
+impl CurrentTrack {
+  fn listened_at(&self, now: i64) -> i64 {
+    self.listened_ms + self.playing_since.map(|since| (now - since).max(0)).unwrap_or(0)
+  }
+
+  fn threshold_ms(&self) -> Option<i64> {
+    match self.duration_ms {
+      Some(duration) if duration < MIN_DURATION_MS => None,
+      Some(duration) => Some((duration / 2).min(MAX_THRESHOLD_MS)),
+      None => Some(MAX_THRESHOLD_MS),
+    }
+  }
+}
+
+/// Decides when to announce "now playing" and when a track has been listened
+/// to long enough to scrobble. Time is passed in explicitly (unix ms).
+#[derive(Debug, Default)]
+pub struct ScrobbleTracker {
+  is_playing: bool,
+  current: Option<CurrentTrack>,
+}
+
+impl ScrobbleTracker {
+  pub fn new() -> Self { Self::default() }
+
+  /// Feeds one event into the tracker and returns the track to announce as
+  /// "now playing", if any
+  pub fn handle(&mut self, event: &MediaEvent, now: i64) -> Option<ScrobbleTrack> {
+    match event {
+      MediaEvent::MediaPropertiesChanged(data) => {
+        let track = to_track(data);
+        if self.current.as_ref().is_some_and(|current| is_same_track(&current.track, &track)) {
+          return None;
+        }
+
+        self.start(track, None, now);
+      },
+      MediaEvent::PlaybackInfoChanged(data) => {
+        self.is_playing = data.is_playing;
+
+        let current = self.current.as_mut()?;
+        match (data.is_playing, current.playing_since) {
+          (true, None) => current.playing_since = Some(now),
+          (false, Some(since)) => {
+            current.listened_ms += (now - since).max(0);
+            current.playing_since = None;
+          },
+          _ => {},
+        }
+      },
+      MediaEvent::TimelinePropertiesChanged(data) => {
+        let current = self.current.as_mut()?;
+        let duration_ms = data.duration_ms() as i64;
+
+        if duration_ms > 0 && current.duration_ms != Some(duration_ms) {
+          current.duration_ms = Some(duration_ms);
+          current.track.duration_secs = Some((duration_ms / 1000) as u32);
+        }
+
+        // Back at the start of a track that was already scrobbled, either on
+        // repeat or played again, so it is a new play
+        if current.scrobbled && data.position_ms() < REPLAY_POSITION_MS {
+          let (track, duration_ms) = (current.track.clone(), current.duration_ms);
+          self.start(track, duration_ms, now);
+        }
+      },
+      MediaEvent::Disconnect(_) => {
+        self.current = None;
+        self.is_playing = false;
+      },
+      MediaEvent::Connect(_) | MediaEvent::VolumeChanged(_) => {},
+    }
+
+    let current = self.current.as_mut()?;
+    if current.announced || !self.is_playing {
+      return None;
+    }
+
+    current.announced = true;
+    Some(current.track.clone())
+  }
+
+  fn start(&mut self, track: ScrobbleTrack, duration_ms: Option<i64>, now: i64) {
+    self.current = Some(CurrentTrack {
+      track,
+      started_at: now,
+      listened_ms: 0,
+      playing_since: self.is_playing.then_some(now),
+      duration_ms,
+      announced: false,
+      scrobbled: false,
+    });
+  }
+
+  /// Returns the scrobble once the threshold has been passed, only once per play
+  pub fn poll(&mut self, now: i64) -> Option<Scrobble> {
+    let current = self.current.as_mut()?;
+    if current.scrobbled || current.listened_at(now) < current.threshold_ms()? {
+      return None;
+    }
+
+    current.scrobbled = true;
+    Some(Scrobble {
+      track: current.track.clone(),
+      timestamp: current.started_at / 1000,
+    })
+  }
+
+  /// Milliseconds until `poll` would return a scrobble, if playback continues
+  pub fn next_poll_in(&self, now: i64) -> Option<i64> {
+    let current = self.current.as_ref()?;
+    if current.scrobbled || current.playing_since.is_none() {
+      return None;
+    }
+
+    Some((current.threshold_ms()? - current.listened_at(now)).max(0))
+  }
+}
+
+fn to_track(data: &MediaSessionData) -> ScrobbleTrack {
+  ScrobbleTrack {
+    artists: data.artists.clone(),
+    title: data.title.clone(),
+    album: data.album.clone(),
+    duration_secs: None,
+  }
+}
+
+fn is_same_track(a: &ScrobbleTrack, b: &ScrobbleTrack) -> bool {
+  a.artists == b.artists && a.title == b.title && a.album == b.album
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::{MediaPlaybackData, MediaTimelineData};
+
+  fn track(title: &str) -> MediaEvent {
+    MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture(title, &["Artist"]))
+  }
+
+  fn playing(is_playing: bool) -> MediaEvent {
+    MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing })
+  }
+
+  fn duration(duration_ms: usize) -> MediaEvent { timeline(0, duration_ms) }
+
+  fn timeline(position_ms: usize, duration_ms: usize) -> MediaEvent {
+    MediaEvent::TimelinePropertiesChanged(MediaTimelineData::fixture(position_ms, duration_ms))
+  }
+
+  #[test]
+  fn announces_now_playing_once_playing() {
+    let mut tracker = ScrobbleTracker::new();
+
+    assert_eq!(tracker.handle(&track("A"), 0), None);
+    assert_eq!(tracker.handle(&playing(true), 1_000).map(|track| track.title), Some("A".into()));
+    assert_eq!(tracker.handle(&track("A"), 2_000), None);
+    assert_eq!(tracker.handle(&playing(false), 3_000), None);
+    assert_eq!(tracker.handle(&playing(true), 4_000), None);
+    assert_eq!(tracker.handle(&track("B"), 5_000).map(|track| track.title), Some("B".into()));
+  }
+
+  #[test]
+  fn scrobbles_after_half_the_track() {
+    let mut tracker = ScrobbleTracker::new();
+    tracker.handle(&playing(true), 0);
+    tracker.handle(&track("A"), 0);
+    tracker.handle(&duration(200_000), 0);
+
+    assert_eq!(tracker.next_poll_in(0), Some(100_000));
+    assert_eq!(tracker.poll(99_999), None);
+
+    let scrobble = tracker.poll(100_000).unwrap();
+    assert_eq!(scrobble.track.title, "A");
+    assert_eq!(scrobble.track.duration_secs, Some(200));
+    assert_eq!(scrobble.timestamp, 0);
+
+    // Only once per play
+    assert_eq!(tracker.poll(150_000), None);
+    assert_eq!(tracker.next_poll_in(150_000), None);
+  }
+
+  #[test]
+  fn scrobbles_long_tracks_after_four_minutes() {
+    let mut tracker = ScrobbleTracker::new();
+    tracker.handle(&playing(true), 0);
+    tracker.handle(&track("A"), 0);
+    tracker.handle(&duration(20 * 60 * 1000), 0);
+
+    assert_eq!(tracker.poll(MAX_THRESHOLD_MS - 1), None);
+    assert!(tracker.poll(MAX_THRESHOLD_MS).is_some());
+  }
+
+  #[test]
+  fn does_not_count_paused_time() {
+    let mut tracker = ScrobbleTracker::new();
+    tracker.handle(&playing(true), 0);
+    tracker.handle(&track("A"), 0);
+    tracker.handle(&duration(100_000), 0);
+    tracker.handle(&playing(false), 20_000);
+
+    assert_eq!(tracker.next_poll_in(20_000), None);
+    assert_eq!(tracker.poll(500_000), None);
+
+    tracker.handle(&playing(true), 500_000);
+    assert_eq!(tracker.next_poll_in(500_000), Some(30_000));
+    assert!(tracker.poll(530_000).is_some());
+  }
+
+  #[test]
+  fn never_scrobbles_short_tracks() {
+    let mut tracker = ScrobbleTracker::new();
+    tracker.handle(&playing(true), 0);
+    tracker.handle(&track("A"), 0);
+    tracker.handle(&duration(29_000), 0);
+
+    assert_eq!(tracker.poll(29_000), None);
+    assert_eq!(tracker.next_poll_in(0), None);
+  }
+
+  #[test]
+  fn scrobbles_the_same_track_again_on_repeat() {
+    let mut tracker = ScrobbleTracker::new();
+    tracker.handle(&playing(true), 0);
+    tracker.handle(&track("A"), 0);
+    tracker.handle(&duration(200_000), 0);
+    assert!(tracker.poll(100_000).is_some());
+
+    // Mid-track updates of the same track are still the same play
+    tracker.handle(&timeline(150_000, 200_000), 150_000);
+    assert_eq!(tracker.handle(&track("A"), 200_000), None);
+
+    let announced = tracker.handle(&timeline(0, 200_000), 200_000);
+    assert_eq!(announced.map(|track| track.title), Some("A".into()));
+    assert_eq!(tracker.next_poll_in(200_000), Some(100_000));
+
+    let scrobble = tracker.poll(300_000).unwrap();
+    assert_eq!(scrobble.timestamp, 200);
+    assert_eq!(scrobble.track.duration_secs, Some(200));
+  }
+
+  #[test]
+  fn keeps_the_play_when_restarted_before_the_scrobble() {
+    let mut tracker = ScrobbleTracker::new();
+    tracker.handle(&playing(true), 0);
+    tracker.handle(&track("A"), 0);
+    tracker.handle(&duration(200_000), 0);
+    tracker.handle(&timeline(0, 200_000), 60_000);
+
+    assert_eq!(tracker.poll(100_000).map(|scrobble| scrobble.timestamp), Some(0));
+  }
+
+  #[test]
+  fn forgets_the_track_on_disconnect() {
+    let mut tracker = ScrobbleTracker::new();
+    tracker.handle(&playing(true), 0);
+    tracker.handle(&track("A"), 0);
+    tracker.handle(&MediaEvent::Disconnect("Spotify.exe".into()), 10_000);
+
+    assert_eq!(tracker.poll(MAX_THRESHOLD_MS), None);
+  }
+}
@@ -0,0 +1,95 @@
+use anyhow::{anyhow, bail};
+use serde_json::{Map, Value};
+
+use super::schema::CURRENT_VERSION;
+
+/// Upgrades the raw document by one version
+pub type Migration = fn(&mut Map<String, Value>);
+
+/// Step `i` takes version `i` to `i + 1`, so there's one per version below
+/// `CURRENT_VERSION`
+pub const MIGRATIONS: &[Migration] = &[from_unversioned];
+
+/// Files without a `version` were written by hand and already use the
+/// version 1 layout
+fn from_unversioned(_: &mut Map<String, Value>) {}
+
+/// Brings `document` up to `CURRENT_VERSION`, returns whether anything was
+/// migrated
+pub fn migrate(document: &mut Value) -> anyhow::Result<bool> { migrate_with(document, MIGRATIONS, CURRENT_VERSION) }
+
+fn migrate_with(document: &mut Value, migrations: &[Migration], current: u32) -> anyhow::Result<bool> {
+  let object = document
+    .as_object_mut()
+    .ok_or_else(|| anyhow!("Settings should be an object"))?;
+
+  let version = match object.get("version") {
+    None => 0,
+    Some(version) => version
+      .as_u64()
+      .and_then(|version| u32::try_from(version).ok())
+      .ok_or_else(|| anyhow!("Invalid settings version {version}"))?,
+  };
+
+  if version > current {
+    bail!("Settings were written by a newer version of the app (version {version}, this one reads up to {current})");
+  }
+
+  for (step, migration) in migrations.iter().enumerate().skip(version as usize) {
+    migration(object);
+    object.insert("version".into(), Value::from(step as u32 + 1));
+  }
+
+  Ok(version < current)
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  fn split_size(object: &mut Map<String, Value>) {
+    if let Some(size) = object.remove("size") {
+      object.insert("window".into(), json!({"width": size[0], "height": size[1]}));
+    }
+  }
+
+  fn rename_color(object: &mut Map<String, Value>) {
+    if let Some(color) = object.remove("color") {
+      object.insert("appearance".into(), json!({ "fallbackColor": color }));
+    }
+  }
+
+  #[test]
+  fn runs_every_step_after_the_stored_version() {
+    let steps: &[Migration] = &[split_size, rename_color];
+
+    let mut document = json!({"size": [400, 100], "color": [1, 2, 3]});
+    assert!(migrate_with(&mut document, steps, 2).unwrap());
+    assert_eq!(
+      document,
+      json!({"version": 2, "window": {"width": 400, "height": 100}, "appearance": {"fallbackColor": [1, 2, 3]}})
+    );
+
+    // Version 1 already has `window`, only the color moves
+    let mut document = json!({"version": 1, "size": [1, 1], "color": [1, 2, 3]});
+    assert!(migrate_with(&mut document, steps, 2).unwrap());
+    assert_eq!(document, json!({"version": 2, "size": [1, 1], "appearance": {"fallbackColor": [1, 2, 3]}}));
+
+    let mut document = json!({"version": 2});
+    assert!(!migrate_with(&mut document, steps, 2).unwrap());
+  }
+
+  #[test]
+  fn rejects_unknown_versions() {
+    let error = migrate(&mut json!({"version": CURRENT_VERSION + 1})).unwrap_err();
+    assert!(error.to_string().starts_with("Settings were written by a newer version of the app"));
+
+    assert!(migrate(&mut json!({"version": "one"})).is_err());
+    assert!(migrate(&mut json!([])).is_err());
+  }
+
+  #[test]
+  fn every_version_has_a_migration() { assert_eq!(MIGRATIONS.len(), CURRENT_VERSION as usize); }
+}
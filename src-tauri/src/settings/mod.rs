@@ -0,0 +1,3 @@
+pub mod migrate;
+pub mod schema;
+pub mod store;
\ No newline at end of file
@@ -0,0 +1,499 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::hotkeys::config::{default_hotkeys, Hotkey};
+use crate::network::probe::ProbeTarget;
+use crate::utils::spotify::DEFAULT_EXCEPTIONS;
+
+/// Bumped whenever a field is renamed, moved or changes meaning, along with
+/// a new step in `migrate::MIGRATIONS`
+pub const CURRENT_VERSION: u32 = 1;
+
+pub type Rgb = (u8, u8, u8);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+  pub version: u32,
+  pub window: WindowSettings,
+  pub appearance: AppearanceSettings,
+  pub player: PlayerSettings,
+  pub visibility: VisibilitySettings,
+  pub volume: VolumeSettings,
+  pub sleep_timer: SleepTimerSettings,
+  pub ducking: DuckingSettings,
+  /// Starts the widget with the desktop session
+  pub autostart: bool,
+  /// Global shortcuts, they work while another app has focus
+  pub hotkeys: Vec<Hotkey>,
+  pub lyrics: LyricsSettings,
+  pub notifications: NotificationSettings,
+  /// Exporting the track for streaming overlays, off when `None`
+  pub now_playing: Option<NowPlayingSettings>,
+  /// Discord rich presence, off when `None`
+  pub discord: Option<DiscordSettings>,
+  pub credits: CreditSettings,
+  pub network: NetworkSettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WindowSettings {
+  /// Logical pixels, in compact mode. Mini mode is a `height` square.
+  pub width: u32,
+  pub height: u32,
+  pub expanded_height: u32,
+  pub mode: DisplayMode,
+  /// Expands the window while the cursor is over it
+  pub auto_expand: bool,
+  pub snap: SnapMode,
+  /// Physical pixels from a screen edge at which the window snaps to it
+  pub snap_distance: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum DisplayMode {
+  /// Cover art only
+  Mini,
+  Compact,
+  /// Large art, progress, volume and lyrics
+  Expanded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum SnapMode {
+  Off,
+  Edges,
+  Corners,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppearanceSettings {
+  /// Used when the cover has no usable colors, or there's no cover at all
+  pub fallback_color: Rgb,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PlayerSettings {
+  /// Media sessions we follow, by app id. The first one found wins.
+  pub app_ids: Vec<String>,
+  /// Part of the cover that's kept, `None` keeps all of it
+  pub cover_crop: Option<CropRect>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VisibilitySettings {
+  /// Hides the widget once playback has been paused this long, `None`
+  /// keeps it up
+  pub hide_when_paused_secs: Option<u32>,
+  /// Hides the widget when the player closes
+  pub hide_on_disconnect: bool,
+  /// Hides the widget while a fullscreen window is on its monitor
+  pub hide_in_fullscreen: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VolumeSettings {
+  /// How far one step up or down goes, along `curve`
+  pub step_percent: u32,
+  pub curve: VolumeCurve,
+  /// Nothing sets the volume above this
+  pub max_percent: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum VolumeCurve {
+  /// Steps of the same size on the player's volume
+  Linear,
+  /// Steps that sound the same size, smaller ones near silence
+  Perceptual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SleepTimerSettings {
+  /// The volume is lowered over this long before the timer pauses
+  pub fade_secs: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DuckingSettings {
+  pub enabled: bool,
+  pub mode: DuckMode,
+  /// The player's volume while ducked, in `lower` mode
+  pub lower_to_percent: u32,
+  /// Process names that duck the player, e.g. `Teams.exe`. Empty means any.
+  pub allow: Vec<String>,
+  /// Process names that never do, e.g. apps that only play notification
+  /// sounds
+  pub deny: Vec<String>,
+  /// How long the other apps have to stay quiet before the player is
+  /// restored
+  pub restore_delay_secs: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum DuckMode {
+  Lower,
+  Pause,
+}
+
+/// Read at startup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LyricsSettings {
+  /// Searched for `.lrc` files
+  pub directories: Vec<String>,
+}
+
+/// Read at startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationSettings {
+  /// For apps missing from `apps`
+  pub enabled: bool,
+  /// Per app on/off, keyed by app id, e.g. `{"Spotify.exe": true}`
+  pub apps: BTreeMap<String, bool>,
+  /// How long properties have to stay the same before we notify
+  pub debounce_ms: u32,
+}
+
+/// Read at startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NowPlayingSettings {
+  /// Where the files are written, e.g. a folder OBS text sources read from
+  pub directory: String,
+  /// File name to template, e.g. `"now-playing.txt": "{{title}} — {{artists}}"`
+  pub templates: BTreeMap<String, String>,
+  /// File name for the cover art PNG, `null` to skip it
+  pub cover_art: Option<String>,
+}
+
+/// Read at startup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiscordSettings {
+  /// The Discord application the presence is shown as
+  pub client_id: String,
+  /// Asset keys uploaded to the application
+  pub large_image: Option<String>,
+  pub playing_image: Option<String>,
+  pub paused_image: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CreditSettings {
+  /// Artist names that are never split apart, e.g. "Simon & Garfunkel"
+  pub exceptions: Vec<String>,
+}
+
+/// Read at startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkSettings {
+  /// Probed to decide whether we are online, e.g. behind a firewall that
+  /// blocks the defaults
+  pub probe_targets: Vec<ProbeTarget>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct CropRect {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingsError {
+  /// Dotted path to the offending field, e.g. `window.width`
+  pub field: String,
+  pub message: String,
+}
+
+impl fmt::Display for SettingsError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}: {}", self.field, self.message) }
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self {
+      version: CURRENT_VERSION,
+      window: WindowSettings::default(),
+      appearance: AppearanceSettings::default(),
+      player: PlayerSettings::default(),
+      visibility: VisibilitySettings::default(),
+      volume: VolumeSettings::default(),
+      sleep_timer: SleepTimerSettings::default(),
+      ducking: DuckingSettings::default(),
+      autostart: false,
+      hotkeys: default_hotkeys(),
+      lyrics: LyricsSettings::default(),
+      notifications: NotificationSettings::default(),
+      now_playing: None,
+      discord: None,
+      credits: CreditSettings::default(),
+      network: NetworkSettings::default(),
+    }
+  }
+}
+
+impl Default for WindowSettings {
+  fn default() -> Self {
+    Self {
+      width: 364,
+      height: 84,
+      expanded_height: 364,
+      mode: DisplayMode::Compact,
+      auto_expand: false,
+      snap: SnapMode::Off,
+      snap_distance: 16,
+    }
+  }
+}
+
+impl Default for AppearanceSettings {
+  fn default() -> Self { Self { fallback_color: (92, 80, 160) } }
+}
+
+impl Default for PlayerSettings {
+  fn default() -> Self {
+    Self {
+      app_ids: vec!["Spotify.exe".into()],
+      // Spotify covers come with a watermark on the left
+      cover_crop: Some(CropRect {
+        x: 34,
+        y: 1,
+        width: 233,
+        height: 233,
+      }),
+    }
+  }
+}
+
+impl Default for VisibilitySettings {
+  fn default() -> Self {
+    Self {
+      hide_when_paused_secs: None,
+      hide_on_disconnect: true,
+      hide_in_fullscreen: true,
+    }
+  }
+}
+
+impl Default for VolumeSettings {
+  fn default() -> Self {
+    Self {
+      step_percent: 5,
+      curve: VolumeCurve::Linear,
+      max_percent: 100,
+    }
+  }
+}
+
+impl Default for SleepTimerSettings {
+  fn default() -> Self { Self { fade_secs: 30 } }
+}
+
+impl Default for DuckingSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      mode: DuckMode::Lower,
+      lower_to_percent: 20,
+      allow: vec![],
+      deny: vec![],
+      restore_delay_secs: 2,
+    }
+  }
+}
+
+impl Default for NotificationSettings {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      apps: BTreeMap::new(),
+      debounce_ms: 1500,
+    }
+  }
+}
+
+impl Default for CreditSettings {
+  fn default() -> Self {
+    Self {
+      exceptions: DEFAULT_EXCEPTIONS.iter().map(|name| name.to_string()).collect(),
+    }
+  }
+}
+
+impl Default for NetworkSettings {
+  fn default() -> Self {
+    Self {
+      probe_targets: vec![
+        ProbeTarget::Tcp("clients3.google.com:80".into()),
+        ProbeTarget::Tcp("detectportal.firefox.com:80".into()),
+      ],
+    }
+  }
+}
+
+impl Default for NowPlayingSettings {
+  fn default() -> Self {
+    Self {
+      directory: String::new(),
+      templates: BTreeMap::new(),
+      cover_art: Some("cover.png".into()),
+    }
+  }
+}
+
+impl Settings {
+  pub fn validate(&self) -> Result<(), Vec<SettingsError>> {
+    let mut errors = vec![];
+    let mut error = |field: &str, message: String| {
+      errors.push(SettingsError {
+        field: field.into(),
+        message,
+      })
+    };
+
+    if self.version != CURRENT_VERSION {
+      error("version", format!("Expected {CURRENT_VERSION}, got {}", self.version));
+    }
+
+    if !(120..=1920).contains(&self.window.width) {
+      error("window.width", format!("{} is outside of 120 to 1920", self.window.width));
+    }
+    if !(40..=1080).contains(&self.window.height) {
+      error("window.height", format!("{} is outside of 40 to 1080", self.window.height));
+    }
+    if !(self.window.height..=1080).contains(&self.window.expanded_height) {
+      error(
+        "window.expandedHeight",
+        format!("{} is outside of {} to 1080", self.window.expanded_height, self.window.height),
+      );
+    }
+
+    if self.window.snap_distance > 200 {
+      error("window.snapDistance", format!("{} is more than 200", self.window.snap_distance));
+    }
+
+    if self.player.app_ids.iter().all(|app_id| app_id.trim().is_empty()) {
+      error("player.appIds", "At least one app id is needed".into());
+    }
+
+    if let Some(crop) = self.player.cover_crop {
+      if crop.width == 0 || crop.height == 0 {
+        error("player.coverCrop", "Width and height can't be 0".into());
+      }
+    }
+
+    if !(1..=50).contains(&self.volume.step_percent) {
+      error("volume.stepPercent", format!("{} is outside of 1 to 50", self.volume.step_percent));
+    }
+    if !(1..=100).contains(&self.volume.max_percent) {
+      error("volume.maxPercent", format!("{} is outside of 1 to 100", self.volume.max_percent));
+    }
+
+    if self.sleep_timer.fade_secs > 600 {
+      error("sleepTimer.fadeSecs", format!("{} is more than 600", self.sleep_timer.fade_secs));
+    }
+
+    if self.ducking.lower_to_percent > 100 {
+      error("ducking.lowerToPercent", format!("{} is more than 100", self.ducking.lower_to_percent));
+    }
+    if self.ducking.restore_delay_secs > 60 {
+      error("ducking.restoreDelaySecs", format!("{} is more than 60", self.ducking.restore_delay_secs));
+    }
+
+    if self.notifications.debounce_ms > 60_000 {
+      error("notifications.debounceMs", format!("{} is more than 60000", self.notifications.debounce_ms));
+    }
+
+    if let Some(now_playing) = &self.now_playing {
+      if now_playing.directory.trim().is_empty() {
+        error("nowPlaying.directory", "A directory is needed".into());
+      }
+    }
+
+    if let Some(discord) = &self.discord {
+      if discord.client_id.is_empty() || !discord.client_id.chars().all(|c| c.is_ascii_digit()) {
+        error("discord.clientId", format!("'{}' is not an application id", discord.client_id));
+      }
+    }
+
+    if self.network.probe_targets.is_empty() {
+      error("network.probeTargets", "At least one target is needed".into());
+    }
+
+    if let Err(hotkey_errors) = crate::hotkeys::config::validate(&self.hotkeys) {
+      errors.extend(hotkey_errors);
+    }
+
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(errors)
+    }
+  }
+
+  pub fn is_player(&self, app_id: &str) -> bool { self.player.app_ids.iter().any(|id| id == app_id) }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn defaults_are_valid() { assert_eq!(Settings::default().validate(), Ok(())); }
+
+  #[test]
+  fn reports_every_invalid_field() {
+    let mut settings = Settings::default();
+    settings.window.width = 10;
+    settings.player.app_ids = vec![" ".into()];
+    settings.player.cover_crop = Some(CropRect {
+      x: 0,
+      y: 0,
+      width: 0,
+      height: 100,
+    });
+    settings.discord = Some(DiscordSettings {
+      client_id: "my-app".into(),
+      ..Default::default()
+    });
+
+    let errors = settings.validate().unwrap_err().iter().map(SettingsError::to_string).collect::<Vec<_>>();
+    assert_eq!(errors, [
+      "window.width: 10 is outside of 120 to 1920",
+      "player.appIds: At least one app id is needed",
+      "player.coverCrop: Width and height can't be 0",
+      "discord.clientId: 'my-app' is not an application id",
+    ]);
+  }
+
+  #[test]
+  fn fills_in_missing_fields() {
+    let settings: Settings = serde_json::from_str(r#"{"version": 1, "window": {"width": 400}}"#).unwrap();
+
+    assert_eq!(settings.window, WindowSettings {
+      width: 400,
+      ..WindowSettings::default()
+    });
+    assert_eq!(settings.player, PlayerSettings::default());
+  }
+}
@@ -0,0 +1,270 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::anyhow;
+use serde_json::Value;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::migrate::migrate;
+use super::schema::{Settings, SettingsError};
+use crate::utils::fs::{backup_path, write_atomic};
+
+/// Why `SettingsStore::update` left the settings as they were
+#[derive(Debug)]
+pub enum UpdateError {
+  Invalid(Vec<SettingsError>),
+  /// The file couldn't be written, the change would be gone on the next start
+  Save(anyhow::Error),
+}
+
+impl fmt::Display for UpdateError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Invalid(errors) => write!(f, "{}", invalid(errors.clone())),
+      Self::Save(err) => write!(f, "Unable to save the settings: {err:#}"),
+    }
+  }
+}
+
+impl std::error::Error for UpdateError {}
+
+impl From<Vec<SettingsError>> for UpdateError {
+  fn from(errors: Vec<SettingsError>) -> Self { Self::Invalid(errors) }
+}
+
+/// Owns `settings.json` and publishes every change to it, whether it came
+/// from the app or from someone editing the file
+pub struct SettingsStore {
+  path: PathBuf,
+  settings: watch::Sender<Settings>,
+}
+
+impl SettingsStore {
+  /// A missing file means the defaults. Files from older versions are
+  /// migrated and written back, the original is kept next to it. So is a
+  /// file that can't be used, the defaults apply until it's fixed or the
+  /// settings are changed from the app
+  pub fn load(path: PathBuf) -> Self {
+    let settings = read_valid(&path).unwrap_or_else(|err| {
+      println!("[Settings] load | Error: {:?}", err);
+      if let Err(err) = std::fs::copy(&path, backup_path(&path)) {
+        println!("[Settings] backup | Error: {:?}", err);
+      }
+
+      Settings::default()
+    });
+
+    Self {
+      path,
+      settings: watch::channel(settings).0,
+    }
+  }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+
+  pub fn get(&self) -> Settings { self.settings.borrow().clone() }
+
+  pub fn subscribe(&self) -> watch::Receiver<Settings> { self.settings.subscribe() }
+
+  /// Validates, stores and publishes `settings`, nothing changes if any
+  /// field is invalid or the file can't be written
+  pub fn update(&self, settings: Settings) -> Result<Settings, UpdateError> {
+    settings.validate()?;
+    save(&self.path, &settings).map_err(UpdateError::Save)?;
+
+    self.publish(settings.clone());
+    Ok(settings)
+  }
+
+  /// Picks up the file as it is now, invalid edits are rejected and the
+  /// current settings stay
+  pub fn reload(&self) -> anyhow::Result<bool> { Ok(self.publish(read_valid(&self.path)?)) }
+
+  /// Checks the file for outside edits every `interval`
+  pub fn watch(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+    println!("[Settings] watch");
+
+    let store = self.clone();
+    let mut last_modified = modified(&store.path);
+
+    tokio::spawn(async move {
+      loop {
+        tokio::time::sleep(interval).await;
+
+        let modified = modified(&store.path);
+        if modified == last_modified {
+          continue;
+        }
+        last_modified = modified;
+
+        match store.reload() {
+          Ok(true) => println!("[Settings] reloaded"),
+          Ok(false) => {},
+          Err(err) => println!("[Settings] reload | Error: {:?}", err),
+        }
+      }
+    })
+  }
+
+  /// Returns whether anything changed
+  fn publish(&self, settings: Settings) -> bool {
+    self.settings.send_if_modified(|current| {
+      if *current == settings {
+        return false;
+      }
+
+      *current = settings;
+      true
+    })
+  }
+}
+
+fn read(path: &Path) -> anyhow::Result<Settings> {
+  if !path.exists() {
+    return Ok(Settings::default());
+  }
+
+  let text = std::fs::read_to_string(path)?;
+  let mut document: Value = serde_json::from_str(&text)?;
+  let migrated = migrate(&mut document)?;
+  let settings: Settings = serde_json::from_value(document)?;
+
+  if migrated {
+    std::fs::write(backup_path(path), text)?;
+    save(path, &settings)?;
+  }
+
+  Ok(settings)
+}
+
+fn read_valid(path: &Path) -> anyhow::Result<Settings> {
+  let settings = read(path)?;
+  settings.validate().map_err(invalid)?;
+  Ok(settings)
+}
+
+fn save(path: &Path, settings: &Settings) -> anyhow::Result<()> {
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)?;
+  }
+
+  write_atomic(path, serde_json::to_string_pretty(settings)?.as_bytes())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> { std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok() }
+
+fn invalid(errors: Vec<SettingsError>) -> anyhow::Error {
+  let messages = errors.iter().map(SettingsError::to_string).collect::<Vec<_>>();
+  anyhow!("Invalid settings\n{}", messages.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("media-widget-settings-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  #[test]
+  fn migrates_and_keeps_the_original() {
+    let dir = temp_dir("migrate");
+    let path = dir.join("settings.json");
+    std::fs::write(&path, r#"{"window": {"width": 400, "height": 100}}"#).unwrap();
+
+    let store = SettingsStore::load(path.clone());
+    assert_eq!((store.get().version, store.get().window.width), (1, 400));
+
+    let saved: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(saved["version"], 1);
+    assert_eq!(saved["player"]["appIds"][0], "Spotify.exe");
+    assert!(dir.join("settings.json.bak").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn falls_back_to_the_defaults() {
+    let dir = temp_dir("broken");
+    let path = dir.join("settings.json");
+    let broken = r#"{"version": 1, "window": {"width": 40"#;
+    std::fs::write(&path, broken).unwrap();
+
+    let store = SettingsStore::load(path.clone());
+    assert_eq!(store.get(), Settings::default());
+    assert_eq!(std::fs::read_to_string(dir.join("settings.json.bak")).unwrap(), broken);
+
+    // Left alone until the settings change
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), broken);
+    store.update(Settings::default()).unwrap();
+    assert_eq!(SettingsStore::load(path).get(), Settings::default());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn validates_updates_and_outside_edits() {
+    let dir = temp_dir("update");
+    let path = dir.join("settings.json");
+    let store = SettingsStore::load(path.clone());
+    let mut changes = store.subscribe();
+
+    let mut settings = store.get();
+    settings.window.height = 0;
+    assert!(matches!(store.update(settings.clone()), Err(UpdateError::Invalid(errors)) if errors[0].field == "window.height"));
+    assert!(!changes.has_changed().unwrap());
+
+    settings.window.height = 120;
+    store.update(settings.clone()).unwrap();
+    assert_eq!(changes.borrow_and_update().window.height, 120);
+    assert_eq!(SettingsStore::load(path.clone()).get(), settings);
+
+    // Saving the same settings again isn't a change
+    assert!(!store.reload().unwrap());
+
+    std::fs::write(&path, r#"{"version": 1, "window": {"width": 5}}"#).unwrap();
+    assert!(store.reload().unwrap_err().to_string().contains("window.width: 5 is outside of 120 to 1920"));
+    assert_eq!(store.get(), settings);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn reports_settings_it_cannot_save() {
+    let dir = temp_dir("unsaved");
+    // A file where the settings' directory should be
+    std::fs::write(dir.join("config"), "").unwrap();
+    let store = SettingsStore::load(dir.join("config").join("settings.json"));
+    let changes = store.subscribe();
+
+    let mut settings = store.get();
+    settings.autostart = true;
+    assert!(matches!(store.update(settings), Err(UpdateError::Save(_))));
+    assert!(!changes.has_changed().unwrap());
+    assert!(!store.get().autostart);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[tokio::test]
+  async fn watches_the_file() {
+    let dir = temp_dir("watch");
+    let path = dir.join("settings.json");
+    let store = SettingsStore::load(path.clone()).arced();
+    let mut changes = store.subscribe();
+    let watcher = store.watch(Duration::from_millis(10));
+
+    std::fs::write(&path, r#"{"version": 1, "appearance": {"fallbackColor": [1, 2, 3]}}"#).unwrap();
+    tokio::time::timeout(Duration::from_secs(2), changes.changed()).await.unwrap().unwrap();
+    assert_eq!(changes.borrow().appearance.fallback_color, (1, 2, 3));
+
+    watcher.abort();
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
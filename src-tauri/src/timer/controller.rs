@@ -0,0 +1,284 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use super::countdown::{Countdown, SleepAfter, Step, TimerStatus};
+use crate::control::state::ControlState;
+use crate::media::lib::{MediaEvent, MediaPlaybackData};
+use crate::router::media::{MediaControl, Method};
+use crate::settings::schema::Settings;
+use crate::utils::time::now_ms;
+
+/// How often a running timer checks the time, often enough for the fade
+/// to sound smooth
+const TICK: Duration = Duration::from_millis(250);
+
+#[derive(Default)]
+struct TimerState {
+  player: ControlState,
+  countdown: Option<Countdown>,
+  /// The volume from before the fade, put back once the player paused
+  restore_after_pause: Option<f32>,
+}
+
+/// Pauses the player once the sleep timer goes off, fading it out first
+pub struct SleepTimer {
+  control: Arc<dyn MediaControl>,
+  state: Arc<Mutex<TimerState>>,
+  status: watch::Sender<Option<TimerStatus>>,
+  /// Wakes the task up when a timer starts
+  started: Arc<Notify>,
+  task: JoinHandle<()>,
+}
+
+impl SleepTimer {
+  pub fn spawn(
+    control: Arc<dyn MediaControl>,
+    mut events: broadcast::Receiver<MediaEvent>,
+    settings: watch::Receiver<Settings>,
+  ) -> Self {
+    println!("[SleepTimer] spawn");
+
+    let state = Arc::new(Mutex::new(TimerState::default()));
+    let status = watch::channel(None).0;
+    let started = Arc::new(Notify::new());
+
+    let task = tokio::spawn({
+      let control = control.clone();
+      let state = state.clone();
+      let status = status.clone();
+      let started = started.clone();
+
+      async move {
+        // Steps only go out on ticks. The fade changes the volume, which
+        // comes back as an event, and must not set it again right away
+        let mut ticks = tokio::time::interval(TICK);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+          let running = state.lock().unwrap().countdown.is_some();
+
+          tokio::select! {
+            event = events.recv() => match event {
+              Ok(event) => {
+                let mut state = state.lock().unwrap();
+                state.player.handle(&event, now_ms());
+                if let Some(countdown) = state.countdown.as_mut() {
+                  countdown.handle(&event);
+                }
+
+                if let MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing: false }) = event {
+                  if let Some(volume) = state.restore_after_pause.take() {
+                    restore(control.as_ref(), volume);
+                  }
+                }
+              },
+              Err(RecvError::Lagged(_)) => continue,
+              Err(RecvError::Closed) => break,
+            },
+            _ = started.notified() => {},
+            _ = ticks.tick(), if running => {
+              let fade_ms = settings.borrow().sleep_timer.fade_secs as u64 * 1000;
+              tick(control.as_ref(), &state, &status, fade_ms);
+            },
+          }
+        }
+      }
+    });
+
+    Self {
+      control,
+      state,
+      status,
+      started,
+      task,
+    }
+  }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+
+  /// Replaces any running timer
+  pub fn start(&self, after: SleepAfter) -> Result<TimerStatus, String> {
+    after.validate()?;
+
+    let now = now_ms();
+    let mut state = self.state.lock().unwrap();
+    let player = state.player.status(now);
+    let countdown = Countdown::start(after, &player, now);
+    let status = countdown.status(&player, now);
+
+    if let Some(volume) = state.countdown.replace(countdown).and_then(Countdown::cancel) {
+      restore(self.control.as_ref(), volume);
+    }
+    drop(state);
+
+    println!("[SleepTimer] start | {:?}", after);
+    publish(&self.status, Some(status));
+    self.started.notify_one();
+
+    Ok(status)
+  }
+
+  pub fn cancel(&self) {
+    let Some(countdown) = self.state.lock().unwrap().countdown.take() else {
+      return;
+    };
+
+    println!("[SleepTimer] cancel");
+    if let Some(volume) = countdown.cancel() {
+      restore(self.control.as_ref(), volume);
+    }
+    publish(&self.status, None);
+  }
+
+  /// `None` without a running timer
+  pub fn remaining(&self) -> Option<TimerStatus> { *self.status.borrow() }
+
+  pub fn subscribe(&self) -> watch::Receiver<Option<TimerStatus>> { self.status.subscribe() }
+}
+
+impl Drop for SleepTimer {
+  fn drop(&mut self) { self.task.abort(); }
+}
+
+fn tick(control: &dyn MediaControl, state: &Mutex<TimerState>, status: &watch::Sender<Option<TimerStatus>>, fade_ms: u64) {
+  let now = now_ms();
+  let mut state = state.lock().unwrap();
+  let player = state.player.status(now);
+  let Some(countdown) = state.countdown.as_mut() else {
+    return;
+  };
+
+  match countdown.step(&player, fade_ms, now) {
+    Step::Wait => {},
+    Step::SetVolume(volume) => {
+      if let Err(err) = control.invoke(Method::SetVolume(volume)) {
+        println!("[SleepTimer] fade | Error: {}", err);
+      }
+    },
+    Step::Finish { restore: volume } => {
+      println!("[SleepTimer] pausing");
+      let paused = control.invoke(Method::Pause);
+      if let Err(err) = &paused {
+        println!("[SleepTimer] pause | Error: {}", err);
+      }
+
+      // Pausing doesn't wait for the player, the volume only goes back once
+      // it reports being paused so the end of the track isn't loud again
+      match volume {
+        Some(volume) if paused.is_ok() && player.is_playing => state.restore_after_pause = Some(volume),
+        Some(volume) => restore(control, volume),
+        None => {},
+      }
+
+      state.countdown = None;
+      publish(status, None);
+      return;
+    },
+  }
+
+  publish(status, Some(countdown.status(&player, now)));
+}
+
+fn restore(control: &dyn MediaControl, volume: f32) {
+  if let Err(err) = control.invoke(Method::SetVolume(volume)) {
+    println!("[SleepTimer] restore | Error: {}", err);
+  }
+}
+
+fn publish(sender: &watch::Sender<Option<TimerStatus>>, status: Option<TimerStatus>) {
+  sender.send_if_modified(|current| {
+    if *current == status {
+      return false;
+    }
+
+    *current = status;
+    true
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::{MediaSessionData, MediaVolumeData};
+  use crate::router::media::stub::RecordingControl;
+  use crate::utils::wait::until;
+
+  fn volume(timer: &SleepTimer) -> Option<f32> { timer.state.lock().unwrap().player.status(now_ms()).volume }
+
+  fn last_invocation(control: &RecordingControl) -> Option<Method> { control.invocations.lock().unwrap().last().cloned() }
+
+  #[tokio::test]
+  async fn fades_out_and_pauses() {
+    let control = Arc::new(RecordingControl::default());
+    let (sender, events) = broadcast::channel(16);
+    let mut settings = Settings::default();
+    settings.sleep_timer.fade_secs = 1;
+    let (_settings, settings) = watch::channel(settings);
+
+    let timer = SleepTimer::spawn(control.clone(), events, settings);
+    let mut countdown = timer.subscribe();
+
+    sender.send(MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A"]))).unwrap();
+    sender.send(MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing: true })).unwrap();
+    sender.send(MediaEvent::VolumeChanged(MediaVolumeData::fixture(0.8))).unwrap();
+    until(|| volume(&timer) == Some(0.8)).await;
+
+    assert_eq!(timer.start(SleepAfter::Duration { seconds: 0 }), Err("The duration can't be 0".into()));
+    let status = timer.start(SleepAfter::Duration { seconds: 1 }).unwrap();
+    assert_eq!(status.remaining_secs, Some(1));
+    assert_eq!(timer.remaining(), Some(status));
+
+    while countdown.changed().await.is_ok() {
+      if countdown.borrow().is_none() {
+        break;
+      }
+    }
+
+    // Still playing until the player says otherwise
+    assert_eq!(last_invocation(&control), Some(Method::Pause));
+    sender.send(MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing: false })).unwrap();
+    until(|| last_invocation(&control) == Some(Method::SetVolume(0.8))).await;
+
+    let invocations = control.invocations.lock().unwrap().clone();
+    let (last, fade) = invocations.split_last().unwrap();
+    assert_eq!(*last, Method::SetVolume(0.8));
+    assert_eq!(fade.last(), Some(&Method::Pause));
+    // Only ever lower
+    assert!(fade[..fade.len() - 1]
+      .windows(2)
+      .all(|pair| matches!(pair, [Method::SetVolume(a), Method::SetVolume(b)] if b < a)));
+    assert_eq!(timer.remaining(), None);
+  }
+
+  #[tokio::test]
+  async fn cancelling_restores_the_volume() {
+    let control = Arc::new(RecordingControl::default());
+    let (sender, events) = broadcast::channel(16);
+    let (_settings, settings) = watch::channel(Settings::default());
+    let timer = SleepTimer::spawn(control.clone(), events, settings);
+
+    sender.send(MediaEvent::VolumeChanged(MediaVolumeData::fixture(0.8))).unwrap();
+    until(|| volume(&timer) == Some(0.8)).await;
+
+    // Shorter than the fade, so it starts right away
+    timer.start(SleepAfter::Duration { seconds: 10 }).unwrap();
+    until(|| timer.remaining().is_some_and(|status| status.fading)).await;
+
+    // The player echoing the fade back doesn't set the volume again
+    let before = control.invocations.lock().unwrap().len();
+    for level in [0.79, 0.78, 0.77, 0.76] {
+      sender.send(MediaEvent::VolumeChanged(MediaVolumeData::fixture(level))).unwrap();
+    }
+    until(|| volume(&timer) == Some(0.76)).await;
+    assert!(control.invocations.lock().unwrap().len() <= before + 1);
+
+    timer.cancel();
+    assert_eq!(timer.remaining(), None);
+    assert_eq!(last_invocation(&control), Some(Method::SetVolume(0.8)));
+  }
+}
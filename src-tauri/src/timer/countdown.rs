@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+
+use crate::control::protocol::Status;
+use crate::media::lib::MediaEvent;
+
+/// When the sleep timer goes off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, rspc::Type)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum SleepAfter {
+  Duration { seconds: u32 },
+  EndOfTrack,
+  /// The current track counts as the first one
+  AfterNTracks { tracks: u32 },
+}
+
+impl SleepAfter {
+  pub fn validate(&self) -> Result<(), String> {
+    match self {
+      SleepAfter::Duration { seconds: 0 } => Err("The duration can't be 0".into()),
+      SleepAfter::AfterNTracks { tracks: 0 } => Err("At least one track is needed".into()),
+      _ => Ok(()),
+    }
+  }
+}
+
+/// What the countdown reports to the UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerStatus {
+  pub after: SleepAfter,
+  /// Rounded up, `None` until the end can be told, e.g. while tracks are
+  /// left before the last one
+  pub remaining_secs: Option<u32>,
+  /// Counting the current one, for the track modes
+  pub tracks_left: Option<u32>,
+  /// Whether the volume is being lowered
+  pub fading: bool,
+}
+
+/// What to do with the player on this tick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+  Wait,
+  SetVolume(f32),
+  /// Pause, then put the volume back to where it was before the fade
+  Finish { restore: Option<f32> },
+}
+
+/// A running sleep timer. Time is unix ms, the track and position come
+/// from the media events through `Status`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Countdown {
+  after: SleepAfter,
+  deadline: Option<i64>,
+  tracks_left: u32,
+  /// Title and artists, `MediaPropertiesChanged` also fires for the same
+  /// track when only the cover or the controls change
+  track: Option<(String, Vec<String>)>,
+  /// The volume before fading, once it started
+  original_volume: Option<f32>,
+}
+
+impl Countdown {
+  pub fn start(after: SleepAfter, status: &Status, now: i64) -> Self {
+    let (deadline, tracks_left) = match after {
+      SleepAfter::Duration { seconds } => (Some(now + seconds as i64 * 1000), 0),
+      SleepAfter::EndOfTrack => (None, 1),
+      SleepAfter::AfterNTracks { tracks } => (None, tracks),
+    };
+
+    Self {
+      after,
+      deadline,
+      tracks_left,
+      track: status.title.clone().map(|title| (title, status.artists.clone())),
+      original_volume: None,
+    }
+  }
+
+  /// Counts the tracks that started since
+  pub fn handle(&mut self, event: &MediaEvent) {
+    let MediaEvent::MediaPropertiesChanged(data) = event else {
+      return;
+    };
+
+    let track = (data.title.clone(), data.artists.clone());
+    if self.track.as_ref().is_some_and(|current| *current != track) {
+      self.tracks_left = self.tracks_left.saturating_sub(1);
+    }
+    self.track = Some(track);
+  }
+
+  pub fn remaining_ms(&self, status: &Status, now: i64) -> Option<u64> {
+    if let Some(deadline) = self.deadline {
+      return Some((deadline - now).max(0) as u64);
+    }
+
+    match self.tracks_left {
+      0 => Some(0),
+      1 => Some(status.duration_ms?.saturating_sub(status.position_ms?)),
+      _ => None,
+    }
+  }
+
+  pub fn status(&self, status: &Status, now: i64) -> TimerStatus {
+    TimerStatus {
+      after: self.after,
+      remaining_secs: self.remaining_ms(status, now).map(|remaining| remaining.div_ceil(1000) as u32),
+      tracks_left: self.deadline.is_none().then_some(self.tracks_left),
+      fading: self.original_volume.is_some(),
+    }
+  }
+
+  /// Lowers the volume along the last `fade_ms`, in proportion to the time
+  /// left, and finishes once it's up
+  pub fn step(&mut self, status: &Status, fade_ms: u64, now: i64) -> Step {
+    let Some(remaining) = self.remaining_ms(status, now) else {
+      return Step::Wait;
+    };
+
+    if remaining == 0 {
+      return Step::Finish {
+        restore: self.original_volume.take(),
+      };
+    }
+
+    if remaining >= fade_ms {
+      return Step::Wait;
+    }
+
+    // Can't fade from a volume we don't know
+    let Some(original) = self.original_volume.or(status.volume) else {
+      return Step::Wait;
+    };
+    self.original_volume = Some(original);
+
+    Step::SetVolume(original * remaining as f32 / fade_ms as f32)
+  }
+
+  /// The volume to go back to when stopped before the end
+  pub fn cancel(self) -> Option<f32> { self.original_volume }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::MediaSessionData;
+
+  fn playing(title: &str, position_ms: u64, duration_ms: u64) -> Status {
+    Status {
+      title: Some(title.into()),
+      artists: vec!["A".into()],
+      is_playing: true,
+      position_ms: Some(position_ms),
+      duration_ms: Some(duration_ms),
+      volume: Some(0.8),
+      ..Status::default()
+    }
+  }
+
+  fn track(title: &str) -> MediaEvent { MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture(title, &["A"])) }
+
+  #[test]
+  fn fades_out_over_the_last_seconds() {
+    let status = playing("Song", 0, 600_000);
+    let mut countdown = Countdown::start(SleepAfter::Duration { seconds: 60 }, &status, 0);
+
+    assert_eq!(countdown.step(&status, 30_000, 10_000), Step::Wait);
+    assert_eq!(countdown.status(&status, 10_000).remaining_secs, Some(50));
+
+    assert_eq!(countdown.step(&status, 30_000, 45_000), Step::SetVolume(0.4));
+    // Our own changes don't move where it fades from
+    let lowered = Status {
+      volume: Some(0.4),
+      ..status.clone()
+    };
+    assert_eq!(countdown.step(&lowered, 30_000, 54_000), Step::SetVolume(0.16));
+    assert!(countdown.status(&lowered, 54_000).fading);
+
+    assert_eq!(countdown.step(&lowered, 30_000, 60_000), Step::Finish { restore: Some(0.8) });
+  }
+
+  #[test]
+  fn counts_tracks() {
+    let mut countdown = Countdown::start(SleepAfter::AfterNTracks { tracks: 2 }, &playing("One", 0, 200_000), 0);
+    assert_eq!(countdown.status(&playing("One", 10_000, 200_000), 0).remaining_secs, None);
+
+    countdown.handle(&track("One"));
+    countdown.handle(&track("Two"));
+    countdown.handle(&track("Two"));
+
+    let last = playing("Two", 190_500, 200_000);
+    assert_eq!(countdown.status(&last, 0), TimerStatus {
+      after: SleepAfter::AfterNTracks { tracks: 2 },
+      remaining_secs: Some(10),
+      tracks_left: Some(1),
+      fading: false,
+    });
+    assert_eq!(countdown.step(&last, 30_000, 0), Step::SetVolume(0.8 * 9_500.0 / 30_000.0));
+
+    countdown.handle(&track("Three"));
+    assert_eq!(countdown.step(&playing("Three", 0, 200_000), 30_000, 0), Step::Finish {
+      restore: Some(0.8)
+    });
+  }
+
+  #[test]
+  fn ends_with_the_track() {
+    // Started before anything was playing
+    let mut countdown = Countdown::start(SleepAfter::EndOfTrack, &Status::default(), 0);
+    countdown.handle(&track("One"));
+
+    let status = playing("One", 100_000, 200_000);
+    assert_eq!(countdown.status(&status, 0).remaining_secs, Some(100));
+    assert_eq!(countdown.step(&status, 30_000, 0), Step::Wait);
+    assert_eq!(countdown.clone().cancel(), None);
+
+    countdown.handle(&track("Two"));
+    assert_eq!(countdown.step(&status, 30_000, 0), Step::Finish { restore: None });
+  }
+}
@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use super::menu::{self, TrayState};
+use crate::media::lib::MediaEvent;
+use crate::router::media::{MediaControl, Method};
+use crate::utils::opener::Opener;
+use crate::utils::window::toggle_window;
+
+/// Keeps the tray menu and tooltip in sync with the current track, and
+/// carries out the menu's actions
+pub struct TrayController {
+  control: Arc<dyn MediaControl>,
+  opener: Arc<dyn Opener>,
+  /// Opened by "Settings", that's where every config file lives
+  config_dir: PathBuf,
+  state: Mutex<TrayState>,
+  always_on_top: AtomicBool,
+}
+
+impl TrayController {
+  pub fn new(control: Arc<dyn MediaControl>, opener: Arc<dyn Opener>, config_dir: PathBuf, always_on_top: bool) -> Self {
+    Self {
+      control,
+      opener,
+      config_dir,
+      state: Mutex::new(TrayState::new()),
+      always_on_top: AtomicBool::new(always_on_top),
+    }
+  }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+
+  pub fn menu(&self) -> tauri::SystemTrayMenu {
+    menu::build(&self.state.lock().unwrap(), self.always_on_top.load(Ordering::Relaxed))
+  }
+
+  pub fn spawn<R: Runtime>(self: &Arc<Self>, app: AppHandle<R>, mut events: broadcast::Receiver<MediaEvent>) -> JoinHandle<()> {
+    let controller = self.clone();
+
+    tokio::spawn(async move {
+      loop {
+        let event = match events.recv().await {
+          Ok(event) => event,
+          Err(RecvError::Lagged(_)) => continue,
+          Err(RecvError::Closed) => break,
+        };
+
+        if !controller.state.lock().unwrap().handle(&event) {
+          continue;
+        }
+
+        if let Err(err) = controller.refresh(&app) {
+          println!("[Tray] Error: {:?}", err);
+        }
+      }
+    })
+  }
+
+  fn refresh<R: Runtime>(&self, app: &AppHandle<R>) -> tauri::Result<()> {
+    let state = self.state.lock().unwrap().clone();
+    let tray = app.tray_handle();
+
+    tray.get_item(menu::TRACK).set_title(state.header())?;
+    tray.get_item(menu::PLAY_PAUSE).set_title(state.play_pause_title())?;
+    for id in [menu::PLAY_PAUSE, menu::NEXT, menu::PREVIOUS] {
+      tray.get_item(id).set_enabled(state.has_track())?;
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    tray.set_tooltip(&state.tooltip())?;
+
+    Ok(())
+  }
+
+  pub fn on_click<R: Runtime>(&self, app: &AppHandle<R>, id: &str) -> anyhow::Result<()> {
+    match id {
+      menu::PLAY_PAUSE if self.state.lock().unwrap().is_playing() => self.control.invoke(Method::Pause),
+      menu::PLAY_PAUSE => self.control.invoke(Method::Play),
+      menu::NEXT => self.control.invoke(Method::Next),
+      menu::PREVIOUS => self.control.invoke(Method::Previous),
+      menu::TOGGLE_MUTE => self.control.invoke(Method::ToggleMute),
+      menu::TOGGLE_WINDOW => {
+        if let Some(window) = app.get_window("main") {
+          toggle_window(&window)?;
+        }
+        Ok(())
+      },
+      menu::ALWAYS_ON_TOP => {
+        let always_on_top = !self.always_on_top.load(Ordering::Relaxed);
+        if let Some(window) = app.get_window("main") {
+          window.set_always_on_top(always_on_top)?;
+        }
+
+        self.always_on_top.store(always_on_top, Ordering::Relaxed);
+        app.tray_handle().get_item(menu::ALWAYS_ON_TOP).set_selected(always_on_top)?;
+        Ok(())
+      },
+      menu::SETTINGS => {
+        std::fs::create_dir_all(&self.config_dir)?;
+        self.opener.open(&self.config_dir.to_string_lossy())
+      },
+      menu::ABOUT => {
+        let info = app.package_info();
+        tauri::api::dialog::message(
+          app.get_window("main").as_ref(),
+          format!("About {}", info.name),
+          format!(
+            "{} {}\n\nShows and controls what's playing.\n\nSettings are read from {}",
+            info.name,
+            info.version,
+            self.config_dir.display()
+          ),
+        );
+        Ok(())
+      },
+      menu::QUIT => {
+        app.exit(0);
+        Ok(())
+      },
+      _ => Ok(()),
+    }
+  }
+}
@@ -0,0 +1,143 @@
+use tauri::{CustomMenuItem, SystemTrayMenu, SystemTrayMenuItem};
+
+use crate::media::lib::MediaEvent;
+
+pub const TRACK: &str = "track";
+pub const PLAY_PAUSE: &str = "play_pause";
+pub const NEXT: &str = "next";
+pub const PREVIOUS: &str = "previous";
+pub const TOGGLE_MUTE: &str = "toggle_mute";
+pub const TOGGLE_WINDOW: &str = "toggle_window";
+pub const ALWAYS_ON_TOP: &str = "always_on_top";
+pub const SETTINGS: &str = "settings";
+pub const ABOUT: &str = "about";
+pub const QUIT: &str = "quit";
+
+/// Longer headers make the whole menu stretch
+const MAX_HEADER_CHARS: usize = 48;
+/// Windows cuts tooltips off at 127 characters
+const MAX_TOOLTIP_CHARS: usize = 127;
+
+/// What the tray shows about the current track
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrayState {
+  title: Option<String>,
+  artists: Vec<String>,
+  is_playing: bool,
+}
+
+impl TrayState {
+  pub fn new() -> Self { Self::default() }
+
+  /// Whether anything shown in the tray changed
+  pub fn handle(&mut self, event: &MediaEvent) -> bool {
+    let previous = self.clone();
+
+    match event {
+      MediaEvent::MediaPropertiesChanged(data) => {
+        self.title = (!data.title.is_empty()).then(|| data.title.clone());
+        self.artists = data.artists.clone();
+      },
+      MediaEvent::PlaybackInfoChanged(data) => self.is_playing = data.is_playing,
+      MediaEvent::Disconnect(_) => *self = Self::new(),
+      _ => {},
+    }
+
+    *self != previous
+  }
+
+  pub fn is_playing(&self) -> bool { self.is_playing }
+
+  pub fn header(&self) -> String {
+    let Some(title) = &self.title else {
+      return "Nothing playing".into();
+    };
+
+    if self.artists.is_empty() {
+      return fit(title, MAX_HEADER_CHARS);
+    }
+
+    fit(&format!("{} — {}", title, self.artists.join(", ")), MAX_HEADER_CHARS)
+  }
+
+  pub fn play_pause_title(&self) -> &'static str { if self.is_playing { "Pause" } else { "Play" } }
+
+  pub fn has_track(&self) -> bool { self.title.is_some() }
+
+  pub fn tooltip(&self) -> String {
+    let Some(title) = &self.title else {
+      return "Media Widget".into();
+    };
+
+    let mut tooltip = title.clone();
+    if !self.artists.is_empty() {
+      tooltip.push('\n');
+      tooltip.push_str(&self.artists.join(", "));
+    }
+
+    fit(&tooltip, MAX_TOOLTIP_CHARS)
+  }
+}
+
+fn fit(text: &str, max_chars: usize) -> String {
+  if text.chars().count() <= max_chars {
+    return text.to_string();
+  }
+
+  let mut fitted = text.chars().take(max_chars - 1).collect::<String>().trim_end().to_string();
+  fitted.push('…');
+  fitted
+}
+
+pub fn build(state: &TrayState, always_on_top: bool) -> SystemTrayMenu {
+  let mut always_on_top_item = CustomMenuItem::new(ALWAYS_ON_TOP, "Always on top");
+  always_on_top_item.selected = always_on_top;
+
+  SystemTrayMenu::new()
+    .add_item(CustomMenuItem::new(TRACK, state.header()).disabled())
+    .add_native_item(SystemTrayMenuItem::Separator)
+    .add_item(CustomMenuItem::new(PLAY_PAUSE, state.play_pause_title()))
+    .add_item(CustomMenuItem::new(NEXT, "Next"))
+    .add_item(CustomMenuItem::new(PREVIOUS, "Previous"))
+    .add_item(CustomMenuItem::new(TOGGLE_MUTE, "Mute/Unmute"))
+    .add_native_item(SystemTrayMenuItem::Separator)
+    .add_item(CustomMenuItem::new(TOGGLE_WINDOW, "Show/Hide widget"))
+    .add_item(always_on_top_item)
+    .add_item(CustomMenuItem::new(SETTINGS, "Settings"))
+    .add_item(CustomMenuItem::new(ABOUT, "About"))
+    .add_native_item(SystemTrayMenuItem::Separator)
+    .add_item(CustomMenuItem::new(QUIT, "Quit"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::{MediaPlaybackData, MediaSessionData, MediaVolumeData};
+
+  #[test]
+  fn follows_the_current_track() {
+    let mut state = TrayState::new();
+    assert_eq!((state.header(), state.tooltip()), ("Nothing playing".into(), "Media Widget".into()));
+
+    assert!(state.handle(&MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A", "B"]))));
+    assert_eq!((state.header(), state.tooltip()), ("Song — A, B".into(), "Song\nA, B".into()));
+    assert!(!state.handle(&MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture("Song", &["A", "B"]))));
+
+    assert!(state.handle(&MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing: true })));
+    assert_eq!(state.play_pause_title(), "Pause");
+    assert!(!state.handle(&MediaEvent::VolumeChanged(MediaVolumeData::fixture(0.5))));
+
+    assert!(state.handle(&MediaEvent::Disconnect("Spotify.exe".into())));
+    assert_eq!((state.header(), state.play_pause_title()), ("Nothing playing".into(), "Play"));
+  }
+
+  #[test]
+  fn truncates_long_headers() {
+    let mut state = TrayState::new();
+    let title = "A Very Long Song Title That Goes On (Extended Version)";
+    state.handle(&MediaEvent::MediaPropertiesChanged(MediaSessionData::fixture(title, &["Artist"])));
+
+    assert_eq!(state.header(), "A Very Long Song Title That Goes On (Extended V…");
+    assert_eq!(state.header().chars().count(), MAX_HEADER_CHARS);
+  }
+}
@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use serde::de::DeserializeOwned;
+
+/// `Ok(None)` when there is no file at `path`
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> anyhow::Result<Option<T>> {
+  let contents = match std::fs::read_to_string(path) {
+    Ok(contents) => contents,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+    Err(err) => return Err(err.into()),
+  };
+
+  Ok(Some(serde_json::from_str(&contents)?))
+}
+
+/// Where a file that can't be used is kept, `settings.json.bak` for
+/// `settings.json`
+pub fn backup_path(path: &Path) -> PathBuf {
+  let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+  file_name.push(".bak");
+  path.with_file_name(file_name)
+}
+
+/// Where sockets and locks go. Without `XDG_RUNTIME_DIR` that's a directory
+/// of the user's own, the temp dir is shared by every user
+#[cfg(unix)]
+pub fn runtime_dir() -> PathBuf {
+  use std::os::unix::fs::DirBuilderExt;
+
+  if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+    return dir.into();
+  }
+
+  let dir = std::env::temp_dir().join(format!("media-widget-{}", unsafe { libc::getuid() }));
+  match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+    Err(err) if err.kind() != std::io::ErrorKind::AlreadyExists => println!("[RuntimeDir] {:?} | Error: {:?}", dir, err),
+    _ => {},
+  }
+  dir
+}
+
+/// The temp dir is already per user on Windows
+#[cfg(windows)]
+pub fn runtime_dir() -> PathBuf { std::env::temp_dir() }
+
+/// Pipe names are shared by every session on the machine, so each user
+/// gets their own
+#[cfg(windows)]
+pub fn user_pipe(name: &str) -> PathBuf {
+  let user = std::env::var("USERNAME").unwrap_or_default();
+  PathBuf::from(format!(r"\\.\pipe\{name}-{user}"))
+}
+
+/// Writes to a temporary file next to `path` and renames it over, so readers
+/// never see a half-written file
+pub fn write_atomic(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+  let file_name = path
+    .file_name()
+    .and_then(|name| name.to_str())
+    .ok_or_else(|| anyhow!("Invalid path {:?}", path))?;
+  let temp = path.with_file_name(format!(".{file_name}.tmp"));
+
+  std::fs::write(&temp, contents)?;
+  if let Err(err) = std::fs::rename(&temp, path) {
+    let _ = std::fs::remove_file(&temp);
+    return Err(err.into());
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn replaces_files_atomically() {
+    let dir = std::env::temp_dir().join(format!("media-widget-atomic-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("a.txt");
+    write_atomic(&path, b"first").unwrap();
+    write_atomic(&path, b"second").unwrap();
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+    let files = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect::<Vec<_>>();
+    assert_eq!(files, vec!["a.txt"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn reads_json_when_present() {
+    let path = std::env::temp_dir().join(format!("media-widget-read-json-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(read_json::<Vec<u32>>(&path).unwrap(), None);
+
+    std::fs::write(&path, "[1, 2]").unwrap();
+    assert_eq!(read_json::<Vec<u32>>(&path).unwrap(), Some(vec![1, 2]));
+
+    std::fs::write(&path, "[1,").unwrap();
+    assert!(read_json::<Vec<u32>>(&path).is_err());
+
+    std::fs::remove_file(&path).unwrap();
+  }
+}
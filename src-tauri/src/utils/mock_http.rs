@@ -0,0 +1,103 @@
+//! A tiny HTTP/1.1 server for testing clients against, which records every
+//! request and answers with queued responses (or `200 {}` once they run out).
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+  pub method: String,
+  pub path: String,
+  pub headers: Vec<(String, String)>,
+  pub body: String,
+}
+
+impl MockRequest {
+  pub fn header(&self, name: &str) -> Option<&str> {
+    self
+      .headers
+      .iter()
+      .find(|(key, _)| key.eq_ignore_ascii_case(name))
+      .map(|(_, value)| value.as_str())
+  }
+}
+
+#[derive(Clone)]
+pub struct MockServer {
+  pub url: String,
+  requests: Arc<Mutex<Vec<MockRequest>>>,
+  responses: Arc<Mutex<VecDeque<(u16, String)>>>,
+}
+
+impl MockServer {
+  pub async fn start() -> Self {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server = Self {
+      url: format!("http://{}", listener.local_addr().unwrap()),
+      requests: Default::default(),
+      responses: Default::default(),
+    };
+
+    let (requests, responses) = (server.requests.clone(), server.responses.clone());
+    tokio::spawn(async move {
+      while let Ok((stream, _)) = listener.accept().await {
+        let (requests, responses) = (requests.clone(), responses.clone());
+
+        tokio::spawn(async move {
+          let mut stream = BufReader::new(stream);
+
+          let mut line = String::new();
+          stream.read_line(&mut line).await.unwrap();
+          let mut parts = line.split_whitespace();
+          let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+
+          let mut headers = vec![];
+          loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            let Some((key, value)) = line.trim_end().split_once(':') else {
+              break;
+            };
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+          }
+
+          let length = headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(0);
+          let mut body = vec![0; length];
+          stream.read_exact(&mut body).await.unwrap();
+
+          requests.lock().unwrap().push(MockRequest {
+            method: method.into(),
+            path: path.into(),
+            headers,
+            body: String::from_utf8_lossy(&body).into(),
+          });
+
+          let (status, body) = responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or((200, "{}".into()));
+          let response = format!(
+            "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+          );
+          let _ = stream.get_mut().write_all(response.as_bytes()).await;
+        });
+      }
+    });
+
+    server
+  }
+
+  /// Queues a response for the next request
+  pub fn respond(&self, status: u16, body: &str) { self.responses.lock().unwrap().push_back((status, body.into())); }
+
+  pub fn requests(&self) -> Vec<MockRequest> { self.requests.lock().unwrap().clone() }
+}
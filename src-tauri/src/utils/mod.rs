@@ -1,4 +1,13 @@
 pub mod window;
 pub mod color;
 pub mod thumbnail;
-pub mod spotify;
\ No newline at end of file
+pub mod opener;
+pub mod spotify;
+pub mod spotify_uri;
+pub mod title;
+pub mod time;
+pub mod fs;
+#[cfg(test)]
+pub mod mock_http;
+#[cfg(test)]
+pub mod wait;
\ No newline at end of file
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use anyhow::bail;
+
+/// Hands a URI over to whatever the OS has registered to handle it.
+pub trait Opener: Send + Sync {
+  fn open(&self, uri: &str) -> anyhow::Result<()>;
+}
+
+pub fn system_opener() -> Arc<dyn Opener> {
+  #[cfg(target_os = "windows")]
+  return Arc::new(ShellOpener);
+
+  #[cfg(target_os = "macos")]
+  return Arc::new(CommandOpener::new(vec![vec!["open".into()]]));
+
+  #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+  return Arc::new(CommandOpener::new(vec![
+    vec!["xdg-open".into()],
+    vec!["gio".into(), "open".into()],
+  ]));
+}
+
+#[cfg(target_os = "windows")]
+pub struct ShellOpener;
+
+#[cfg(target_os = "windows")]
+impl Opener for ShellOpener {
+  fn open(&self, uri: &str) -> anyhow::Result<()> {
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::w;
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::Shell::ShellExecuteW;
+    use windows::Win32::UI::WindowsAndMessaging::SW_SHOW;
+
+    let instance = unsafe {
+      ShellExecuteW(
+        HWND(0),
+        w!("open"),
+        &HSTRING::from(uri),
+        PCWSTR::null(),
+        PCWSTR::null(),
+        SW_SHOW,
+      )
+    };
+
+    // Anything above 32 is success, everything else is an error code
+    // https://learn.microsoft.com/en-us/windows/win32/api/shellapi/nf-shellapi-shellexecutew#return-value
+    if instance.0 <= 32 {
+      bail!("ShellExecuteW failed to open '{uri}' (code {})", instance.0);
+    }
+
+    Ok(())
+  }
+}
+
+/// Runs the first launcher program that exists, e.g. `xdg-open <uri>`, falling
+/// back to the next one when it isn't installed.
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+pub struct CommandOpener {
+  candidates: Vec<Vec<String>>,
+}
+
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+impl CommandOpener {
+  pub fn new(candidates: Vec<Vec<String>>) -> Self { Self { candidates } }
+}
+
+impl Opener for CommandOpener {
+  fn open(&self, uri: &str) -> anyhow::Result<()> {
+    for candidate in &self.candidates {
+      let Some((program, args)) = candidate.split_first() else {
+        continue;
+      };
+
+      let status = match std::process::Command::new(program).args(args).arg(uri).status() {
+        Ok(status) => status,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+        Err(err) => bail!("'{program}' failed to open '{uri}': {err}"),
+      };
+
+      if !status.success() {
+        bail!("'{program}' failed to open '{uri}' ({status})");
+      }
+
+      return Ok(());
+    }
+
+    bail!("No launcher found to open '{uri}'")
+  }
+}
+
+#[cfg(test)]
+pub mod stub {
+  use std::sync::Mutex;
+
+  use super::*;
+
+  /// Records every URI instead of launching it
+  #[derive(Default)]
+  pub struct RecordingOpener {
+    pub invocations: Mutex<Vec<String>>,
+    pub fail: bool,
+  }
+
+  impl Opener for RecordingOpener {
+    fn open(&self, uri: &str) -> anyhow::Result<()> {
+      self.invocations.lock().unwrap().push(uri.into());
+
+      if self.fail {
+        bail!("failed to open '{uri}'");
+      }
+
+      Ok(())
+    }
+  }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+  use super::*;
+
+  fn command(args: &[&str]) -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() }
+
+  #[test]
+  fn falls_back_to_the_next_launcher() {
+    let opener = CommandOpener::new(vec![command(&["media-widget-missing-launcher"]), command(&["true"])]);
+
+    assert!(opener.open("spotify:track:4uLU6hMCjMI75M1A2tKUQC").is_ok());
+  }
+
+  #[test]
+  fn surfaces_launch_failures() {
+    let opener = CommandOpener::new(vec![command(&["false"]), command(&["true"])]);
+
+    assert!(opener.open("spotify:track:4uLU6hMCjMI75M1A2tKUQC").is_err());
+  }
+
+  #[test]
+  fn errors_when_no_launcher_exists() {
+    let opener = CommandOpener::new(vec![command(&["media-widget-missing-launcher"]), vec![]]);
+
+    assert!(opener.open("spotify:track:4uLU6hMCjMI75M1A2tKUQC").is_err());
+  }
+}
@@ -1,20 +1,294 @@
-use regex::Regex;
-
-pub fn get_all_artists<'a>(artist: &'a str, title: &'a str) -> anyhow::Result<Vec<String>> {
-    let re = Regex::new(r#"[(\[](?:(f|F)eat\.|ft\.|with|w/|&|and|,)\s(?P<names>.+)[)\]]"#)?;
-    let names = re
-			.captures(title)
-			.and_then(|captures| captures.name("names"))
-			.map(|names| names.as_str())
-			.unwrap_or("");
-
-    let mut names = Regex::new(r#",|&"#)?
-			.split(names)
-			.map(str::trim)
-			.map(str::to_string)
-			.filter(|s| !s.is_empty())
-			.collect::<Vec<_>>();
-
-    names.insert(0, artist.to_string());
-    Ok(names)
-}
\ No newline at end of file
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexBuilder};
+
+/// Band names which contain one of the artist separators and should never be
+/// split, e.g. "Simon & Garfunkel" is one artist, not two.
+pub const DEFAULT_EXCEPTIONS: &[&str] = &[
+  "Above & Beyond",
+  "Angus & Julia Stone",
+  "Belle and Sebastian",
+  "Bob Marley & The Wailers",
+  "Brooks & Dunn",
+  "Chase & Status",
+  "Crosby, Stills & Nash",
+  "Crosby, Stills, Nash & Young",
+  "Derek & The Dominos",
+  "Earth, Wind & Fire",
+  "Echo & the Bunnymen",
+  "Hall & Oates",
+  "Huey Lewis & The News",
+  "Iron & Wine",
+  "Kool & the Gang",
+  "Marina and the Diamonds",
+  "Matt and Kim",
+  "Mumford & Sons",
+  "Nick Cave & The Bad Seeds",
+  "Of Monsters and Men",
+  "Peter, Paul and Mary",
+  "Sam & Dave",
+  "Simon & Garfunkel",
+  "Tom Petty and the Heartbreakers",
+  "Tyler, The Creator",
+  "Years & Years",
+];
+
+/// Featuring credit inside a bracketed group, e.g. "Song (feat. A & B)"
+static BRACKETED_CREDIT: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r"(?i)\s*[(\[]\s*(?:feat\.?|ft\.?|featuring|with|w/|&)\s+(?P<names>[^)\]]+?)\s*[)\]]")
+    .unwrap()
+});
+
+/// Featuring credit without brackets, e.g. "Song - feat. A". The names end
+/// at a bracket or " - ", what follows is kept as `rest`
+static TRAILING_CREDIT: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r"(?i)\s*(?:-\s*)?\b(?:feat\.|ft\.|featuring\b)\s*(?P<names>.+?)(?P<rest>\s*[(\[].*|\s+-\s.*)?$").unwrap()
+});
+
+/// Separators between artist names in a single field
+static SEPARATOR: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"(?i)\s*(?:,|&|\sx\s|\svs\.?\s|\sand\s)\s*").unwrap());
+
+static WHITESPACE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+").unwrap());
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credits {
+  /// The title with any featuring credit removed
+  pub title: String,
+  /// Every credited artist, primary artists first, without duplicates
+  pub artists: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreditParser {
+  exceptions: Option<Regex>,
+}
+
+impl Default for CreditParser {
+  fn default() -> Self { Self::with_exceptions(DEFAULT_EXCEPTIONS) }
+}
+
+impl CreditParser {
+  pub fn with_exceptions<I, S>(exceptions: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+  {
+    let mut names = exceptions
+      .into_iter()
+      .map(|name| regex::escape(name.as_ref().trim()))
+      .filter(|name| !name.is_empty())
+      .collect::<Vec<_>>();
+
+    // Longest names first so "Crosby, Stills, Nash & Young" wins over
+    // "Crosby, Stills & Nash" style prefixes
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+
+    let exceptions = (!names.is_empty()).then(|| {
+      RegexBuilder::new(&format!(r"\b(?:{})\b", names.join("|")))
+        .case_insensitive(true)
+        .build()
+        .unwrap()
+    });
+
+    Self { exceptions }
+  }
+
+  /// Extracts every credited artist from the artist field and title, and
+  /// returns the title with the featuring credit removed.
+  pub fn parse(&self, artist: &str, title: &str) -> Credits {
+    let (primary, featured) = split_credit(artist);
+
+    let mut artists = self.split_artists(primary);
+    if let Some(featured) = featured {
+      artists.extend(self.split_artists(featured));
+    }
+
+    let mut title = title.to_string();
+    for credit in [&*BRACKETED_CREDIT, &*TRAILING_CREDIT] {
+      if let Some(captures) = credit.captures(&title) {
+        artists.extend(self.split_artists(&captures["names"]));
+        // `rest` is empty for bracketed credits
+        title = credit.replace(&title, "${rest}").into_owned();
+      }
+    }
+
+    Credits {
+      title: normalize_whitespace(&title),
+      artists: dedup(artists),
+    }
+  }
+
+  /// Splits an artist field such as "A, B & C" into its individual names,
+  /// keeping known band names intact.
+  pub fn split_artists(&self, field: &str) -> Vec<String> {
+    let protected = self
+      .exceptions
+      .as_ref()
+      .map(|exceptions| {
+        exceptions
+          .find_iter(field)
+          .map(|m| m.range())
+          .collect::<Vec<_>>()
+      })
+      .unwrap_or_default();
+
+    let mut names = vec![];
+    let mut start = 0;
+    for separator in SEPARATOR.find_iter(field) {
+      if protected
+        .iter()
+        .any(|range| separator.start() < range.end && range.start < separator.end())
+      {
+        continue;
+      }
+
+      names.push(&field[start..separator.start()]);
+      start = separator.end();
+    }
+    names.push(&field[start..]);
+
+    names
+      .into_iter()
+      .map(normalize_whitespace)
+      .filter(|name| !name.is_empty())
+      .collect()
+  }
+}
+
+/// Splits "A feat. B" into ("A", Some("B"))
+fn split_credit(field: &str) -> (&str, Option<&str>) {
+  match TRAILING_CREDIT.captures(field) {
+    Some(captures) => {
+      let credit = captures.get(0).unwrap();
+      (&field[..credit.start()], captures.name("names").map(|m| m.as_str()))
+    },
+    None => (field, None),
+  }
+}
+
+fn normalize_whitespace(value: &str) -> String {
+  WHITESPACE
+    .replace_all(value.trim(), " ")
+    .trim_end_matches(|c: char| c == '-' || c.is_whitespace())
+    .to_string()
+}
+
+fn dedup(artists: Vec<String>) -> Vec<String> {
+  let mut seen = vec![];
+  artists
+    .into_iter()
+    .filter(|artist| {
+      let key = artist.to_lowercase();
+      if seen.contains(&key) {
+        return false;
+      }
+      seen.push(key);
+      true
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_credits() {
+    let cases: &[(&str, &str, &str, &[&str])] = &[
+      // (artist, title, expected title, expected artists)
+      ("A", "Song", "Song", &["A"]),
+      ("A", "Song (feat. B)", "Song", &["A", "B"]),
+      ("A", "Song (Feat. B)", "Song", &["A", "B"]),
+      ("A", "Song (FEAT. B)", "Song", &["A", "B"]),
+      ("A", "Song (feat B)", "Song", &["A", "B"]),
+      ("A", "Song (ft. B)", "Song", &["A", "B"]),
+      ("A", "Song (ft B)", "Song", &["A", "B"]),
+      ("A", "Song (featuring B)", "Song", &["A", "B"]),
+      ("A", "Song (with B)", "Song", &["A", "B"]),
+      ("A", "Song (w/ B)", "Song", &["A", "B"]),
+      ("A", "Song (& B)", "Song", &["A", "B"]),
+      ("A", "Song [feat. B]", "Song", &["A", "B"]),
+      ("A", "Song (feat. B & C)", "Song", &["A", "B", "C"]),
+      ("A", "Song (feat. B, C & D)", "Song", &["A", "B", "C", "D"]),
+      ("A", "Song (feat. B and C)", "Song", &["A", "B", "C"]),
+      ("A", "Song (feat. B x C)", "Song", &["A", "B", "C"]),
+      ("A", "Song ( feat. B )", "Song", &["A", "B"]),
+      ("A", "Song - feat. B", "Song", &["A", "B"]),
+      ("A", "Song feat. B", "Song", &["A", "B"]),
+      ("A", "Song - feat. B (Remix)", "Song (Remix)", &["A", "B"]),
+      ("A", "Song feat. B & C [Live]", "Song [Live]", &["A", "B", "C"]),
+      ("A", "Song feat. B - Remix", "Song - Remix", &["A", "B"]),
+      ("A", "Song ft. B & C", "Song", &["A", "B", "C"]),
+      ("A", "Song featuring B", "Song", &["A", "B"]),
+      ("A", "Song (feat. B) - Remix", "Song - Remix", &["A", "B"]),
+      ("A", "Song (feat. B) [Live]", "Song [Live]", &["A", "B"]),
+      ("A, B", "Song", "Song", &["A", "B"]),
+      ("A & B", "Song", "Song", &["A", "B"]),
+      ("A, B & C", "Song", "Song", &["A", "B", "C"]),
+      ("A x B", "Song", "Song", &["A", "B"]),
+      ("A X B", "Song", "Song", &["A", "B"]),
+      ("A vs. B", "Song", "Song", &["A", "B"]),
+      ("A vs B", "Song", "Song", &["A", "B"]),
+      ("A and B", "Song", "Song", &["A", "B"]),
+      ("A feat. B", "Song", "Song", &["A", "B"]),
+      ("A ft. B & C", "Song", "Song", &["A", "B", "C"]),
+      ("A featuring B", "Song", "Song", &["A", "B"]),
+      ("A & B feat. C", "Song (feat. D)", "Song", &["A", "B", "C", "D"]),
+      ("A", "Song (feat. A)", "Song", &["A"]),
+      ("A", "Song (feat. a)", "Song", &["A"]),
+      ("A, B", "Song (feat. B & C)", "Song", &["A", "B", "C"]),
+      ("A,B", "Song", "Song", &["A", "B"]),
+      ("A ,  B", "Song", "Song", &["A", "B"]),
+      ("A", "Song (Remastered)", "Song (Remastered)", &["A"]),
+      ("A", "Defeated", "Defeated", &["A"]),
+      ("A", "No Small Feat", "No Small Feat", &["A"]),
+      ("A", "Soft Song (Acoustic)", "Soft Song (Acoustic)", &["A"]),
+      ("A", "Dancing with Myself", "Dancing with Myself", &["A"]),
+      ("Malcolm X", "Song", "Song", &["Malcolm X"]),
+      ("Ax Bx", "Song", "Song", &["Ax Bx"]),
+      ("Vanderbilt", "Song", "Song", &["Vanderbilt"]),
+      ("Simon & Garfunkel", "The Boxer", "The Boxer", &["Simon & Garfunkel"]),
+      ("simon & garfunkel", "The Boxer", "The Boxer", &["simon & garfunkel"]),
+      ("Earth, Wind & Fire", "September", "September", &["Earth, Wind & Fire"]),
+      ("Tyler, The Creator", "Song", "Song", &["Tyler, The Creator"]),
+      ("Mumford & Sons", "Song (feat. A & B)", "Song", &["Mumford & Sons", "A", "B"]),
+      ("A", "Song (with Simon & Garfunkel)", "Song", &["A", "Simon & Garfunkel"]),
+      ("Simon & Garfunkel, A", "Song", "Song", &["Simon & Garfunkel", "A"]),
+      ("A & Hall & Oates", "Song", "Song", &["A", "Hall & Oates"]),
+      ("Crosby, Stills, Nash & Young", "Song", "Song", &["Crosby, Stills, Nash & Young"]),
+      ("Of Monsters and Men", "Little Talks", "Little Talks", &["Of Monsters and Men"]),
+      ("", "Song", "Song", &[]),
+      ("A", "", "", &["A"]),
+    ];
+
+    let parser = CreditParser::default();
+    for (artist, title, expected_title, expected_artists) in cases {
+      let credits = parser.parse(artist, title);
+      assert_eq!(
+        credits,
+        Credits {
+          title: expected_title.to_string(),
+          artists: expected_artists.iter().map(|a| a.to_string()).collect(),
+        },
+        "artist: {artist:?}, title: {title:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn uses_custom_exceptions() {
+    let cases: &[(&[&str], &str, &[&str])] = &[
+      (&[], "Simon & Garfunkel", &["Simon", "Garfunkel"]),
+      (&["A & B"], "A & B", &["A & B"]),
+      (&["A & B"], "A & B & C", &["A & B", "C"]),
+      (&["A & B"], "Simon & Garfunkel", &["Simon", "Garfunkel"]),
+      (&["  "], "A & B", &["A", "B"]),
+      (&["A.B & C"], "A.B & C", &["A.B & C"]),
+    ];
+
+    for (exceptions, field, expected) in cases {
+      let parser = CreditParser::with_exceptions(exceptions.iter());
+      assert_eq!(parser.split_artists(field), *expected, "field: {field:?}");
+    }
+  }
+}
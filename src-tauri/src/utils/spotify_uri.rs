@@ -0,0 +1,247 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+const WEB_HOST: &str = "open.spotify.com";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum SpotifyUriKind {
+  Track,
+  Album,
+  Artist,
+  Playlist,
+  Show,
+  Episode,
+  User,
+}
+
+impl SpotifyUriKind {
+  fn as_str(&self) -> &'static str {
+    match self {
+      Self::Track => "track",
+      Self::Album => "album",
+      Self::Artist => "artist",
+      Self::Playlist => "playlist",
+      Self::Show => "show",
+      Self::Episode => "episode",
+      Self::User => "user",
+    }
+  }
+
+  fn parse(value: &str) -> Option<Self> {
+    Some(match value {
+      "track" => Self::Track,
+      "album" => Self::Album,
+      "artist" => Self::Artist,
+      "playlist" => Self::Playlist,
+      "show" => Self::Show,
+      "episode" => Self::Episode,
+      "user" => Self::User,
+      _ => return None,
+    })
+  }
+}
+
+/// A validated `spotify:<kind>:<id>` URI, which can also be parsed from and
+/// converted to an `open.spotify.com` URL.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotifyUri {
+  pub kind: SpotifyUriKind,
+  pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpotifyUriError {
+  UnknownFormat,
+  UnknownKind(String),
+  InvalidId(String),
+}
+
+impl fmt::Display for SpotifyUriError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::UnknownFormat => write!(f, "expected a 'spotify:' URI or an '{WEB_HOST}' URL"),
+      Self::UnknownKind(kind) => write!(f, "unsupported Spotify resource '{kind}'"),
+      Self::InvalidId(id) => write!(f, "invalid Spotify id '{id}'"),
+    }
+  }
+}
+
+impl std::error::Error for SpotifyUriError {}
+
+impl SpotifyUri {
+  pub fn new(kind: SpotifyUriKind, id: &str) -> Result<Self, SpotifyUriError> {
+    if !is_valid_id(kind, id) {
+      return Err(SpotifyUriError::InvalidId(id.into()));
+    }
+
+    Ok(Self { kind, id: id.into() })
+  }
+
+  /// `spotify:track:4uLU6hMCjMI75M1A2tKUQC`
+  pub fn to_uri(&self) -> String { format!("spotify:{}:{}", self.kind.as_str(), self.id) }
+
+  /// `https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC`
+  pub fn to_url(&self) -> String { format!("https://{WEB_HOST}/{}/{}", self.kind.as_str(), self.id) }
+
+  fn from_uri(uri: &str) -> Result<Self, SpotifyUriError> {
+    let mut parts = uri.split(':');
+    let (Some("spotify"), Some(kind), Some(id), None) =
+      (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+      return Err(SpotifyUriError::UnknownFormat);
+    };
+
+    Self::from_parts(kind, id)
+  }
+
+  fn from_url(url: &str) -> Result<Self, SpotifyUriError> {
+    let url = url
+      .strip_prefix("https://")
+      .or_else(|| url.strip_prefix("http://"))
+      .unwrap_or(url);
+
+    let Some(path) = url
+      .strip_prefix(WEB_HOST)
+      .filter(|path| path.is_empty() || path.starts_with('/'))
+    else {
+      return Err(SpotifyUriError::UnknownFormat);
+    };
+
+    // Drop the query string (e.g. `?si=...`) and fragment
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+
+    let mut segments = path
+      .split('/')
+      .filter(|segment| !segment.is_empty())
+      // Localized links, e.g. `/intl-de/track/...` or `/intl-pt_BR/track/...`
+      .skip_while(|segment| segment.starts_with("intl-"));
+
+    let (Some(kind), Some(id), None) = (segments.next(), segments.next(), segments.next()) else {
+      return Err(SpotifyUriError::UnknownFormat);
+    };
+
+    Self::from_parts(kind, id)
+  }
+
+  fn from_parts(kind: &str, id: &str) -> Result<Self, SpotifyUriError> {
+    let kind = SpotifyUriKind::parse(kind).ok_or_else(|| SpotifyUriError::UnknownKind(kind.into()))?;
+    Self::new(kind, id)
+  }
+}
+
+impl FromStr for SpotifyUri {
+  type Err = SpotifyUriError;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let value = value.trim();
+
+    if value.starts_with("spotify:") {
+      return Self::from_uri(value);
+    }
+
+    Self::from_url(value)
+  }
+}
+
+impl fmt::Display for SpotifyUri {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.to_uri()) }
+}
+
+/// Every id is base62, and everything except user ids is exactly 22 long
+fn is_valid_id(kind: SpotifyUriKind, id: &str) -> bool {
+  let is_base62 = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric());
+
+  match kind {
+    SpotifyUriKind::User => is_base62,
+    _ => is_base62 && id.len() == 22,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const ID: &str = "4uLU6hMCjMI75M1A2tKUQC";
+
+  #[test]
+  fn parses_uris_and_urls() {
+    let cases = [
+      ("spotify:track:4uLU6hMCjMI75M1A2tKUQC", SpotifyUriKind::Track, ID),
+      ("spotify:album:4uLU6hMCjMI75M1A2tKUQC", SpotifyUriKind::Album, ID),
+      ("spotify:artist:4uLU6hMCjMI75M1A2tKUQC", SpotifyUriKind::Artist, ID),
+      ("spotify:playlist:4uLU6hMCjMI75M1A2tKUQC", SpotifyUriKind::Playlist, ID),
+      ("spotify:show:4uLU6hMCjMI75M1A2tKUQC", SpotifyUriKind::Show, ID),
+      ("spotify:episode:4uLU6hMCjMI75M1A2tKUQC", SpotifyUriKind::Episode, ID),
+      ("spotify:user:jesse", SpotifyUriKind::User, "jesse"),
+      ("  spotify:track:4uLU6hMCjMI75M1A2tKUQC  ", SpotifyUriKind::Track, ID),
+      ("https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC", SpotifyUriKind::Track, ID),
+      ("http://open.spotify.com/album/4uLU6hMCjMI75M1A2tKUQC", SpotifyUriKind::Album, ID),
+      ("open.spotify.com/artist/4uLU6hMCjMI75M1A2tKUQC", SpotifyUriKind::Artist, ID),
+      ("https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC/", SpotifyUriKind::Track, ID),
+      (
+        "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC?si=a1b2c3d4e5f6",
+        SpotifyUriKind::Track,
+        ID,
+      ),
+      (
+        "https://open.spotify.com/intl-de/track/4uLU6hMCjMI75M1A2tKUQC?si=a1b2",
+        SpotifyUriKind::Track,
+        ID,
+      ),
+      (
+        "https://open.spotify.com/intl-pt_BR/playlist/4uLU6hMCjMI75M1A2tKUQC",
+        SpotifyUriKind::Playlist,
+        ID,
+      ),
+      ("https://open.spotify.com/user/jesse", SpotifyUriKind::User, "jesse"),
+    ];
+
+    for (input, kind, id) in cases {
+      assert_eq!(
+        input.parse::<SpotifyUri>(),
+        Ok(SpotifyUri { kind, id: id.into() }),
+        "input: {input:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn rejects_malformed_input() {
+    let cases = [
+      ("", SpotifyUriError::UnknownFormat),
+      ("spotify:", SpotifyUriError::UnknownFormat),
+      ("spotify:track", SpotifyUriError::UnknownFormat),
+      ("spotify:track:4uLU6hMCjMI75M1A2tKUQC:extra", SpotifyUriError::UnknownFormat),
+      ("spotify:song:4uLU6hMCjMI75M1A2tKUQC", SpotifyUriError::UnknownKind("song".into())),
+      ("spotify:track:short", SpotifyUriError::InvalidId("short".into())),
+      ("spotify:track:4uLU6hMCjMI75M1A2tKU-C", SpotifyUriError::InvalidId("4uLU6hMCjMI75M1A2tKU-C".into())),
+      ("spotify:user:", SpotifyUriError::InvalidId("".into())),
+      ("https://example.com/track/4uLU6hMCjMI75M1A2tKUQC", SpotifyUriError::UnknownFormat),
+      ("https://open.spotify.com/", SpotifyUriError::UnknownFormat),
+      ("https://open.spotify.com/track", SpotifyUriError::UnknownFormat),
+      ("https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC/extra", SpotifyUriError::UnknownFormat),
+      ("https://open.spotify.com/genre/4uLU6hMCjMI75M1A2tKUQC", SpotifyUriError::UnknownKind("genre".into())),
+      ("https://open.spotify.com.evil.com/track/4uLU6hMCjMI75M1A2tKUQC", SpotifyUriError::UnknownFormat),
+      ("calc.exe", SpotifyUriError::UnknownFormat),
+    ];
+
+    for (input, error) in cases {
+      assert_eq!(input.parse::<SpotifyUri>(), Err(error), "input: {input:?}");
+    }
+  }
+
+  #[test]
+  fn converts_between_forms() {
+    let uri = SpotifyUri::new(SpotifyUriKind::Episode, ID).unwrap();
+
+    assert_eq!(uri.to_uri(), format!("spotify:episode:{ID}"));
+    assert_eq!(uri.to_url(), format!("https://open.spotify.com/episode/{ID}"));
+    assert_eq!(uri.to_string(), uri.to_uri());
+    assert_eq!(uri.to_url().parse::<SpotifyUri>(), Ok(uri.clone()));
+    assert_eq!(uri.to_uri().parse::<SpotifyUri>(), Ok(uri));
+  }
+}
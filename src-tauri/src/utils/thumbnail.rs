@@ -7,10 +7,12 @@ use windows::core::{Error as WindowsError, HRESULT};
 use windows::Security::Cryptography::CryptographicBuffer;
 use windows::Storage::Streams::{Buffer, IRandomAccessStreamReference, InputStreamOptions};
 
+use crate::settings::schema::CropRect;
 use crate::utils::color::get_color_palette;
 
 pub async fn get_thumbnail_data(
   stream_reference: Result<IRandomAccessStreamReference, WindowsError>,
+  crop: Option<CropRect>,
 ) -> windows::core::Result<(Option<Palette>, AverageColor, String)> {
   println!("get_thumbnail_base64");
 
@@ -30,8 +32,10 @@ pub async fn get_thumbnail_data(
 
   match load_from_memory(bytes.as_bytes()) {
     Ok(mut image) => {
-      /* Crop the Spotify watermark out of the image */
-      let image = image.crop(34, 1, 233, 233);
+      /* Crop watermarks, like Spotify's, out of the image */
+      if let Some(CropRect { x, y, width, height }) = crop {
+        image = image.crop(x, y, width, height);
+      }
 
       /* Write the image to a buffer, which is then encoded into a base64 string */
       let mut buf = vec![];
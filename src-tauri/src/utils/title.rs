@@ -0,0 +1,200 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Trailing " - Suffix", as used by Spotify, e.g. "Song - 2011 Remaster"
+static DASH_SUFFIX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+-\s+(?P<tag>[^-]+?)\s*$").unwrap());
+
+/// Trailing bracketed group, e.g. "Song (Live at X)"
+static BRACKETED_SUFFIX: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"\s*[(\[](?P<tag>[^()\[\]]+)[)\]]\s*$").unwrap());
+
+static REMASTER: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(
+    r"(?i)^(?:(?P<before>\d{4})\s+)?(?:digital(?:ly)?\s+)?remaster(?:ed)?(?:\s+(?:version|edition))?(?:\s+(?P<after>\d{4}))?(?:\s+(?:version|edition))?$",
+  )
+  .unwrap()
+});
+static LIVE: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r"(?i)^live(?:\s+version)?(?:\s+(?:at|from|in)\s+(?P<venue>.+))?$").unwrap()
+});
+static EDIT: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"(?i)^(?:(?P<kind>.+?)\s+)?edit(?:\s+version)?$").unwrap());
+/// "Kygo Remix" and "Kygo's Extended Remix" name the remixer, "Extended
+/// Remix" only describes the mix
+static REMIX: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(
+    r"(?i)^(?:(?P<before>.+?)(?:['’]s)?\s+)??(?:(?:extended|radio|club|original|dub|vip|official|short|long)\s+)*remix(?:ed\s+by\s+(?P<after>.+))?$",
+  )
+  .unwrap()
+});
+static ACOUSTIC: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"(?i)^acoustic(?:\s+(?:version|mix))?$").unwrap());
+static INSTRUMENTAL: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"(?i)^instrumental(?:\s+version)?$").unwrap());
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum VersionTag {
+  Remaster { year: Option<u16> },
+  Live { venue: Option<String> },
+  Edit { name: Option<String> },
+  Remix { remixer: Option<String> },
+  Acoustic,
+  Instrumental,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizedTitle {
+  /// The title without any version suffixes
+  pub base_title: String,
+  /// Version tags in the order they appeared in the title
+  pub tags: Vec<VersionTag>,
+}
+
+/// Strips trailing remaster/live/edit/remix/acoustic/instrumental suffixes off
+/// a title and returns them as structured tags.
+pub fn normalize_title(title: &str) -> NormalizedTitle {
+  let mut base_title = title.trim().to_string();
+  let mut tags = vec![];
+
+  'strip: loop {
+    for suffix in [&*DASH_SUFFIX, &*BRACKETED_SUFFIX] {
+      let Some(captures) = suffix.captures(&base_title) else {
+        continue;
+      };
+
+      let Some(tag) = parse_tag(captures["tag"].trim()) else {
+        continue;
+      };
+
+      let start = captures.get(0).unwrap().start();
+      // Never strip the whole title, e.g. a song called "(Live)"
+      if base_title[..start].trim().is_empty() {
+        continue;
+      }
+
+      tags.insert(0, tag);
+      base_title.truncate(start);
+      continue 'strip;
+    }
+
+    break;
+  }
+
+  NormalizedTitle {
+    base_title: base_title.trim().to_string(),
+    tags,
+  }
+}
+
+fn parse_tag(tag: &str) -> Option<VersionTag> {
+  let named = |captures: &regex::Captures, names: &[&str]| {
+    names
+      .iter()
+      .find_map(|name| captures.name(name))
+      .map(|m| m.as_str().trim().to_string())
+  };
+
+  if let Some(captures) = REMASTER.captures(tag) {
+    return Some(VersionTag::Remaster {
+      year: named(&captures, &["before", "after"]).and_then(|year| year.parse().ok()),
+    });
+  }
+
+  if let Some(captures) = LIVE.captures(tag) {
+    return Some(VersionTag::Live {
+      venue: named(&captures, &["venue"]),
+    });
+  }
+
+  if let Some(captures) = REMIX.captures(tag) {
+    return Some(VersionTag::Remix {
+      remixer: named(&captures, &["before", "after"]),
+    });
+  }
+
+  if let Some(captures) = EDIT.captures(tag) {
+    return Some(VersionTag::Edit {
+      name: named(&captures, &["kind"]).map(|kind| format!("{kind} Edit")),
+    });
+  }
+
+  if ACOUSTIC.is_match(tag) {
+    return Some(VersionTag::Acoustic);
+  }
+
+  if INSTRUMENTAL.is_match(tag) {
+    return Some(VersionTag::Instrumental);
+  }
+
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use VersionTag::*;
+
+  #[test]
+  fn normalizes_titles() {
+    let some = |value: &str| Some(value.to_string());
+    let cases = vec![
+      ("Song", "Song", vec![]),
+      ("Song - 2011 Remaster", "Song", vec![Remaster { year: Some(2011) }]),
+      ("Song - Remastered 2009", "Song", vec![Remaster { year: Some(2009) }]),
+      ("Song - Remastered", "Song", vec![Remaster { year: None }]),
+      ("Song - 2015 Digital Remaster", "Song", vec![Remaster { year: Some(2015) }]),
+      ("Song (Remastered Version)", "Song", vec![Remaster { year: None }]),
+      ("Song [2001 Remaster]", "Song", vec![Remaster { year: Some(2001) }]),
+      ("Song (Live)", "Song", vec![Live { venue: None }]),
+      ("Song - Live", "Song", vec![Live { venue: None }]),
+      ("Song (Live at Wembley)", "Song", vec![Live { venue: some("Wembley") }]),
+      ("Song - Live From Austin, TX", "Song", vec![Live { venue: some("Austin, TX") }]),
+      ("Song - Radio Edit", "Song", vec![Edit { name: some("Radio Edit") }]),
+      ("Song (Single Edit)", "Song", vec![Edit { name: some("Single Edit") }]),
+      ("Song - Edit", "Song", vec![Edit { name: None }]),
+      ("Song - Remix", "Song", vec![Remix { remixer: None }]),
+      ("Song - Kygo Remix", "Song", vec![Remix { remixer: some("Kygo") }]),
+      ("Song (Remixed by Kygo)", "Song", vec![Remix { remixer: some("Kygo") }]),
+      ("Song (Extended Remix)", "Song", vec![Remix { remixer: None }]),
+      ("Song - Radio Club Remix", "Song", vec![Remix { remixer: None }]),
+      ("Song - Kygo Extended Remix", "Song", vec![Remix { remixer: some("Kygo") }]),
+      ("Song (Kygo's Remix)", "Song", vec![Remix { remixer: some("Kygo") }]),
+      ("Song - Acoustic", "Song", vec![Acoustic]),
+      ("Song (Acoustic Version)", "Song", vec![Acoustic]),
+      ("Song - Instrumental", "Song", vec![Instrumental]),
+      (
+        "Song (Live) - 2011 Remaster",
+        "Song",
+        vec![Live { venue: None }, Remaster { year: Some(2011) }],
+      ),
+      (
+        "Song - Acoustic (Instrumental)",
+        "Song",
+        vec![Acoustic, Instrumental],
+      ),
+      ("Song - Part 2", "Song - Part 2", vec![]),
+      ("Song (Interlude)", "Song (Interlude)", vec![]),
+      ("Song (Live) (Interlude)", "Song (Live) (Interlude)", vec![]),
+      ("Live", "Live", vec![]),
+      ("(Live)", "(Live)", vec![]),
+      ("Live Forever", "Live Forever", vec![]),
+      ("Remix", "Remix", vec![]),
+      ("Anti-Hero", "Anti-Hero", vec![]),
+      ("  Song  ", "Song", vec![]),
+    ];
+
+    for (title, base_title, tags) in cases {
+      assert_eq!(
+        normalize_title(title),
+        NormalizedTitle {
+          base_title: base_title.to_string(),
+          tags,
+        },
+        "title: {title:?}"
+      );
+    }
+  }
+}
@@ -0,0 +1,16 @@
+//! Waiting on background tasks in tests, by what they do rather than how
+//! long they usually take.
+
+use std::time::Duration;
+
+/// Polls `condition` until it holds, failing the test if that takes more
+/// than a few seconds
+pub async fn until(mut condition: impl FnMut() -> bool) {
+  tokio::time::timeout(Duration::from_secs(5), async {
+    while !condition() {
+      tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+  })
+  .await
+  .expect("Timed out waiting for a background task");
+}
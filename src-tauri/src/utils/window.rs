@@ -12,4 +12,14 @@ pub fn apply_window_blur(window: &Window) {
 	#[cfg(target_os = "windows")]
 	apply_blur(window, None)
 		.expect("Unsupported platform! 'apply_blur' is only supported on Windows");
-}
\ No newline at end of file
+}
+
+/// Hides the widget when it's showing, otherwise brings it to the front
+pub fn toggle_window(window: &Window) -> tauri::Result<()> {
+  if window.is_visible()? {
+    return window.hide();
+  }
+
+  window.show()?;
+  window.set_focus()
+}
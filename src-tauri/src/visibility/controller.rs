@@ -0,0 +1,144 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, watch, Notify};
+use tokio::task::JoinHandle;
+
+use super::rules::{Visibility, VisibilityState};
+use crate::media::lib::MediaEvent;
+use crate::settings::schema::Settings;
+use crate::utils::time::now_ms;
+
+/// How often we look for fullscreen windows
+const FULLSCREEN_POLL: Duration = Duration::from_secs(1);
+
+/// Tells whether a fullscreen window is on the widget's monitor
+pub trait FullscreenDetector: Send + Sync {
+  fn is_covered(&self) -> bool;
+}
+
+/// Evaluates the visibility rules against the player's events and
+/// publishes whether the widget should be shown
+pub struct VisibilityController {
+  visibility: watch::Receiver<Visibility>,
+  /// Only available once the window exists
+  detector: Arc<Mutex<Option<Arc<dyn FullscreenDetector>>>>,
+  /// Wakes the task up to check the newly attached detector
+  attached: Arc<Notify>,
+  task: JoinHandle<()>,
+}
+
+impl VisibilityController {
+  /// `has_session` tells whether the player is already there, events only
+  /// report it connecting or going away later
+  pub fn spawn(
+    mut events: broadcast::Receiver<MediaEvent>,
+    mut settings: watch::Receiver<Settings>,
+    has_session: bool,
+  ) -> Self {
+    println!("[VisibilityController] spawn");
+
+    let mut state = VisibilityState::new(has_session);
+    let (sender, visibility) = watch::channel(state.evaluate(&settings.borrow().visibility, now_ms()));
+    let detector: Arc<Mutex<Option<Arc<dyn FullscreenDetector>>>> = Arc::new(Mutex::new(None));
+    let attached = Arc::new(Notify::new());
+
+    let task = tokio::spawn({
+      let detector = detector.clone();
+      let attached = attached.clone();
+
+      async move {
+        loop {
+          let rules = settings.borrow().visibility.clone();
+          let next_change = state.next_change_in(&rules, now_ms());
+          let poll = rules.hide_in_fullscreen && detector.lock().unwrap().is_some();
+
+          tokio::select! {
+            event = events.recv() => match event {
+              Ok(event) => state.handle(&event, now_ms()),
+              Err(RecvError::Lagged(_)) => continue,
+              Err(RecvError::Closed) => break,
+            },
+            changed = settings.changed() => if changed.is_err() { break },
+            _ = attached.notified() => {},
+            _ = tokio::time::sleep(next_change.unwrap_or_default()), if next_change.is_some() => {},
+            _ = tokio::time::sleep(FULLSCREEN_POLL), if poll => {},
+          }
+
+          let rules = settings.borrow().visibility.clone();
+          let detector = detector.lock().unwrap().clone();
+          state.set_fullscreen(rules.hide_in_fullscreen && detector.is_some_and(|detector| detector.is_covered()));
+
+          let visibility = state.evaluate(&rules, now_ms());
+          sender.send_if_modified(|current| {
+            if *current == visibility {
+              return false;
+            }
+
+            *current = visibility;
+            true
+          });
+        }
+      }
+    });
+
+    Self {
+      visibility,
+      detector,
+      attached,
+      task,
+    }
+  }
+
+  pub fn attach(&self, detector: Arc<dyn FullscreenDetector>) {
+    *self.detector.lock().unwrap() = Some(detector);
+    self.attached.notify_one();
+  }
+
+  pub fn visibility(&self) -> Visibility { *self.visibility.borrow() }
+
+  pub fn subscribe(&self) -> watch::Receiver<Visibility> { self.visibility.clone() }
+
+  pub fn arced(self) -> Arc<Self> { Arc::new(self) }
+}
+
+impl Drop for VisibilityController {
+  fn drop(&mut self) { self.task.abort(); }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::visibility::rules::HideReason;
+
+  struct AlwaysCovered;
+
+  impl FullscreenDetector for AlwaysCovered {
+    fn is_covered(&self) -> bool { true }
+  }
+
+  async fn next(visibility: &mut watch::Receiver<Visibility>) -> Visibility {
+    tokio::time::timeout(Duration::from_secs(1), visibility.changed()).await.unwrap().unwrap();
+    *visibility.borrow()
+  }
+
+  #[tokio::test]
+  async fn publishes_changes() {
+    let (sender, events) = broadcast::channel(16);
+    let (settings, settings_receiver) = watch::channel(Settings::default());
+    let controller = VisibilityController::spawn(events, settings_receiver, false);
+    let mut visibility = controller.subscribe();
+    assert_eq!(visibility.borrow().reason, Some(HideReason::Disconnected));
+
+    sender.send(MediaEvent::Connect("Spotify.exe".into())).unwrap();
+    assert_eq!(next(&mut visibility).await, Visibility::shown());
+
+    // Noticed without waiting for another event
+    controller.attach(Arc::new(AlwaysCovered));
+    assert_eq!(next(&mut visibility).await.reason, Some(HideReason::Fullscreen));
+
+    settings.send_modify(|settings| settings.visibility.hide_in_fullscreen = false);
+    assert_eq!(next(&mut visibility).await, Visibility::shown());
+  }
+}
@@ -0,0 +1,60 @@
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::Graphics::Gdi::{
+  GetMonitorInfoW,
+  MonitorFromWindow,
+  MONITORINFO,
+  MONITOR_DEFAULTTONEAREST,
+  MONITOR_DEFAULTTONULL,
+};
+use windows::Win32::UI::WindowsAndMessaging::{GetClassNameW, GetForegroundWindow, GetWindowRect};
+
+use super::controller::FullscreenDetector;
+
+/// The desktop's windows span the whole monitor but aren't fullscreen apps
+const DESKTOP_CLASSES: [&str; 2] = ["Progman", "WorkerW"];
+
+/// Looks for a foreground window that covers the whole monitor the widget
+/// is on, like games, videos and presentations
+pub struct ForegroundFullscreen {
+  widget: HWND,
+}
+
+impl ForegroundFullscreen {
+  pub fn new(widget: isize) -> Self { Self { widget: HWND(widget) } }
+}
+
+impl FullscreenDetector for ForegroundFullscreen {
+  fn is_covered(&self) -> bool {
+    unsafe {
+      let foreground = GetForegroundWindow();
+      if foreground.0 == 0 || foreground == self.widget || is_desktop(foreground) {
+        return false;
+      }
+
+      let monitor = MonitorFromWindow(foreground, MONITOR_DEFAULTTONULL);
+      if monitor.is_invalid() || monitor != MonitorFromWindow(self.widget, MONITOR_DEFAULTTONEAREST) {
+        return false;
+      }
+
+      let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+      };
+      let mut window = RECT::default();
+      if !GetMonitorInfoW(monitor, &mut info).as_bool() || !GetWindowRect(foreground, &mut window).as_bool() {
+        return false;
+      }
+
+      let screen = info.rcMonitor;
+      window.left <= screen.left && window.top <= screen.top && window.right >= screen.right && window.bottom >= screen.bottom
+    }
+  }
+}
+
+unsafe fn is_desktop(window: HWND) -> bool {
+  let mut class = [0u16; 32];
+  let length = GetClassNameW(window, &mut class);
+  let class = String::from_utf16_lossy(&class[..length.max(0) as usize]);
+
+  DESKTOP_CLASSES.contains(&class.as_str())
+}
@@ -0,0 +1,5 @@
+pub mod controller;
+#[cfg(windows)]
+pub mod fullscreen;
+pub mod rules;
+pub mod window;
\ No newline at end of file
@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::media::lib::MediaEvent;
+use crate::settings::schema::VisibilitySettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum HideReason {
+  Paused,
+  Disconnected,
+  Fullscreen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, rspc::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Visibility {
+  pub visible: bool,
+  /// Why the widget is hidden, the first rule that applies wins
+  pub reason: Option<HideReason>,
+}
+
+impl Visibility {
+  pub fn shown() -> Self {
+    Self {
+      visible: true,
+      reason: None,
+    }
+  }
+
+  fn hidden(reason: HideReason) -> Self {
+    Self {
+      visible: false,
+      reason: Some(reason),
+    }
+  }
+}
+
+/// What the visibility rules are evaluated against
+#[derive(Debug, Clone, Default)]
+pub struct VisibilityState {
+  /// When playback was paused, while it is
+  paused_at: Option<i64>,
+  disconnected: bool,
+  fullscreen: bool,
+}
+
+impl VisibilityState {
+  /// Starts out disconnected unless the player already has a session
+  pub fn new(has_session: bool) -> Self {
+    Self {
+      disconnected: !has_session,
+      ..Default::default()
+    }
+  }
+
+  pub fn handle(&mut self, event: &MediaEvent, now: i64) {
+    match event {
+      MediaEvent::Connect(_) => {
+        self.disconnected = false;
+        self.paused_at = None;
+      },
+      MediaEvent::Disconnect(_) => {
+        self.disconnected = true;
+        self.paused_at = None;
+      },
+      MediaEvent::PlaybackInfoChanged(data) if data.is_playing => self.paused_at = None,
+      MediaEvent::PlaybackInfoChanged(_) => {
+        self.paused_at.get_or_insert(now);
+      },
+      _ => {},
+    }
+  }
+
+  pub fn set_fullscreen(&mut self, fullscreen: bool) { self.fullscreen = fullscreen; }
+
+  pub fn evaluate(&self, rules: &VisibilitySettings, now: i64) -> Visibility {
+    if rules.hide_in_fullscreen && self.fullscreen {
+      return Visibility::hidden(HideReason::Fullscreen);
+    }
+
+    if rules.hide_on_disconnect && self.disconnected {
+      return Visibility::hidden(HideReason::Disconnected);
+    }
+
+    if self.paused_for(rules, now).is_some_and(|remaining| remaining.is_zero()) {
+      return Visibility::hidden(HideReason::Paused);
+    }
+
+    Visibility::shown()
+  }
+
+  /// How long until the pause rule hides the widget
+  pub fn next_change_in(&self, rules: &VisibilitySettings, now: i64) -> Option<Duration> {
+    self.paused_for(rules, now).filter(|remaining| !remaining.is_zero())
+  }
+
+  fn paused_for(&self, rules: &VisibilitySettings, now: i64) -> Option<Duration> {
+    let hide_after = rules.hide_when_paused_secs? as i64 * 1000;
+    let paused_ms = now - self.paused_at?;
+
+    Some(Duration::from_millis((hide_after - paused_ms).max(0) as u64))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::media::lib::MediaPlaybackData;
+
+  fn playing(is_playing: bool) -> MediaEvent { MediaEvent::PlaybackInfoChanged(MediaPlaybackData { is_playing }) }
+
+  #[test]
+  fn hides_after_a_long_pause() {
+    let rules = VisibilitySettings {
+      hide_when_paused_secs: Some(30),
+      ..VisibilitySettings::default()
+    };
+    let mut state = VisibilityState::new(true);
+
+    state.handle(&playing(false), 1_000);
+    // Repeated updates while paused don't restart the countdown
+    state.handle(&playing(false), 11_000);
+    assert_eq!(state.evaluate(&rules, 21_000), Visibility::shown());
+    assert_eq!(state.next_change_in(&rules, 21_000), Some(Duration::from_secs(10)));
+
+    assert_eq!(state.evaluate(&rules, 31_000), Visibility::hidden(HideReason::Paused));
+    assert_eq!(state.next_change_in(&rules, 31_000), None);
+
+    state.handle(&playing(true), 40_000);
+    assert_eq!(state.evaluate(&rules, 40_000), Visibility::shown());
+
+    // Off by default
+    state.handle(&playing(false), 50_000);
+    assert_eq!(state.evaluate(&VisibilitySettings::default(), 500_000), Visibility::shown());
+  }
+
+  #[test]
+  fn hides_until_the_player_is_back() {
+    let rules = VisibilitySettings::default();
+    let mut state = VisibilityState::new(true);
+
+    state.handle(&MediaEvent::Disconnect("Spotify.exe".into()), 0);
+    assert_eq!(state.evaluate(&rules, 0), Visibility::hidden(HideReason::Disconnected));
+
+    let kept = VisibilitySettings {
+      hide_on_disconnect: false,
+      ..rules.clone()
+    };
+    assert_eq!(state.evaluate(&kept, 0), Visibility::shown());
+
+    state.handle(&MediaEvent::Connect("Spotify.exe".into()), 0);
+    assert_eq!(state.evaluate(&rules, 0), Visibility::shown());
+  }
+
+  #[test]
+  fn fullscreen_wins() {
+    let rules = VisibilitySettings::default();
+    let mut state = VisibilityState::new(true);
+
+    state.handle(&MediaEvent::Disconnect("Spotify.exe".into()), 0);
+    state.set_fullscreen(true);
+    assert_eq!(state.evaluate(&rules, 0), Visibility::hidden(HideReason::Fullscreen));
+
+    state.set_fullscreen(false);
+    state.handle(&MediaEvent::Connect("Spotify.exe".into()), 0);
+    state.handle(&playing(true), 0);
+    assert_eq!(state.evaluate(&rules, 0), Visibility::shown());
+  }
+
+  #[test]
+  fn hides_without_a_session_at_startup() {
+    let rules = VisibilitySettings::default();
+
+    assert_eq!(VisibilityState::new(false).evaluate(&rules, 0), Visibility::hidden(HideReason::Disconnected));
+    assert_eq!(VisibilityState::new(true).evaluate(&rules, 0), Visibility::shown());
+  }
+}
@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use tauri::{Runtime, Window};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::rules::Visibility;
+
+/// Gives the UI time to fade out before the window goes away
+const FADE_OUT: Duration = Duration::from_millis(300);
+
+/// Shows and hides the window as the visibility rules say
+pub fn follow_visibility<R: Runtime>(window: Window<R>, mut visibility: watch::Receiver<Visibility>) -> JoinHandle<()> {
+  tokio::spawn(async move {
+    while visibility.changed().await.is_ok() {
+      let result = if visibility.borrow_and_update().visible {
+        window.show()
+      } else {
+        tokio::time::sleep(FADE_OUT).await;
+
+        // Shown again while fading out, handled on the next round
+        if visibility.borrow().visible {
+          continue;
+        }
+        window.hide()
+      };
+
+      if let Err(err) = result {
+        println!("[Visibility] Error: {:?}", err);
+      }
+    }
+  })
+}
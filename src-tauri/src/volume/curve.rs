@@ -0,0 +1,86 @@
+use crate::settings::schema::{VolumeCurve, VolumeSettings};
+
+/// Where `level` sits on the curve, steps and fades move evenly along it.
+/// Loudness is heard roughly as the cube root of the amplitude, so the
+/// perceptual curve takes smaller steps near silence.
+fn to_curve(level: f32, curve: VolumeCurve) -> f32 {
+  match curve {
+    VolumeCurve::Linear => level,
+    VolumeCurve::Perceptual => level.cbrt(),
+  }
+}
+
+fn from_curve(position: f32, curve: VolumeCurve) -> f32 {
+  match curve {
+    VolumeCurve::Linear => position,
+    VolumeCurve::Perceptual => position.powi(3),
+  }
+}
+
+/// Keeps `level` between silence and the configured maximum
+pub fn capped(level: f32, settings: &VolumeSettings) -> f32 { level.clamp(0.0, settings.max_percent as f32 / 100.0) }
+
+/// `steps` steps up, or down when negative, from `level`
+pub fn stepped(level: f32, steps: i32, settings: &VolumeSettings) -> f32 {
+  let step = settings.step_percent as f32 / 100.0;
+  let position = to_curve(level.clamp(0.0, 1.0), settings.curve) + steps as f32 * step;
+
+  capped(from_curve(position.clamp(0.0, 1.0), settings.curve), settings)
+}
+
+/// The level `progress` (0 to 1) of the way from `from` to `to`
+pub fn faded(from: f32, to: f32, progress: f32, settings: &VolumeSettings) -> f32 {
+  let (from, to) = (to_curve(from.clamp(0.0, 1.0), settings.curve), to_curve(to.clamp(0.0, 1.0), settings.curve));
+
+  capped(from_curve(from + (to - from) * progress.clamp(0.0, 1.0), settings.curve), settings)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn settings(curve: VolumeCurve, max_percent: u32) -> VolumeSettings {
+    VolumeSettings {
+      step_percent: 10,
+      curve,
+      max_percent,
+    }
+  }
+
+  fn close(a: f32, b: f32) -> bool { (a - b).abs() < 1e-4 }
+
+  #[test]
+  fn steps_along_the_curve() {
+    let linear = settings(VolumeCurve::Linear, 100);
+    assert!(close(stepped(0.5, 1, &linear), 0.6));
+    assert!(close(stepped(0.5, -2, &linear), 0.3));
+    assert_eq!(stepped(0.95, 1, &linear), 1.0);
+    assert_eq!(stepped(0.05, -1, &linear), 0.0);
+
+    let perceptual = settings(VolumeCurve::Perceptual, 100);
+    assert!(close(stepped(0.5, 1, &perceptual), (0.5_f32.cbrt() + 0.1).powi(3)));
+    assert_eq!(stepped(0.001, -1, &perceptual), 0.0);
+    // Near silence a step is smaller than a linear one, 0.1 to ~0.048
+    assert!(close(stepped(0.1, -1, &perceptual), (0.1_f32.cbrt() - 0.1).powi(3)));
+  }
+
+  #[test]
+  fn never_goes_past_the_cap() {
+    let capped_at = settings(VolumeCurve::Linear, 80);
+
+    assert!(close(stepped(0.75, 1, &capped_at), 0.8));
+    assert!(close(faded(0.2, 1.0, 1.0, &capped_at), 0.8));
+    assert!(close(capped(0.9, &capped_at), 0.8));
+  }
+
+  #[test]
+  fn fades_between_levels() {
+    let linear = settings(VolumeCurve::Linear, 100);
+    assert!(close(faded(0.2, 0.6, 0.5, &linear), 0.4));
+    assert!(close(faded(0.6, 0.2, 1.0, &linear), 0.2));
+
+    let perceptual = settings(VolumeCurve::Perceptual, 100);
+    assert!(close(faded(0.0, 1.0, 0.5, &perceptual), 0.125));
+    assert!(close(faded(1.0, 0.0, 0.0, &perceptual), 1.0));
+  }
+}
@@ -0,0 +1,59 @@
+/// Muting sets the volume to 0 and remembers the level it was at, so
+/// unmuting can put it back
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MuteState {
+  /// Some while muted
+  muted_from: Option<f32>,
+}
+
+impl MuteState {
+  pub fn new() -> Self { Self::default() }
+
+  pub fn is_muted(&self) -> bool { self.muted_from.is_some() }
+
+  /// The level to set, `None` when already muted
+  pub fn mute(&mut self, current: f32) -> Option<f32> {
+    if self.is_muted() {
+      return None;
+    }
+
+    self.muted_from = Some(current);
+    Some(0.0)
+  }
+
+  /// The level to go back to, `None` when not muted
+  pub fn unmute(&mut self) -> Option<f32> { self.muted_from.take() }
+
+  /// Follows the volume as it's reported, anything above 0 (e.g. from the
+  /// system mixer) unmutes. Returns whether it's muted.
+  pub fn observe(&mut self, level: f32) -> bool {
+    if level > 0.0 {
+      self.muted_from = None;
+    }
+
+    self.is_muted()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn remembers_the_previous_level() {
+    let mut state = MuteState::new();
+    assert_eq!(state.unmute(), None);
+
+    assert_eq!(state.mute(0.6), Some(0.0));
+    assert_eq!(state.mute(0.0), None);
+    assert!(state.observe(0.0));
+
+    assert_eq!(state.unmute(), Some(0.6));
+    assert!(!state.is_muted());
+
+    // Raised somewhere else while muted
+    state.mute(0.4);
+    assert!(!state.observe(0.3));
+    assert_eq!(state.unmute(), None);
+  }
+}
@@ -1,11 +1,19 @@
-import { useEffect, useState } from 'react';
+import { useEffect, useRef, useState } from 'react';
 
+import { Lyrics } from './components/Lyrics';
+import { SleepTimer } from './components/SleepTimer';
 import { Thumbnail } from './components/Thumbnail';
 import { Timeline } from './components/Timeline';
 import { UriLink } from './components/UriLink';
 import { VolumeSlider, VolumeSliderSkeletonLoader } from './components/VolumeSlider';
 
-import { MediaPlaybackData, MediaSessionData, MediaTimelineData } from './utils/bindings';
+import {
+	DisplayMode,
+	MediaPlaybackData,
+	MediaSessionData,
+	MediaTimelineData,
+	Settings
+} from './utils/bindings';
 import { clsx } from './utils/clsx';
 import { resetTheme, updateTheme } from './utils/color';
 import { rspc } from './utils/rspc';
@@ -35,11 +43,26 @@ const LoadingSkeleton = () => {
 
 function App() {
 	const { mutate: invokeMediaProperties } = rspc.useMutation('media.invokeMediaProperties');
+	const { mutate: setHovered } = rspc.useMutation('window.setHovered');
 
 	const [metadata, setMetadata] = useState<MediaSessionData | null>();
 	const [playbackData, setPlaybackData] = useState<MediaPlaybackData | null>();
 	const [timelineData, setTimelineData] = useState<MediaTimelineData | null>();
 	const [trackData, setTrackData] = useState<SpotifySearchResult | null>();
+	// Read from subscription callbacks, which keep the first render's closure
+	const settings = useRef<Settings>();
+
+	// Keeps a change that arrived before the query answered
+	rspc.useQuery(['settings.get'], { onSuccess: (data) => (settings.current ??= data) });
+	rspc.useSubscription(['settings.changed'], { onData: (data) => (settings.current = data) });
+
+	// The backend resizes the window, this only picks the layout
+	const [mode, setMode] = useState<DisplayMode>('compact');
+	rspc.useSubscription(['window.modeChanged'], { onData: setMode });
+
+	// Fades out before the backend hides the window
+	const [visible, setVisible] = useState(true);
+	rspc.useSubscription(['visibility.changed'], { onData: (data) => setVisible(data.visible) });
 
 	rspc.useSubscription(['media.mediaPropertiesChanged'], {
 		onData: async (data) => {
@@ -66,11 +89,11 @@ function App() {
 	rspc.useSubscription(['media.timelinePropertiesChanged'], { onData: setTimelineData });
 	rspc.useSubscription(['media.sessionChanged'], {
 		onData: (data) => {
-			if (data.appId === 'Spotify.exe' && !data.sessionActive) {
+			if (settings.current?.player.appIds.includes(data.appId) && !data.sessionActive) {
 				setMetadata(null);
 				setPlaybackData(null);
 				setTimelineData(null);
-				resetTheme();
+				resetTheme(settings.current.appearance.fallbackColor);
 			}
 		}
 	});
@@ -81,63 +104,110 @@ function App() {
 
 	const hasSession = !!metadata && !!playbackData && !!timelineData;
 
+	const trackInfo = hasSession && (
+		<>
+			<UriLink
+				className="-my-[6px] -mx-px px-px text-base font-medium"
+				uri={trackData?.tracks?.items[0]?.album.uri ?? ''}
+			>
+				{metadata.title}
+			</UriLink>
+			<div className="flex gap-1">
+				{metadata.artists.map((artist) => (
+					<UriLink
+						key={artist}
+						className="text-xs leading-5 opacity-90 [&:not(:last-child)]:after:content-[',']"
+						uri={getArtistInfo(artist, trackData)?.uri ?? ''}
+					>
+						{artist}
+					</UriLink>
+				))}
+				<SleepTimer />
+			</div>
+		</>
+	);
+
 	return (
 		<div
 			data-tauri-drag-region
+			onMouseEnter={() => setHovered(true)}
+			onMouseLeave={() => setHovered(false)}
 			className={clsx(
-				'h-screen w-full select-none overflow-hidden rounded-[calc(6px+2px)] border border-theme-700/80 bg-theme-800/80 p-[2px] font-satoshi text-theme-200 transition-colors'
+				'h-screen w-full select-none overflow-hidden rounded-[calc(6px+2px)] border border-theme-700/80 bg-theme-800/80 p-[2px] font-satoshi text-theme-200 transition',
+				{ 'opacity-0': !visible }
 			)}
 		>
-			<div className="pointer-events-none flex h-full max-h-full">
-				{/* Thumbnail */}
-				<Thumbnail
-					src={metadata?.thumbnail.base64}
-					isPlaying={playbackData?.isPlaying}
-					loading={!hasSession}
-				/>
-
-				<div className="z-10 flex min-w-0 flex-1 gap-1 py-2 px-3">
-					{!hasSession ? (
-						<LoadingSkeleton />
-					) : (
-						<>
-							<div className="flex h-full min-w-0 flex-1 flex-col">
-								{/* Metadata */}
-								<div className="flex-grow overflow-hidden">
-									<>
-										<UriLink
-											className="-my-[6px] -mx-px px-px text-base font-medium"
-											uri={trackData?.tracks?.items[0]?.album.uri ?? ''}
-										>
-											{metadata.title}
-										</UriLink>
-										<div className="flex gap-1">
-											{metadata.artists.map((artist) => (
-												<UriLink
-													key={artist}
-													className="text-xs leading-5 opacity-90 [&:not(:last-child)]:after:content-[',']"
-													uri={getArtistInfo(artist, trackData)?.uri ?? ''}
-												>
-													{artist}
-												</UriLink>
-											))}
+			{mode === 'expanded' ? (
+				<div className="pointer-events-none flex h-full max-h-full flex-col gap-2">
+					<div className="flex h-40 shrink-0">
+						{/* Thumbnail */}
+						<Thumbnail
+							src={metadata?.thumbnail.base64}
+							isPlaying={playbackData?.isPlaying}
+							loading={!hasSession}
+							large
+						/>
+
+						<div className="z-10 flex min-w-0 flex-1 gap-1 py-2 px-3">
+							{!hasSession ? (
+								<LoadingSkeleton />
+							) : (
+								<>
+									{/* Metadata */}
+									<div className="min-w-0 flex-1 overflow-hidden">{trackInfo}</div>
+
+									{/* Volume Slider */}
+									<span className="pointer-events-auto pl-2">
+										<VolumeSlider />
+									</span>
+								</>
+							)}
+						</div>
+					</div>
+
+					{/* Lyrics */}
+					<Lyrics className="flex min-h-0 flex-1 items-center justify-center px-3" />
+
+					{hasSession && (
+						<div className="relative px-3 pb-2">
+							<Timeline data={timelineData} isPlaying={playbackData?.isPlaying} />
+						</div>
+					)}
+				</div>
+			) : (
+				<div className="pointer-events-none flex h-full max-h-full">
+					{/* Thumbnail */}
+					<Thumbnail
+						src={metadata?.thumbnail.base64}
+						isPlaying={playbackData?.isPlaying}
+						loading={!hasSession}
+					/>
+
+					{mode !== 'mini' && (
+						<div className="z-10 flex min-w-0 flex-1 gap-1 py-2 px-3">
+							{!hasSession ? (
+								<LoadingSkeleton />
+							) : (
+								<>
+									<div className="flex h-full min-w-0 flex-1 flex-col">
+										{/* Metadata */}
+										<div className="flex-grow overflow-hidden">{trackInfo}</div>
+
+										<div className="relative">
+											<Timeline data={timelineData} isPlaying={playbackData?.isPlaying} />
 										</div>
-									</>
-								</div>
-
-								<div className="relative">
-									<Timeline data={timelineData} isPlaying={playbackData?.isPlaying} />
-								</div>
-							</div>
-
-							{/* Volume Slider */}
-							<span className="pointer-events-auto pl-2">
-								<VolumeSlider />
-							</span>
-						</>
+									</div>
+
+									{/* Volume Slider */}
+									<span className="pointer-events-auto pl-2">
+										<VolumeSlider />
+									</span>
+								</>
+							)}
+						</div>
 					)}
 				</div>
-			</div>
+			)}
 		</div>
 	);
 }
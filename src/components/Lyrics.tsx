@@ -0,0 +1,27 @@
+import { FC, useState } from 'react';
+import { CurrentLine } from '../utils/bindings';
+import { clsx } from '../utils/clsx';
+import { rspc } from '../utils/rspc';
+
+type Props = {
+	className?: string;
+};
+
+/** The line being sung right now, blank for tracks without synced lyrics */
+export const Lyrics: FC<Props> = ({ className }) => {
+	const [line, setLine] = useState<CurrentLine | null>(null);
+	rspc.useSubscription(['lyrics.currentLine'], { onData: setLine });
+
+	return (
+		<p
+			key={line?.index}
+			className={clsx(
+				'max-h-full overflow-hidden text-center text-sm font-medium leading-5 text-theme-100 transition-opacity',
+				className,
+				{ 'opacity-0': !line?.text }
+			)}
+		>
+			{line?.text}
+		</p>
+	);
+};
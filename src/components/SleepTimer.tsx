@@ -0,0 +1,39 @@
+import { useState } from 'react';
+import { TimerStatus } from '../utils/bindings';
+import { clsx } from '../utils/clsx';
+import { rspc } from '../utils/rspc';
+import { formatTime } from '../utils/time';
+
+const TICKS_PER_SECOND = 10_000_000;
+
+const describe = (status: TimerStatus) => {
+	if (status.remainingSecs !== null) {
+		return formatTime(status.remainingSecs * TICKS_PER_SECOND);
+	}
+
+	return `${status.tracksLeft} tracks`;
+};
+
+/** Counts down a running sleep timer, clicking it cancels the timer */
+export const SleepTimer = () => {
+	const { mutate: cancel } = rspc.useMutation('timer.cancel');
+
+	const [status, setStatus] = useState<TimerStatus | null>(null);
+	rspc.useSubscription(['timer.countdown'], { onData: setStatus });
+
+	if (!status) {
+		return null;
+	}
+
+	return (
+		<button
+			title="Cancel the sleep timer"
+			className={clsx('pointer-events-auto ml-auto shrink-0 text-xs leading-5 opacity-70 hover:opacity-100', {
+				'animate-pulse': status.fading
+			})}
+			onClick={() => cancel(undefined)}
+		>
+			☾ {describe(status)}
+		</button>
+	);
+};
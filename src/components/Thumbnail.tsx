@@ -15,12 +15,19 @@ type Props = {
 	src: string | undefined;
 	loading?: boolean;
 	isPlaying?: boolean;
+	/** Square and as tall as its container, for the expanded mode */
+	large?: boolean;
 };
 
-const ThumbnailImage: FC<Props> = ({ src }) => {
+const ThumbnailImage: FC<Props> = ({ src, large }) => {
 	if (!src?.length || src === undefined) {
 		return (
-			<span className="absolute inset-0 w-20 bg-gradient-to-br from-spotify-thumbnail-green/40 to-spotify-thumbnail-blue/40 p-px">
+			<span
+				className={clsx(
+					'absolute inset-0 bg-gradient-to-br from-spotify-thumbnail-green/40 to-spotify-thumbnail-blue/40 p-px',
+					large ? 'w-full' : 'w-20'
+				)}
+			>
 				<span className="flex h-full w-full items-center justify-center rounded-[5px] bg-gradient-to-br from-spotify-thumbnail-blue to-spotify-thumbnail-green">
 					<MusicalNoteIcon className="h-1/2 w-1/2" />
 				</span>
@@ -29,11 +36,15 @@ const ThumbnailImage: FC<Props> = ({ src }) => {
 	}
 
 	return (
-		<img className="h-full rounded-[5px]" draggable={false} src={`data:image/png;base64,${src}`} />
+		<img
+			className={clsx('h-full rounded-[5px]', { 'w-full object-cover': large })}
+			draggable={false}
+			src={`data:image/png;base64,${src}`}
+		/>
 	);
 };
 
-export const Thumbnail: FC<Props> = ({ src, loading = true, isPlaying = false }) => {
+export const Thumbnail: FC<Props> = ({ src, loading = true, isPlaying = false, large = false }) => {
 	const { mutate: invokeMediaMethod } = rspc.useMutation('media.invokeMethod');
 
 	const [isHovering, setIsHovering] = useState(false);
@@ -86,13 +97,15 @@ export const Thumbnail: FC<Props> = ({ src, loading = true, isPlaying = false })
 			onMouseEnter={handleMouseEnter}
 			onMouseLeave={handleMouseLeave}
 			className={clsx(
-				'group pointer-events-auto relative z-0 h-full w-[78px] self-center overflow-hidden rounded-md border border-theme-700',
+				'group pointer-events-auto relative z-0 h-full self-center overflow-hidden rounded-md border border-theme-700',
 				{
+					'w-[78px]': !large,
+					'aspect-square': large,
 					'border-none': loading || !hasThumbnail
 				}
 			)}
 		>
-			<ThumbnailImage src={src} />
+			<ThumbnailImage src={src} large={large} />
 
 			{/* Buttons */}
 			{!loading && (
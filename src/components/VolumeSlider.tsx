@@ -1,10 +1,9 @@
 import { useRef, useState } from 'react';
 import { useMouseWheel } from '../hooks/useMouseWheel';
+import { clsx } from '../utils/clsx';
 import { rspc } from '../utils/rspc';
 import { Slider } from './base/Slider';
 
-const STEP = 5 / 100;
-
 export const VolumeSliderSkeletonLoader = () => {
 	return <span className="block h-full w-2 animate-pulse rounded-full bg-theme-700 duration-200" />;
 };
@@ -14,44 +13,64 @@ export const VolumeSlider = () => {
 
 	const ref = useRef<HTMLSpanElement | null>(null);
 
-	const [volume, setVolume] = useState(0);
+	// `null` until the player's audio session shows up
+	const [volume, setVolume] = useState<number | null>(null);
+	const [muted, setMuted] = useState(false);
+	// Same steps as the mouse wheel and hotkeys
+	const [stepPercent, setStepPercent] = useState(5);
 
 	useMouseWheel(
 		ref,
 		{
-			// delta: 100 || -100
+			// delta: 100 || -100, the backend steps along the configured curve
 			onChange: (delta) => {
-				commitValue(volume + STEP * (delta / -Math.abs(delta)));
+				invokeMethod({ stepVolume: delta / -Math.abs(delta) });
 			}
 		},
-		[volume]
+		[]
 	);
 
+	rspc.useQuery(['settings.get'], { onSuccess: (data) => setStepPercent(data.volume.stepPercent) });
+	rspc.useSubscription(['settings.changed'], { onData: (data) => setStepPercent(data.volume.stepPercent) });
+
 	rspc.useQuery(['media.getVolume'], { onSuccess: setVolume });
-	rspc.useSubscription(['media.volumeChanged'], { onData: setVolume });
+	rspc.useSubscription(['media.volumeChanged'], {
+		onData: (data) => {
+			setVolume(data.level);
+			setMuted(data.muted);
+		}
+	});
+	rspc.useSubscription(['media.sessionChanged'], {
+		onData: (data) => {
+			if (!data.sessionActive) {
+				setVolume(null);
+				setMuted(false);
+			}
+		}
+	});
 
 	const handleValueChange = async (value: number) => {
-		commitValue(value);
-	};
-
-	const commitValue = async (value: number) => {
 		const volume = Math.max(0, Math.min(1, value));
 		setVolume(volume);
 		invokeMethod({ setVolume: volume });
 	};
 
-	if (volume < 0) {
+	if (volume === null) {
 		return <VolumeSliderSkeletonLoader />;
 	}
 
 	return (
-		<div className="relative h-full w-1">
+		<div
+			className={clsx('relative h-full w-1 transition-opacity', { 'opacity-50': muted })}
+			title={muted ? 'Muted, double-click to unmute' : 'Double-click to mute'}
+			onDoubleClick={() => invokeMethod('toggleMute')}
+		>
 			<Slider
 				ref={ref}
 				value={[volume]}
 				max={1}
 				className="absolute left-1/2 -translate-x-1/2"
-				step={STEP}
+				step={stepPercent / 100}
 				onValueChange={(value) => handleValueChange(value[0])}
 				orientation="vertical"
 			/>
@@ -1,18 +1,13 @@
-import { useEffect } from 'react';
+import { useState } from 'react';
 import { rspc } from './../utils/rspc';
 
 // 'navigator.onLine' does not work with tauri...
 export const useIsOnline = () => {
-	const { data: isOnline, refetch } = rspc.useQuery(['network.status']);
+	const [isOnline, setIsOnline] = useState<boolean>();
 
-	useEffect(() => {
-		refetch();
-		const timer = setInterval(() => refetch(), 1000);
-
-		return () => {
-			clearInterval(timer);
-		};
-	}, []);
+	rspc.useSubscription(['network.statusChanged'], {
+		onData: (status) => setIsOnline(status.connectivity !== 'offline')
+	});
 
 	return isOnline;
 };
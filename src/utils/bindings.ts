@@ -2,34 +2,134 @@
 
 export type Procedures = {
     queries: 
-        { key: "media.getVolume", input: never, result: number } | 
-        { key: "network.status", input: never, result: boolean },
+        { key: "history.recent", input: HistoryRange, result: Array<Play> } | 
+        { key: "history.topArtists", input: HistoryRange, result: Array<ArtistStats> } | 
+        { key: "history.topTracks", input: HistoryRange, result: Array<TrackStats> } | 
+        { key: "media.getVolume", input: never, result: number | null } | 
+        { key: "network.status", input: never, result: boolean } | 
+        { key: "settings.get", input: never, result: Settings } | 
+        { key: "spotify.parseUri", input: string, result: SpotifyUri } | 
+        { key: "timer.remaining", input: never, result: TimerStatus | null } | 
+        { key: "visibility.status", input: never, result: Visibility } | 
+        { key: "window.mode", input: never, result: DisplayMode },
     mutations: 
         { key: "media.invokeMediaProperties", input: never, result: null } | 
         { key: "media.invokeMethod", input: Method, result: null } | 
         { key: "media.invokePlaybackInfo", input: never, result: null } | 
         { key: "media.invokeTimelineProperties", input: never, result: null } | 
-        { key: "spotify.invokeUri", input: string, result: null },
+        { key: "settings.hotkeys", input: Array<Hotkey>, result: Array<Hotkey> } | 
+        { key: "settings.update", input: Settings, result: Settings } | 
+        { key: "spotify.invokeUri", input: string, result: null } | 
+        { key: "timer.cancel", input: never, result: null } | 
+        { key: "timer.start", input: SleepAfter, result: TimerStatus } | 
+        { key: "window.setHovered", input: boolean, result: DisplayMode } | 
+        { key: "window.setMode", input: DisplayMode, result: DisplayMode },
     subscriptions: 
+        { key: "lyrics.currentLine", input: never, result: CurrentLine | null } | 
         { key: "media.mediaPropertiesChanged", input: never, result: MediaSessionData } | 
         { key: "media.playbackInfoChanged", input: never, result: MediaPlaybackData } | 
         { key: "media.sessionChanged", input: never, result: SessionChangedData } | 
         { key: "media.timelinePropertiesChanged", input: never, result: MediaTimelineData } | 
-        { key: "media.volumeChanged", input: never, result: number }
+        { key: "media.volumeChanged", input: never, result: MediaVolumeData } | 
+        { key: "network.statusChanged", input: never, result: NetworkStatus } | 
+        { key: "settings.changed", input: never, result: Settings } | 
+        { key: "timer.countdown", input: never, result: TimerStatus | null } | 
+        { key: "visibility.changed", input: never, result: Visibility } | 
+        { key: "window.modeChanged", input: never, result: DisplayMode }
 };
 
+export interface AppearanceSettings { fallbackColor: [number, number, number] }
+
+export interface ArtistStats { artist: string, plays: number, listenedMs: number }
+
+export type Connectivity = "unknown" | "online" | "degraded" | "offline"
+
+export interface CreditSettings { exceptions: Array<string> }
+
+export interface CropRect { x: number, y: number, width: number, height: number }
+
+export interface CurrentLine { index: number, text: string, startMs: number, endMs: number | null, words: Array<LyricsWord> }
+
+export interface DiscordSettings { clientId: string, largeImage: string | null, playingImage: string | null, pausedImage: string | null }
+
+export type DisplayMode = "mini" | "compact" | "expanded"
+
+export type DuckMode = "lower" | "pause"
+
+export interface DuckingSettings { enabled: boolean, mode: DuckMode, lowerToPercent: number, allow: Array<string>, deny: Array<string>, restoreDelaySecs: number }
+
+export type HideReason = "paused" | "disconnected" | "fullscreen"
+
+export interface HistoryRange { from: number | null, to: number | null, limit: number | null }
+
+export interface Hotkey { accelerator: string, action: HotkeyAction }
+
+export type HotkeyAction = { kind: "playPause" } | { kind: "next" } | { kind: "previous" } | { kind: "seek", seconds: number } | { kind: "volume", steps: number } | { kind: "toggleMute" } | { kind: "toggleWindow" }
+
+export interface LyricsSettings { directories: Array<string> }
+
+export interface LyricsWord { startMs: number, text: string }
+
 export interface MediaPlaybackData { isPlaying: boolean }
 
-export interface MediaSessionData { isPlayEnabled: boolean, isPauseEnabled: boolean, isPlayOrPauseEnabled: boolean, isPreviousEnabled: boolean, isNextEnabled: boolean, title: string, artists: Array<string>, album: string, thumbnail: ThumbnailData }
+export interface MediaSessionData { isPlayEnabled: boolean, isPauseEnabled: boolean, isPlayOrPauseEnabled: boolean, isPreviousEnabled: boolean, isNextEnabled: boolean, title: string, normalizedTitle: NormalizedTitle, artists: Array<string>, album: string, thumbnail: ThumbnailData }
 
 export interface MediaTimelineData { timelineStartTime: number, timelineEndTime: number, timelinePosition: number }
 
-export type Method = "play" | "pause" | "next" | "previous" | { setPlaybackPosition: number } | { setVolume: number }
+export interface MediaVolumeData { level: number, muted: boolean }
+
+export type Method = "play" | "pause" | "next" | "previous" | { setPlaybackPosition: number } | { setVolume: number } | { setMuted: boolean } | "toggleMute" | { stepVolume: number } | { fadeVolume: { volume: number, durationMs: number } }
+
+export interface NetworkSettings { probeTargets: Array<ProbeTarget> }
+
+export interface NetworkStatus { connectivity: Connectivity, latencyMs: number | null }
+
+export interface NormalizedTitle { baseTitle: string, tags: Array<VersionTag> }
+
+export interface NotificationSettings { enabled: boolean, apps: Record<string, boolean>, debounceMs: number }
+
+export interface NowPlayingSettings { directory: string, templates: Record<string, string>, coverArt: string | null }
+
+export interface Play { appId: string, title: string, artists: Array<string>, album: string, startedAt: number, listenedMs: number, durationMs: number | null, status: PlayStatus }
+
+export type PlayStatus = "completed" | "skipped"
+
+export interface PlayerSettings { appIds: Array<string>, coverCrop: CropRect | null }
+
+export type ProbeTarget = { kind: "tcp", target: string } | { kind: "dns", target: string } | { kind: "http", target: string }
 
 export interface SessionChangedData { appId: string, sessionActive: boolean }
 
+export interface Settings { version: number, window: WindowSettings, appearance: AppearanceSettings, player: PlayerSettings, visibility: VisibilitySettings, volume: VolumeSettings, sleepTimer: SleepTimerSettings, ducking: DuckingSettings, autostart: boolean, hotkeys: Array<Hotkey>, lyrics: LyricsSettings, notifications: NotificationSettings, nowPlaying: NowPlayingSettings | null, discord: DiscordSettings | null, credits: CreditSettings, network: NetworkSettings }
+
+export type SleepAfter = { kind: "duration", seconds: number } | { kind: "endOfTrack" } | { kind: "afterNTracks", tracks: number }
+
+export interface SleepTimerSettings { fadeSecs: number }
+
+export type SnapMode = "off" | "edges" | "corners"
+
+export interface SpotifyUri { kind: SpotifyUriKind, id: string }
+
+export type SpotifyUriKind = "track" | "album" | "artist" | "playlist" | "show" | "episode" | "user"
+
 export interface TailwindPalette { shades: Array<TailwindShade> }
 
 export interface TailwindShade { number: string, hexcode: string, rgb: [number, number, number] }
 
 export interface ThumbnailData { base64: string, palette: TailwindPalette, prominantColor: [number, number, number], averageColor: [number, number, number] }
+
+export interface TimerStatus { after: SleepAfter, remainingSecs: number | null, tracksLeft: number | null, fading: boolean }
+
+export interface TrackStats { title: string, artists: Array<string>, album: string, plays: number, listenedMs: number }
+
+export type VersionTag = { kind: "remaster", year: number | null } | { kind: "live", venue: string | null } | { kind: "edit", name: string | null } | { kind: "remix", remixer: string | null } | { kind: "acoustic" } | { kind: "instrumental" }
+
+export interface Visibility { visible: boolean, reason: HideReason | null }
+
+export interface VisibilitySettings { hideWhenPausedSecs: number | null, hideOnDisconnect: boolean, hideInFullscreen: boolean }
+
+export type VolumeCurve = "linear" | "perceptual"
+
+export interface VolumeSettings { stepPercent: number, curve: VolumeCurve, maxPercent: number }
+
+export interface WindowSettings { width: number, height: number, expandedHeight: number, mode: DisplayMode, autoExpand: boolean, snap: SnapMode, snapDistance: number }
@@ -25,4 +25,5 @@ export const updateTheme = (
 	document.documentElement.style.setProperty('--theme-average', `${averageColor.join(' ')}`);
 };
 
-export const resetTheme = () => updateTheme();
+export const resetTheme = (fallbackColor?: [number, number, number]) =>
+	updateTheme(defaultShades, fallbackColor, fallbackColor);